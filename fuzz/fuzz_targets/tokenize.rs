@@ -0,0 +1,9 @@
+#![no_main]
+
+use libfuzzer_sys::fuzz_target;
+use sklang::lexer::Lexer;
+
+// The lexer must never panic, whatever it is given
+fuzz_target!(|source: &str| {
+    let _ = Lexer::new(source).tokenize();
+});
@@ -0,0 +1,74 @@
+use super::{error::Result, Token, TokenCategory, TokenType};
+
+/// Adapters for iterators of lexed tokens, such as a [Lexer](super::Lexer), that chain with the
+/// std iterator methods. Errors are always passed through untouched.
+pub trait TokenIteratorExt<S>: Iterator<Item = Result<Token<S>>> + Sized {
+    /// Drops trivia, the tokens that carry no meaning for a parser, i.e. comments. Whitespace
+    /// never becomes a token so there is nothing else to drop.
+    fn without_trivia(self) -> impl Iterator<Item = Result<Token<S>>> {
+        self.filter(
+            |token| !matches!(token, Ok(token) if token.ttype.category() == TokenCategory::Comment),
+        )
+    }
+
+    /// Maps every token to its [TokenType], dropping its position
+    fn kinds(self) -> impl Iterator<Item = Result<TokenType<S>>> {
+        self.map(|token| token.map(|token| token.ttype))
+    }
+}
+
+impl<S, I: Iterator<Item = Result<Token<S>>>> TokenIteratorExt<S> for I {}
+
+#[cfg(test)]
+mod tests {
+    use crate::lexer::{error::LexerError, Lexer};
+
+    use super::*;
+
+    const MIXED: &str = "// header\nvar x = ~; // trailing\nx";
+
+    #[test]
+    fn without_trivia_drops_comments_only() {
+        let kinds: Vec<_> = Lexer::new(MIXED).without_trivia().kinds().collect();
+
+        assert_eq!(
+            kinds,
+            vec![
+                Ok(TokenType::Var),
+                Ok(TokenType::Identifier("x".into())),
+                Ok(TokenType::Equal),
+                Err(LexerError::UnknownCharacter {
+                    line: 2,
+                    col: 9,
+                    offset: 18,
+                    character: '~',
+                }),
+                Ok(TokenType::Semicolon),
+                Ok(TokenType::Identifier("x".into())),
+                Ok(TokenType::Eof),
+            ]
+        );
+    }
+
+    #[test]
+    fn kinds_keeps_trivia_and_composes_with_std_adapters() {
+        let comments = Lexer::new(MIXED)
+            .kinds()
+            .filter(|kind| matches!(kind, Ok(TokenType::Comment(_))))
+            .count();
+        assert_eq!(comments, 2);
+
+        let owned: Vec<TokenType> = Lexer::new(MIXED)
+            .tokenize()
+            .into_iter()
+            .without_trivia()
+            .kinds()
+            .take(2)
+            .collect::<Result<_>>()
+            .expect("the first tokens should lex");
+        assert_eq!(
+            owned,
+            vec![TokenType::Var, TokenType::Identifier("x".to_owned())]
+        );
+    }
+}
@@ -0,0 +1,157 @@
+use super::{Token, TokenType};
+
+#[derive(Debug, PartialEq)]
+pub enum BracketError {
+    /// A closing bracket that does not match the innermost open bracket, e.g. the `)` in `([)]`
+    Mismatched {
+        line: usize,
+        col: usize,
+        expected: char,
+        got: char,
+    },
+    /// A closing bracket with no open bracket left to close
+    Unopened { line: usize, col: usize, got: char },
+    /// An open bracket that is never closed, positioned at the open bracket
+    Unclosed { line: usize, col: usize, open: char },
+}
+
+impl std::fmt::Display for BracketError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Mismatched {
+                line,
+                col,
+                expected,
+                got,
+            } => write!(
+                f,
+                "[line {line}: {col}] Mismatched bracket '{got}', expected '{expected}'"
+            ),
+            Self::Unopened { line, col, got } => {
+                write!(f, "[line {line}: {col}] Unexpected closing bracket '{got}'")
+            }
+            Self::Unclosed { line, col, open } => {
+                write!(f, "[line {line}: {col}] Unclosed bracket '{open}'")
+            }
+        }
+    }
+}
+
+impl std::error::Error for BracketError {}
+
+/// Checks that every `(`, `[` and `{` is closed by its matching bracket in the right order,
+/// reporting the first bracket that breaks the nesting
+pub fn check_brackets<S>(tokens: &[Token<S>]) -> Result<(), BracketError> {
+    let mut open: Vec<(char, &Token<S>)> = Vec::new();
+
+    for token in tokens {
+        match token.ttype {
+            TokenType::LeftParen => open.push((')', token)),
+            TokenType::LeftBracket => open.push((']', token)),
+            TokenType::LeftBrace => open.push(('}', token)),
+            TokenType::RightParen | TokenType::RightBracket | TokenType::RightBrace => {
+                let got = closing_char(&token.ttype);
+
+                match open.pop() {
+                    Some((expected, _)) if expected == got => {}
+                    Some((expected, _)) => {
+                        return Err(BracketError::Mismatched {
+                            line: token.line,
+                            col: token.col,
+                            expected,
+                            got,
+                        })
+                    }
+                    None => {
+                        return Err(BracketError::Unopened {
+                            line: token.line,
+                            col: token.col,
+                            got,
+                        })
+                    }
+                }
+            }
+            _ => {}
+        }
+    }
+
+    // The outermost unclosed bracket is the one that needs fixing
+    match open.first() {
+        Some((closing, token)) => Err(BracketError::Unclosed {
+            line: token.line,
+            col: token.col,
+            open: opening_char(*closing),
+        }),
+        None => Ok(()),
+    }
+}
+
+fn closing_char<S>(ttype: &TokenType<S>) -> char {
+    match ttype {
+        TokenType::RightParen => ')',
+        TokenType::RightBracket => ']',
+        _ => '}',
+    }
+}
+
+fn opening_char(closing: char) -> char {
+    match closing {
+        ')' => '(',
+        ']' => '[',
+        _ => '{',
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::parser::test_tokens;
+
+    use super::*;
+
+    fn check(source: &str) -> Result<(), BracketError> {
+        check_brackets(&test_tokens(source))
+    }
+
+    #[test]
+    fn balanced() {
+        assert_eq!(check("fn f(a: int) { g([a, (1)]); }"), Ok(()));
+        assert_eq!(check(""), Ok(()));
+    }
+
+    #[test]
+    fn interleaved() {
+        assert_eq!(
+            check("([)]"),
+            Err(BracketError::Mismatched {
+                line: 1,
+                col: 3,
+                expected: ']',
+                got: ')',
+            })
+        );
+    }
+
+    #[test]
+    fn unclosed() {
+        assert_eq!(
+            check("fn f() {\n    g();\n"),
+            Err(BracketError::Unclosed {
+                line: 1,
+                col: 8,
+                open: '{',
+            })
+        );
+    }
+
+    #[test]
+    fn extra_closing() {
+        assert_eq!(
+            check("f());"),
+            Err(BracketError::Unopened {
+                line: 1,
+                col: 4,
+                got: ')',
+            })
+        );
+    }
+}
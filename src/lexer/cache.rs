@@ -0,0 +1,343 @@
+use serde_json::{json, Map, Value};
+
+use crate::span::Span;
+
+use super::{FloatSuffix, IntSuffix, Lexer, StringPart, Token, TokenType};
+
+/// Version of the format written by [serialize_tokens], bumped whenever a change to it would
+/// make older payloads decode differently.
+///
+/// Version 2 added the suffixed, interpolated and `Eof` shapes. Every version 1 payload is also a
+/// valid version 2 payload, so both are read.
+pub const TOKEN_SCHEMA_VERSION: u64 = 2;
+
+#[derive(Debug)]
+pub enum DeserializeError {
+    Json(serde_json::Error),
+    /// The payload was written with a schema version this crate does not read
+    UnsupportedVersion {
+        found: u64,
+        expected: u64,
+    },
+    /// The payload is valid JSON but does not follow the schema, e.g. a missing field
+    Malformed(String),
+}
+
+impl std::fmt::Display for DeserializeError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Json(error) => write!(f, "Invalid JSON: {error}"),
+            Self::UnsupportedVersion { found, expected } => write!(
+                f,
+                "Unsupported token schema version {found}, expected {expected}"
+            ),
+            Self::Malformed(message) => write!(f, "Malformed token payload: {message}"),
+        }
+    }
+}
+
+impl std::error::Error for DeserializeError {}
+
+impl From<serde_json::Error> for DeserializeError {
+    fn from(error: serde_json::Error) -> Self {
+        Self::Json(error)
+    }
+}
+
+/// Serializes tokens into a JSON payload that later versions of this crate can still read.
+///
+/// The payload is `{"version": 2, "tokens": [...]}`. Literals and identifiers are written as
+/// `{"integer": 1}`, `{"decimal": 1.5}`, `{"char": "c"}`, `{"string": "s"}` or
+/// `{"identifier": "x"}`, suffixed literals as `{"integer": 42, "suffix": "u8"}` or
+/// `{"decimal": 2.5, "suffix": "f32"}`, interpolated strings as
+/// `{"interpolated": [{"literal": "a "}, {"expr": "b"}]}`, the end of the file as `{"eof": true}`
+/// and every other token as its source spelling, e.g. `{"text": "+="}`.
+/// Every token also has `line`, `col` and `span: [start, end]`. Nothing depends on the order
+/// or names of [TokenType]'s variants, so reordering or renaming them keeps old caches valid.
+pub fn serialize_tokens(tokens: &[Token]) -> String {
+    let tokens: Vec<Value> = tokens
+        .iter()
+        .map(|token| {
+            let mut object = match &token.ttype {
+                TokenType::Integer(n) => json!({ "integer": n }),
+                TokenType::NegativeInteger(n) => json!({ "negative_integer": n }),
+                TokenType::Decimal(n) => json!({ "decimal": n }),
+                TokenType::SuffixedInteger { value, suffix } => {
+                    json!({ "integer": value, "suffix": suffix.to_string() })
+                }
+                TokenType::SuffixedDecimal { value, suffix } => {
+                    json!({ "decimal": value, "suffix": suffix.to_string() })
+                }
+                TokenType::InterpolatedString(parts) => {
+                    let parts: Vec<Value> = parts
+                        .iter()
+                        .map(|part| match part {
+                            StringPart::Literal(literal) => json!({ "literal": literal }),
+                            StringPart::Expr(expr) => json!({ "expr": expr }),
+                        })
+                        .collect();
+                    json!({ "interpolated": parts })
+                }
+                TokenType::Eof => json!({ "eof": true }),
+                TokenType::Character(c) => json!({ "char": c }),
+                TokenType::String(string) => json!({ "string": string }),
+                TokenType::Identifier(identifier) => json!({ "identifier": identifier }),
+                ttype => json!({ "text": ttype.to_string() }),
+            };
+            object["line"] = json!(token.line);
+            object["col"] = json!(token.col);
+            object["span"] = json!([token.span.start, token.span.end]);
+
+            object
+        })
+        .collect();
+
+    json!({ "version": TOKEN_SCHEMA_VERSION, "tokens": tokens }).to_string()
+}
+
+/// Reads tokens written by [serialize_tokens], rejecting payloads of any other schema version
+pub fn deserialize_tokens(payload: &str) -> Result<Vec<Token>, DeserializeError> {
+    let payload: Value = serde_json::from_str(payload)?;
+
+    let version = payload["version"]
+        .as_u64()
+        .ok_or_else(|| malformed("missing version"))?;
+    if !(1..=TOKEN_SCHEMA_VERSION).contains(&version) {
+        return Err(DeserializeError::UnsupportedVersion {
+            found: version,
+            expected: TOKEN_SCHEMA_VERSION,
+        });
+    }
+
+    payload["tokens"]
+        .as_array()
+        .ok_or_else(|| malformed("missing tokens"))?
+        .iter()
+        .map(|token| {
+            let token = token
+                .as_object()
+                .ok_or_else(|| malformed("token is not an object"))?;
+            let [start, end] = [0, 1].map(|i| token.get("span").and_then(|span| span[i].as_u64()));
+
+            Ok(Token {
+                ttype: token_type(token)?,
+                line: field(token, "line")? as usize,
+                col: field(token, "col")? as usize,
+                span: Span::new(
+                    start.ok_or_else(|| malformed("missing span"))? as usize,
+                    end.ok_or_else(|| malformed("missing span"))? as usize,
+                ),
+            })
+        })
+        .collect()
+}
+
+fn token_type(token: &Map<String, Value>) -> Result<TokenType, DeserializeError> {
+    let string = |key: &str| token.get(key).and_then(Value::as_str);
+
+    if let Some(suffix) = string("suffix") {
+        if let Some(value) = token.get("integer").and_then(Value::as_u64) {
+            let suffix = IntSuffix::from_spelling(suffix)
+                .ok_or_else(|| malformed(&format!("unknown integer suffix '{suffix}'")))?;
+            return Ok(TokenType::SuffixedInteger { value, suffix });
+        }
+        if let Some(value) = token.get("decimal").and_then(Value::as_f64) {
+            let suffix = FloatSuffix::from_spelling(suffix)
+                .ok_or_else(|| malformed(&format!("unknown decimal suffix '{suffix}'")))?;
+            return Ok(TokenType::SuffixedDecimal { value, suffix });
+        }
+        return Err(malformed("suffix without a number"));
+    }
+    if let Some(n) = token.get("integer").and_then(Value::as_u64) {
+        return Ok(TokenType::Integer(n));
+    }
+    if let Some(n) = token.get("negative_integer").and_then(Value::as_u64) {
+        return Ok(TokenType::NegativeInteger(n));
+    }
+    if let Some(n) = token.get("decimal").and_then(Value::as_f64) {
+        return Ok(TokenType::Decimal(n));
+    }
+    if let Some(c) = string("char") {
+        let mut chars = c.chars();
+        return match (chars.next(), chars.next()) {
+            (Some(c), None) => Ok(TokenType::Character(c)),
+            _ => Err(malformed("char is not a single character")),
+        };
+    }
+    if let Some(string) = string("string") {
+        return Ok(TokenType::String(string.to_owned()));
+    }
+    if let Some(identifier) = string("identifier") {
+        return Ok(TokenType::Identifier(identifier.to_owned()));
+    }
+    if let Some(parts) = token.get("interpolated") {
+        let parts = parts
+            .as_array()
+            .ok_or_else(|| malformed("interpolated string is not an array"))?
+            .iter()
+            .map(|part| {
+                let text = |key: &str| part.get(key).and_then(Value::as_str).map(str::to_owned);
+                text("literal")
+                    .map(StringPart::Literal)
+                    .or_else(|| text("expr").map(StringPart::Expr))
+                    .ok_or_else(|| malformed("string part is neither a literal nor an expr"))
+            })
+            .collect::<Result<_, _>>()?;
+        return Ok(TokenType::InterpolatedString(parts));
+    }
+    if token.get("eof").and_then(Value::as_bool) == Some(true) {
+        return Ok(TokenType::Eof);
+    }
+
+    // Every other token is stored as its spelling, which lexes back to exactly that token
+    let text = string("text").ok_or_else(|| malformed("token has no type"))?;
+    match Lexer::new(text).tokenize().as_slice() {
+        [Ok(token)] if token.ttype.to_string() == text => Ok(token.ttype.clone()),
+        _ => Err(malformed(&format!("unknown token '{text}'"))),
+    }
+}
+
+fn field(token: &Map<String, Value>, key: &str) -> Result<u64, DeserializeError> {
+    token
+        .get(key)
+        .and_then(Value::as_u64)
+        .ok_or_else(|| malformed(&format!("missing {key}")))
+}
+
+fn malformed(message: &str) -> DeserializeError {
+    DeserializeError::Malformed(message.to_owned())
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{lexer::options::LexerOptions, parser::test_tokens};
+
+    use super::*;
+
+    #[test]
+    fn round_trips_every_kind_of_token() {
+        let tokens = test_tokens(
+            "fn f(a: usize) -> void { var s = \"hi\"; a += 1.5; c = 'x'; } // done\nfor i in 0..=9 {}",
+        );
+
+        let payload = serialize_tokens(&tokens);
+
+        assert_eq!(deserialize_tokens(&payload).unwrap(), tokens);
+    }
+
+    /// Declares `variant`, naming the variant of a token out of every `TokenType` variant, and
+    /// `VARIANT_COUNT`. The match has no wildcard arm, so adding a variant fails to compile until
+    /// it is listed here, and [round_trips_every_token_type] then fails until it is covered.
+    macro_rules! every_variant {
+        ($($variant:pat,)*) => {
+            fn variant(ttype: &TokenType) -> &'static str {
+                use TokenType::*;
+
+                match ttype {
+                    $($variant => stringify!($variant),)*
+                }
+            }
+
+            const VARIANT_COUNT: usize = [$(stringify!($variant),)*].len();
+        };
+    }
+
+    every_variant! {
+        Add, AddEqual, Minus, MinusEqual, Modulo, ModuloEqual, Slash, SlashEqual, Star, StarEqual,
+        StarStar, StarStarEqual, Bang, BangEqual, Equal, EqualEqual, Greater, GreaterEqual, Less,
+        LessEqual, LessLess, Spaceship, Ampersand, Arrow, At, Dollar, FatArrow, Bar, PipeGreater,
+        Question, QuestionQuestion, QuestionQuestionEqual, Colon, Comma, Dot, DotDot, DotDotEqual,
+        DotDotDot, Hash, LeftBrace, LeftBracket, LeftParen, LogicalAnd, LogicalOr, RightBrace,
+        RightBracket, RightParen, Semicolon, Bool(_), Character(_), Decimal(_), Identifier(_),
+        Integer(_), String(_), InterpolatedString(_), NegativeInteger(_), SuffixedInteger { .. },
+        SuffixedDecimal { .. }, Lifetime(_), Primitive(_), Comment(_), BlockComment(_), Break,
+        Continue, Default, Else, Enum, Fn, For, If, In, Loop, Match, Null, Return, Struct, Switch,
+        Var, While, Underscore, Eof,
+    }
+
+    #[test]
+    fn round_trips_every_token_type() {
+        let source = "+ += - -= % %= / /= * *= ** **= ! != = == > >= < <= << <=> & -> @ $ => | |> \
+            ? ?? ??= : , . .. ..= ... # { [ ( && || } ] ) ; true 'c' 1.5 x 1 \"s\" \"a ${b}\" \
+            = -5 100000000000000000000.0f64 42u8 'outer int // line\n/* block */ break continue \
+            default else enum fn for if in loop match null return struct switch var while _";
+        let options = LexerOptions {
+            fold_negative_literals: true,
+            ..LexerOptions::default()
+        };
+        let tokens: Vec<Token> = Lexer::with_options(source, options)
+            .map(|token| token.expect("source should lex").into_owned())
+            .collect();
+
+        let mut variants: Vec<_> = tokens.iter().map(|token| variant(&token.ttype)).collect();
+        variants.sort_unstable();
+        variants.dedup();
+        assert_eq!(variants.len(), VARIANT_COUNT, "{variants:?}");
+
+        let payload = serialize_tokens(&tokens);
+        assert_eq!(deserialize_tokens(&payload).unwrap(), tokens);
+    }
+
+    #[test]
+    fn suffixed_literals_are_stored_with_their_suffix() {
+        let payload: Value =
+            serde_json::from_str(&serialize_tokens(&test_tokens("42u8 2.5f32"))).unwrap();
+
+        assert_eq!(payload["tokens"][0]["integer"], json!(42));
+        assert_eq!(payload["tokens"][0]["suffix"], json!("u8"));
+        assert_eq!(payload["tokens"][1]["decimal"], json!(2.5));
+        assert_eq!(payload["tokens"][1]["suffix"], json!("f32"));
+    }
+
+    #[test]
+    fn reads_version_1_payloads() {
+        let payload = r#"{"version":1,"tokens":[{"text":"+=","line":1,"col":2,"span":[0,2]}]}"#;
+
+        assert_eq!(
+            deserialize_tokens(payload).unwrap()[0].ttype,
+            TokenType::AddEqual
+        );
+    }
+
+    #[test]
+    fn payload_names_tokens_by_spelling() {
+        let payload: Value =
+            serde_json::from_str(&serialize_tokens(&test_tokens("x += 2"))).unwrap();
+
+        assert_eq!(
+            payload,
+            json!({
+                "version": 2,
+                "tokens": [
+                    { "identifier": "x", "line": 1, "col": 1, "span": [0, 1] },
+                    { "text": "+=", "line": 1, "col": 4, "span": [2, 4] },
+                    { "integer": 2, "line": 1, "col": 6, "span": [5, 6] },
+                ],
+            })
+        );
+    }
+
+    #[test]
+    fn rejects_other_versions() {
+        let payload =
+            serialize_tokens(&test_tokens("var a;")).replacen("\"version\":2", "\"version\":3", 1);
+
+        assert!(matches!(
+            deserialize_tokens(&payload),
+            Err(DeserializeError::UnsupportedVersion {
+                found: 3,
+                expected: 2
+            })
+        ));
+    }
+
+    #[test]
+    fn rejects_unknown_tokens() {
+        let payload = r#"{"version":1,"tokens":[{"text":"+-","line":1,"col":2,"span":[0,2]}]}"#;
+
+        assert!(matches!(
+            deserialize_tokens(payload),
+            Err(DeserializeError::Malformed(_))
+        ));
+    }
+}
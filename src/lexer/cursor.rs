@@ -19,73 +19,149 @@ pub trait Peekable {
     }
 }
 
-/// A cursor over a [Vec<char>](std::alloc::Vec)
+/// A cursor over the bytes of a borrowed source string.
+///
+/// ASCII is read straight from the bytes and UTF-8 is only decoded when a non-ASCII byte is hit,
+/// while the position is kept as a byte offset so that substrings can be sliced without copying.
 #[derive(Debug)]
-pub struct Cursor {
-    stack: Vec<char>,
+pub struct Cursor<'src> {
+    source: &'src str,
     needle: usize,
 }
 
-impl Cursor {
-    pub fn new(source: &str) -> Self {
+impl<'src> Cursor<'src> {
+    pub fn new(source: &'src str) -> Self {
+        Self { source, needle: 0 }
+    }
+
+    /// Creates a cursor that starts at a byte offset into `source`, which must lie on a char
+    /// boundary
+    pub fn starting_at(source: &'src str, offset: usize) -> Self {
+        debug_assert!(source.is_char_boundary(offset));
+
         Self {
-            stack: source.chars().collect(),
-            needle: 0,
+            source,
+            needle: offset,
         }
     }
 
-    /// Gets a substring that starts and ends at the specified indicies, exclusive.
-    /// Returns `None` if the one or both of the indices given are invalid.
-    pub fn substring(&self, start: usize, end: usize) -> Option<String> {
-        let stack_len = self.stack.len();
+    /// The whole source this cursor walks over, regardless of its position
+    pub fn as_str(&self) -> &'src str {
+        self.source
+    }
 
-        let substring_option = if start > stack_len || end > stack_len || start > end {
+    /// The part of the source that has not been consumed yet
+    pub fn rest(&self) -> &'src str {
+        &self.source[self.needle..]
+    }
+
+    /// Gets a substring that starts and ends at the specified byte offsets, exclusive.
+    /// Returns `None` if the one or both of the offsets given are invalid.
+    pub fn substring(&self, start: usize, end: usize) -> Option<&'src str> {
+        if start > end {
             return None;
-        } else {
-            let slice = &self.stack[start..end];
-            let slice = slice.iter().collect();
+        }
 
-            Some(slice)
-        };
+        self.source.get(start..end)
+    }
+
+    /// Byte offset of the next char
+    pub fn offset(&self) -> usize {
+        self.needle
+    }
+
+    /// Moves the cursor to a byte offset anywhere in the source, backward or forward, so that
+    /// lexing can restart from an earlier point.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `byte_offset` is past the end of the source or inside a multi-byte char.
+    pub fn seek(&mut self, byte_offset: usize) {
+        assert!(
+            self.source.is_char_boundary(byte_offset),
+            "cannot seek to byte offset {byte_offset}, which is not on a char boundary"
+        );
 
-        substring_option
+        self.needle = byte_offset;
+    }
+
+    /// Peek at the next byte without consuming it, which is cheaper than [Peekable::peek] when
+    /// only ASCII is of interest
+    pub fn peek_byte(&self) -> Option<u8> {
+        self.source.as_bytes().get(self.needle).copied()
     }
 
     // For testing only
     #[cfg(test)]
+    #[allow(clippy::len_without_is_empty)]
     pub fn len(&self) -> usize {
-        self.stack.len()
+        self.source.len()
     }
 
     pub fn is_at_end(&self) -> bool {
-        self.needle >= self.stack.len()
+        self.needle >= self.source.len()
+    }
+
+    /// Decodes the char starting at the byte offset `at`
+    fn char_at(&self, at: usize) -> Option<char> {
+        let byte = *self.source.as_bytes().get(at)?;
+        if byte.is_ascii() {
+            return Some(byte as char);
+        }
+
+        self.source[at..].chars().next()
     }
 }
 
-impl Iterator for Cursor {
+/// Number of bytes in the UTF-8 sequence started by `leading_byte`
+fn utf8_width(leading_byte: u8) -> usize {
+    match leading_byte {
+        0x00..=0x7F => 1,
+        0xC0..=0xDF => 2,
+        0xE0..=0xEF => 3,
+        _ => 4,
+    }
+}
+
+impl Iterator for Cursor<'_> {
     type Item = char;
 
     fn next(&mut self) -> Option<Self::Item> {
-        let c = self.stack.get(self.needle)?;
+        let c = self.char_at(self.needle)?;
 
-        self.needle += 1;
+        self.needle += c.len_utf8();
 
-        Some(*c)
+        Some(c)
     }
 }
 
-impl Peekable for Cursor {
+impl Peekable for Cursor<'_> {
     type Item = char;
 
     fn peek_nth(&self, n: usize) -> Option<Self::Item> {
-        match self.needle.checked_add(n) {
-            Some(target) if target < self.stack.len() => Some(self.stack[target]),
-            _ => None,
+        let bytes = self.source.as_bytes();
+
+        let mut at = self.needle;
+        for _ in 0..n {
+            at += utf8_width(*bytes.get(at)?);
         }
+
+        self.char_at(at)
     }
 
     fn peek_prev_nth(&self, n: usize) -> Option<Self::Item> {
-        self.needle.checked_sub(n).map(|target| self.stack[target])
+        let bytes = self.source.as_bytes();
+
+        let mut at = self.needle;
+        for _ in 0..n {
+            at = at.checked_sub(1)?;
+            // Step back over UTF-8 continuation bytes to the start of the char
+            while bytes[at] & 0b1100_0000 == 0b1000_0000 {
+                at -= 1;
+            }
+        }
+
+        self.char_at(at)
     }
 }
 
@@ -124,4 +200,80 @@ mod tests {
 
         assert_eq!(cursor.substring(start, end), None);
     }
+
+    #[test]
+    fn substring_return_none_when_not_on_char_boundary() {
+        let cursor = Cursor::new("héllo");
+
+        assert_eq!(cursor.substring(0, 2), None);
+        assert_eq!(cursor.substring(0, 3), Some("hé"));
+    }
+
+    #[test]
+    fn next_and_peek_over_multi_byte_chars() {
+        let mut cursor = Cursor::new("aé😀b");
+
+        assert_eq!(cursor.next(), Some('a'));
+        assert_eq!(cursor.peek(), Some('é'));
+        assert_eq!(cursor.peek_nth(1), Some('😀'));
+        assert_eq!(cursor.next(), Some('é'));
+        assert_eq!(cursor.next(), Some('😀'));
+        assert_eq!(cursor.peek_prev(), Some('😀'));
+        assert_eq!(cursor.peek_prev_nth(2), Some('é'));
+        assert_eq!(cursor.next(), Some('b'));
+        assert_eq!(cursor.next(), None);
+        assert!(cursor.is_at_end());
+    }
+
+    #[test]
+    fn offset_and_peek_byte_track_bytes() {
+        let mut cursor = Cursor::new("é!");
+
+        assert_eq!(cursor.offset(), 0);
+        assert_eq!(cursor.peek_byte(), Some(0xC3));
+        cursor.next();
+        assert_eq!(cursor.offset(), 2);
+        assert_eq!(cursor.peek_byte(), Some(b'!'));
+        cursor.next();
+        assert_eq!(cursor.peek_byte(), None);
+    }
+
+    #[test]
+    fn seek_backward_and_forward() {
+        let mut cursor = Cursor::new("aé😀b");
+        cursor.by_ref().for_each(drop);
+
+        cursor.seek(1);
+        assert_eq!(cursor.offset(), 1);
+        assert_eq!(cursor.peek_prev(), Some('a'));
+        assert_eq!(cursor.next(), Some('é'));
+
+        cursor.seek(7);
+        assert_eq!(cursor.next(), Some('b'));
+        assert!(cursor.is_at_end());
+
+        cursor.seek(0);
+        assert_eq!(cursor.collect::<String>(), "aé😀b");
+    }
+
+    #[test]
+    fn seek_to_the_end() {
+        let mut cursor = Cursor::new("ab");
+
+        cursor.seek(2);
+        assert!(cursor.is_at_end());
+        assert_eq!(cursor.next(), None);
+    }
+
+    #[test]
+    #[should_panic(expected = "not on a char boundary")]
+    fn seek_inside_a_char_panics() {
+        Cursor::new("é").seek(1);
+    }
+
+    #[test]
+    #[should_panic(expected = "not on a char boundary")]
+    fn seek_past_the_end_panics() {
+        Cursor::new("ab").seek(3);
+    }
 }
@@ -0,0 +1,82 @@
+use std::ops::Range;
+
+use codespan_reporting::diagnostic::{Diagnostic, Label};
+
+use super::error::LexerError;
+
+impl LexerError {
+    /// Converts the error into a diagnostic for `codespan-reporting`, labelling the char the
+    /// error was reported at in `source`, which must be the source that was lexed
+    pub fn to_diagnostic<FileId>(&self, file_id: FileId, source: &str) -> Diagnostic<FileId> {
+        let message = match self {
+            Self::UnexpectedEof { .. } => "unexpected end of file",
+            Self::UnexpectedCharacter { .. } => "unexpected character",
+            Self::UnknownCharacter { .. } => "unknown character",
+            Self::UnterminatedString { .. } => "unterminated string literal",
+            Self::UnterminatedInterpolation { .. } => "unterminated interpolation",
+            Self::UnterminatedComment { .. } => "unterminated block comment",
+            Self::StringTooLong { .. } => "string literal too long",
+            Self::IntegerOverflow { .. } => "integer literal out of range",
+            Self::NumberOutOfRange { .. } => "number out of range for its type",
+            Self::MissingHexExponent { .. } => "hex float without an exponent",
+            Self::InvalidNumberSeparator { .. } => "misplaced digit separator",
+            Self::MultiScalarChar { .. } => "char literal with several scalar values",
+            Self::InvalidHexEscape { .. } => "invalid hex escape",
+            Self::InvalidNumberSuffix { .. } => "invalid suffix on number",
+        };
+
+        Diagnostic::error()
+            .with_message(message)
+            .with_code(self.code())
+            .with_labels(vec![
+                Label::primary(file_id, self.byte_range(source)).with_message(self.to_string())
+            ])
+    }
+
+    /// The bytes of the char the error was reported at, empty if that is past the end of the
+    /// source
+    fn byte_range(&self, source: &str) -> Range<usize> {
+        let start = self.offset();
+        let len = source[start..].chars().next().map_or(0, char::len_utf8);
+
+        start..start + len
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::lexer::Lexer;
+
+    #[test]
+    fn unknown_character_label_covers_the_character() {
+        let source = "var a = 1;\nvar é = a ~ 2;";
+        let error = Lexer::new(source)
+            .tokenize()
+            .into_iter()
+            .find_map(Result::err)
+            .expect("source should fail to lex");
+
+        let diagnostic = error.to_diagnostic((), source);
+
+        assert_eq!(diagnostic.code.as_deref(), Some("SK0001"));
+        assert_eq!(diagnostic.labels.len(), 1);
+        let range = diagnostic.labels[0].range.clone();
+        assert_eq!(&source[range.clone()], "~");
+        assert_eq!(range.start, source.find('~').unwrap());
+    }
+
+    #[test]
+    fn unexpected_eof_label_covers_the_last_character() {
+        let source = "var c = '";
+        let error = Lexer::new(source)
+            .tokenize()
+            .into_iter()
+            .find_map(Result::err)
+            .expect("source should fail to lex");
+
+        let diagnostic = error.to_diagnostic((), source);
+
+        assert_eq!(diagnostic.code.as_deref(), Some("SK0002"));
+        assert_eq!(diagnostic.labels[0].range, source.len() - 1..source.len());
+    }
+}
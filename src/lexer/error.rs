@@ -1,23 +1,189 @@
 pub type Result<T> = core::result::Result<T, LexerError>;
 
-#[derive(Debug, PartialEq)]
+/// An error positioned at a char of the source, given both as a line and column and as the byte
+/// offset of the char
+#[derive(Debug, PartialEq, Eq, Clone, Hash)]
 pub enum LexerError {
     UnexpectedEof {
         line: usize,
         col: usize,
+        offset: usize,
         expected: String,
     },
     UnexpectedCharacter {
         line: usize,
         col: usize,
+        offset: usize,
         expected: String,
         got: char,
     },
     UnknownCharacter {
         line: usize,
         col: usize,
+        offset: usize,
         character: char,
     },
+    /// A string literal that is never closed, positioned at its opening quote
+    UnterminatedString {
+        line: usize,
+        col: usize,
+        offset: usize,
+    },
+    /// A `/*` that is never closed by a `*/`, positioned at its `/`
+    UnterminatedComment {
+        line: usize,
+        col: usize,
+        offset: usize,
+    },
+    /// A `${` in a string literal that is never closed by a `}`, positioned at its `$`
+    UnterminatedInterpolation {
+        line: usize,
+        col: usize,
+        offset: usize,
+    },
+    /// A string literal longer than
+    /// [LexerOptions::max_string_len](super::options::LexerOptions::max_string_len), positioned
+    /// at its opening quote
+    StringTooLong {
+        line: usize,
+        col: usize,
+        offset: usize,
+        max: usize,
+    },
+    /// An integer literal too large to fit in a `u64`, positioned at its last digit
+    IntegerOverflow {
+        line: usize,
+        col: usize,
+        offset: usize,
+    },
+    /// An integer literal too large for its type suffix, e.g. `256u8`, positioned at the last
+    /// char of the whole lexeme
+    NumberOutOfRange {
+        line: usize,
+        col: usize,
+        offset: usize,
+        lexeme: String,
+    },
+    /// A hex float such as `0x1.8` without its `p` exponent, or with no digits after the `p`,
+    /// positioned at the last char of the whole lexeme
+    MissingHexExponent {
+        line: usize,
+        col: usize,
+        offset: usize,
+        lexeme: String,
+    },
+    /// A `_` in a numeric literal that is not between two digits, e.g. `1__0` or `5_`,
+    /// positioned at the first misplaced `_`
+    InvalidNumberSeparator {
+        line: usize,
+        col: usize,
+        offset: usize,
+    },
+    /// A char literal holding more than one Unicode scalar value, such as an emoji made of
+    /// several, positioned at its opening quote
+    MultiScalarChar {
+        line: usize,
+        col: usize,
+        offset: usize,
+    },
+    /// A `\x` escape not followed by two hex digits of at most `7F`, positioned at its backslash
+    InvalidHexEscape {
+        line: usize,
+        col: usize,
+        offset: usize,
+    },
+    /// A numeric literal directly followed by letters, e.g. `123abc`, positioned at the last
+    /// char of the whole lexeme
+    InvalidNumberSuffix {
+        line: usize,
+        col: usize,
+        offset: usize,
+        lexeme: String,
+    },
+}
+
+impl LexerError {
+    /// The line and column the error was reported at
+    pub fn position(&self) -> (usize, usize) {
+        match self {
+            Self::UnexpectedEof { line, col, .. }
+            | Self::UnexpectedCharacter { line, col, .. }
+            | Self::UnknownCharacter { line, col, .. }
+            | Self::UnterminatedString { line, col, .. }
+            | Self::UnterminatedInterpolation { line, col, .. }
+            | Self::UnterminatedComment { line, col, .. }
+            | Self::InvalidNumberSeparator { line, col, .. }
+            | Self::MultiScalarChar { line, col, .. }
+            | Self::InvalidHexEscape { line, col, .. }
+            | Self::StringTooLong { line, col, .. }
+            | Self::IntegerOverflow { line, col, .. }
+            | Self::NumberOutOfRange { line, col, .. }
+            | Self::MissingHexExponent { line, col, .. }
+            | Self::InvalidNumberSuffix { line, col, .. } => (*line, *col),
+        }
+    }
+
+    /// The byte offset of the char the error was reported at, which unlike
+    /// [position](Self::position) does not depend on the column encoding
+    pub fn offset(&self) -> usize {
+        match self {
+            Self::UnexpectedEof { offset, .. }
+            | Self::UnexpectedCharacter { offset, .. }
+            | Self::UnknownCharacter { offset, .. }
+            | Self::UnterminatedString { offset, .. }
+            | Self::UnterminatedInterpolation { offset, .. }
+            | Self::UnterminatedComment { offset, .. }
+            | Self::InvalidNumberSeparator { offset, .. }
+            | Self::MultiScalarChar { offset, .. }
+            | Self::InvalidHexEscape { offset, .. }
+            | Self::StringTooLong { offset, .. }
+            | Self::IntegerOverflow { offset, .. }
+            | Self::NumberOutOfRange { offset, .. }
+            | Self::MissingHexExponent { offset, .. }
+            | Self::InvalidNumberSuffix { offset, .. } => *offset,
+        }
+    }
+
+    /// Moves the error `by` bytes further into the source, for errors found in a slice of it
+    pub(super) fn shift_offset(&mut self, by: usize) {
+        match self {
+            Self::UnexpectedEof { offset, .. }
+            | Self::UnexpectedCharacter { offset, .. }
+            | Self::UnknownCharacter { offset, .. }
+            | Self::UnterminatedString { offset, .. }
+            | Self::UnterminatedInterpolation { offset, .. }
+            | Self::UnterminatedComment { offset, .. }
+            | Self::InvalidNumberSeparator { offset, .. }
+            | Self::MultiScalarChar { offset, .. }
+            | Self::InvalidHexEscape { offset, .. }
+            | Self::StringTooLong { offset, .. }
+            | Self::IntegerOverflow { offset, .. }
+            | Self::NumberOutOfRange { offset, .. }
+            | Self::MissingHexExponent { offset, .. }
+            | Self::InvalidNumberSuffix { offset, .. } => *offset += by,
+        }
+    }
+
+    /// A stable identifier for the kind of error, for tools to match on instead of the message.
+    /// Codes are never reused or renumbered.
+    pub fn code(&self) -> &'static str {
+        match self {
+            Self::UnknownCharacter { .. } => "SK0001",
+            Self::UnexpectedEof { .. } => "SK0002",
+            Self::UnexpectedCharacter { .. } => "SK0003",
+            Self::UnterminatedString { .. } => "SK0004",
+            Self::IntegerOverflow { .. } => "SK0005",
+            Self::InvalidNumberSuffix { .. } => "SK0006",
+            Self::UnterminatedInterpolation { .. } => "SK0007",
+            Self::StringTooLong { .. } => "SK0008",
+            Self::UnterminatedComment { .. } => "SK0009",
+            Self::NumberOutOfRange { .. } => "SK0010",
+            Self::MissingHexExponent { .. } => "SK0011",
+            Self::InvalidNumberSeparator { .. } => "SK0012",
+            Self::MultiScalarChar { .. } => "SK0013",
+            Self::InvalidHexEscape { .. } => "SK0014",
+        }
+    }
 }
 
 impl std::fmt::Display for LexerError {
@@ -27,6 +193,7 @@ impl std::fmt::Display for LexerError {
                 line,
                 col,
                 expected: message,
+                ..
             } => write!(
                 f,
                 "[line {line}: {col}] Unexpected end of file, expected {message}."
@@ -36,6 +203,7 @@ impl std::fmt::Display for LexerError {
                 col,
                 expected,
                 got,
+                ..
             } => {
                 write!(
                     f,
@@ -46,11 +214,200 @@ impl std::fmt::Display for LexerError {
                 line,
                 col,
                 character,
+                ..
             } => {
                 write!(f, "[line {line}: {col}] Unknown character '{character}'")
             }
+            Self::UnterminatedString { line, col, .. } => {
+                write!(f, "[line {line}: {col}] Unterminated string literal")
+            }
+            Self::UnterminatedInterpolation { line, col, .. } => {
+                write!(
+                    f,
+                    "[line {line}: {col}] Unterminated '${{' in string literal"
+                )
+            }
+            Self::UnterminatedComment { line, col, .. } => {
+                write!(f, "[line {line}: {col}] Unterminated block comment")
+            }
+            Self::StringTooLong { line, col, max, .. } => {
+                write!(
+                    f,
+                    "[line {line}: {col}] String literal is longer than {max} bytes"
+                )
+            }
+            Self::IntegerOverflow { line, col, .. } => {
+                write!(f, "[line {line}: {col}] Integer literal is too large")
+            }
+            Self::NumberOutOfRange {
+                line, col, lexeme, ..
+            } => {
+                write!(
+                    f,
+                    "[line {line}: {col}] Number '{lexeme}' is out of range for its type"
+                )
+            }
+            Self::InvalidNumberSeparator { line, col, .. } => {
+                write!(
+                    f,
+                    "[line {line}: {col}] A digit separator must be between two digits"
+                )
+            }
+            Self::MultiScalarChar { line, col, .. } => {
+                write!(
+                    f,
+                    "[line {line}: {col}] Char literal must be a single scalar value"
+                )
+            }
+            Self::InvalidHexEscape { line, col, .. } => {
+                write!(
+                    f,
+                    "[line {line}: {col}] A '\\x' escape must be two hex digits of at most 7F"
+                )
+            }
+            // No hex digit is a `p`, so one in the lexeme is always the start of the exponent
+            Self::MissingHexExponent {
+                line, col, lexeme, ..
+            } if lexeme.contains(['p', 'P']) => {
+                write!(
+                    f,
+                    "[line {line}: {col}] Hex float '{lexeme}' is missing the digits of its 'p' exponent"
+                )
+            }
+            Self::MissingHexExponent {
+                line, col, lexeme, ..
+            } => {
+                write!(
+                    f,
+                    "[line {line}: {col}] Hex float '{lexeme}' is missing its 'p' exponent"
+                )
+            }
+            Self::InvalidNumberSuffix {
+                line, col, lexeme, ..
+            } => {
+                write!(
+                    f,
+                    "[line {line}: {col}] Invalid suffix on number '{lexeme}'"
+                )
+            }
         }
     }
 }
 
 impl std::error::Error for LexerError {}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashSet;
+
+    use super::*;
+
+    #[test]
+    fn errors_can_be_cloned_and_deduplicated() {
+        let error = LexerError::UnknownCharacter {
+            line: 1,
+            col: 5,
+            offset: 4,
+            character: '$',
+        };
+
+        let errors = HashSet::from([error.clone(), error.clone()]);
+
+        assert_eq!(errors.len(), 1);
+        assert!(errors.contains(&error));
+    }
+
+    #[test]
+    fn codes_are_stable_and_unique() {
+        let errors = [
+            LexerError::UnknownCharacter {
+                line: 1,
+                col: 1,
+                offset: 0,
+                character: '$',
+            },
+            LexerError::UnexpectedEof {
+                line: 1,
+                col: 1,
+                offset: 0,
+                expected: "'".to_owned(),
+            },
+            LexerError::UnexpectedCharacter {
+                line: 1,
+                col: 1,
+                offset: 0,
+                expected: "'".to_owned(),
+                got: 'b',
+            },
+            LexerError::UnterminatedString {
+                line: 1,
+                col: 1,
+                offset: 0,
+            },
+            LexerError::IntegerOverflow {
+                line: 1,
+                col: 1,
+                offset: 0,
+            },
+            LexerError::InvalidNumberSuffix {
+                line: 1,
+                col: 1,
+                offset: 0,
+                lexeme: "1a".to_owned(),
+            },
+            LexerError::UnterminatedInterpolation {
+                line: 1,
+                col: 1,
+                offset: 0,
+            },
+            LexerError::StringTooLong {
+                line: 1,
+                col: 1,
+                offset: 0,
+                max: 1,
+            },
+            LexerError::UnterminatedComment {
+                line: 1,
+                col: 1,
+                offset: 0,
+            },
+            LexerError::NumberOutOfRange {
+                line: 1,
+                col: 1,
+                offset: 0,
+                lexeme: "256u8".to_owned(),
+            },
+            LexerError::MissingHexExponent {
+                line: 1,
+                col: 1,
+                offset: 0,
+                lexeme: "0x1.8".to_owned(),
+            },
+            LexerError::InvalidNumberSeparator {
+                line: 1,
+                col: 1,
+                offset: 0,
+            },
+            LexerError::MultiScalarChar {
+                line: 1,
+                col: 1,
+                offset: 0,
+            },
+            LexerError::InvalidHexEscape {
+                line: 1,
+                col: 1,
+                offset: 0,
+            },
+        ];
+
+        let codes: Vec<&str> = errors.iter().map(LexerError::code).collect();
+        assert_eq!(
+            codes,
+            [
+                "SK0001", "SK0002", "SK0003", "SK0004", "SK0005", "SK0006", "SK0007", "SK0008",
+                "SK0009", "SK0010", "SK0011", "SK0012", "SK0013", "SK0014"
+            ]
+        );
+        assert_eq!(codes.iter().collect::<HashSet<_>>().len(), codes.len());
+    }
+}
@@ -0,0 +1,180 @@
+use crate::{line_index::line_breaks, span::Span};
+
+use super::{
+    cursor::Cursor, error::Result, precedes_prefix, Lexer, Token, TokenCategory, TokenType,
+};
+
+/// A single replacement in the source, in byte offsets.
+///
+/// The bytes `start..old_end` of the old source were replaced by the bytes `start..new_end` of
+/// the new source, so an insertion has `start == old_end` and a deletion has `start == new_end`.
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub struct Edit {
+    pub start: usize,
+    pub old_end: usize,
+    pub new_end: usize,
+}
+
+impl Edit {
+    /// How far everything after the edit moved
+    fn delta(&self) -> isize {
+        self.new_end as isize - self.old_end as isize
+    }
+}
+
+impl<'src> Lexer<'src> {
+    /// Brings the tokens of the source before `edit` up to date with the source this lexer was
+    /// created with, which must be the source after `edit`.
+    ///
+    /// Only the lines touched by the edit are lexed again, widened to cover any token that starts
+    /// on an earlier line and runs into them, e.g. a multi-line string. Lexing stops once it
+    /// produces a token past the edited lines that the old tokens also have at the same shifted
+    /// position, and the remaining old tokens are reused with their spans and lines shifted.
+    ///
+    /// `old_tokens` must be the full, error free output of [Lexer::tokenize] for the old source.
+    /// A lexer error in the relexed region is returned as is, callers may fall back to
+    /// tokenizing the whole file to collect every error.
+    pub fn relex(&self, old_tokens: &[Token], edit: Edit) -> Result<Vec<Token>> {
+        let source = self.source.as_str();
+
+        // Tokens that end before the dirty region are untouched
+        let mut dirty_start = line_start(source, edit.start);
+        let mut first_dirty = old_tokens.partition_point(|token| token.span.end <= dirty_start);
+        while let Some(token) = old_tokens.get(first_dirty) {
+            if token.span.start >= dirty_start {
+                break;
+            }
+            dirty_start = line_start(source, token.span.start);
+            first_dirty = old_tokens.partition_point(|token| token.span.end <= dirty_start);
+        }
+        let dirty_end = line_end(source, edit.new_end);
+
+        let mut tokens = old_tokens[..first_dirty].to_vec();
+        let mut cursor = Cursor::new(source);
+        cursor.seek(dirty_start);
+        let mut lexer = Lexer {
+            source: cursor,
+            start: dirty_start,
+            line: 1 + line_breaks(&source[..dirty_start]).count(),
+            col: 0,
+            options: self.options.clone(),
+            finished: false,
+            peeked: None,
+            prefix_position: tokens
+                .iter()
+                .rev()
+                .find(|token| token.ttype.category() != TokenCategory::Comment)
+                .is_none_or(|token| precedes_prefix(&token.ttype)),
+        };
+        let mut old = first_dirty;
+
+        loop {
+            let token = lexer.next_token()?;
+            if token.ttype == TokenType::Eof {
+                break;
+            }
+
+            if token.span.start >= dirty_end {
+                // Skip old tokens that now lie before the new token
+                while old_tokens
+                    .get(old)
+                    .is_some_and(|old_token| shift(old_token.span.start, edit) < token.span.start)
+                {
+                    old += 1;
+                }
+
+                if let Some(old_token) = old_tokens.get(old) {
+                    let resynced = old_token.span.start >= edit.old_end
+                        && shift(old_token.span.start, edit) == token.span.start
+                        && old_token.ttype == token.ttype.clone().into_owned();
+
+                    if resynced {
+                        let line_delta = token.line as isize - old_token.line as isize;
+                        tokens.extend(old_tokens[old..].iter().map(|old_token| Token {
+                            line: old_token.line.saturating_add_signed(line_delta),
+                            span: Span::new(
+                                shift(old_token.span.start, edit),
+                                shift(old_token.span.end, edit),
+                            ),
+                            ..old_token.clone()
+                        }));
+
+                        return Ok(tokens);
+                    }
+                }
+            }
+
+            tokens.push(token.into_owned());
+        }
+
+        Ok(tokens)
+    }
+}
+
+fn shift(offset: usize, edit: Edit) -> usize {
+    offset.saturating_add_signed(edit.delta())
+}
+
+/// Byte offset of the start of the line containing `offset`
+fn line_start(source: &str, offset: usize) -> usize {
+    source[..offset]
+        .rfind('\n')
+        .map_or(0, |newline| newline + 1)
+}
+
+/// Byte offset just past the end of the line containing `offset`, including its newline
+fn line_end(source: &str, offset: usize) -> usize {
+    source[offset..]
+        .find('\n')
+        .map_or(source.len(), |newline| offset + newline + 1)
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::parser::test_tokens;
+
+    use super::*;
+
+    const SOURCE: &str =
+        "fn main() {\n    var a: int = 1;\n    print(\"a b\");\n    return a;\n}\n";
+
+    /// Replaces the only occurrence of `old_text` in `source` with `new_text`
+    fn assert_relex_matches_full(source: &str, old_text: &str, new_text: &str) {
+        assert_eq!(source.matches(old_text).count(), 1);
+        let start = source.find(old_text).unwrap();
+        let edited = source.replacen(old_text, new_text, 1);
+        let edit = Edit {
+            start,
+            old_end: start + old_text.len(),
+            new_end: start + new_text.len(),
+        };
+
+        let relexed = Lexer::new(&edited)
+            .relex(&test_tokens(source), edit)
+            .expect("edited source should lex");
+
+        assert_eq!(relexed, test_tokens(&edited));
+    }
+
+    #[test]
+    fn insert() {
+        assert_relex_matches_full(SOURCE, "1;", "1 + 2;\n    var b = a;");
+    }
+
+    #[test]
+    fn delete() {
+        assert_relex_matches_full(SOURCE, "    print(\"a b\");\n", "");
+    }
+
+    #[test]
+    fn edit_inside_string() {
+        assert_relex_matches_full(SOURCE, "a b", "a \" + b + \"");
+    }
+
+    #[test]
+    fn edit_on_a_later_line_of_a_multi_line_string() {
+        let source = "var a = 1;\nvar s = \"first\nsecond\nthird\";\nvar b = 2;\n";
+
+        assert_relex_matches_full(source, "second", "2nd\";\nvar t = \"");
+    }
+}
@@ -0,0 +1,159 @@
+use std::borrow::Cow;
+
+use serde_json::{json, Value};
+
+use super::{error::LexerError, BorrowedToken, Lexer, StringPart, TokenType};
+
+/// Lexes `source` and dumps the result as JSON for tools that consume tokens over stdio.
+///
+/// The output is an object of the form `{"tokens": [...], "errors": [...]}` where every token is
+/// `{"type", "value", "line", "col", "span": {"start", "end"}}` and every error is
+/// `{"message", "line", "col"}`. `value` is `null` for tokens that carry no payload, and an
+/// interpolated string's value is an array of `{"literal": ...}` and `{"expr": ...}` parts. A
+/// literal with a type suffix such as `42u8` also has a `"suffix"`.
+pub fn dump_tokens_json(source: &str) -> String {
+    let mut tokens = Vec::new();
+    let mut errors = Vec::new();
+
+    for result in Lexer::new(source).tokenize_borrowed() {
+        match result {
+            Ok(token) => tokens.push(token_json(&token)),
+            Err(error) => errors.push(error_json(&error)),
+        }
+    }
+
+    json!({ "tokens": tokens, "errors": errors }).to_string()
+}
+
+fn token_json(token: &BorrowedToken) -> Value {
+    let mut object = json!({
+        "type": type_name(&token.ttype),
+        "value": value(&token.ttype),
+        "line": token.line,
+        "col": token.col,
+        "span": { "start": token.span.start, "end": token.span.end },
+    });
+
+    match &token.ttype {
+        TokenType::SuffixedInteger { suffix, .. } => object["suffix"] = json!(suffix.to_string()),
+        TokenType::SuffixedDecimal { suffix, .. } => object["suffix"] = json!(suffix.to_string()),
+        _ => {}
+    }
+
+    object
+}
+
+fn error_json(error: &LexerError) -> Value {
+    let (line, col) = error.position();
+
+    json!({
+        "message": error.to_string(),
+        "line": line,
+        "col": col,
+        "offset": error.offset(),
+    })
+}
+
+/// The name of the variant, e.g. `Identifier` for `Identifier("main")` or `SuffixedInteger` for
+/// `SuffixedInteger { value: 42, suffix: U8 }`
+fn type_name(ttype: &TokenType<Cow<str>>) -> String {
+    let debug = format!("{ttype:?}");
+
+    match debug.split_once(['(', ' ', '{']) {
+        Some((name, _)) => name.to_owned(),
+        None => debug,
+    }
+}
+
+fn value(ttype: &TokenType<Cow<str>>) -> Value {
+    match ttype {
+        TokenType::Bool(b) => json!(b),
+        TokenType::Character(c) => json!(c),
+        TokenType::Decimal(n) | TokenType::SuffixedDecimal { value: n, .. } => json!(n),
+        TokenType::Identifier(identifier) => json!(identifier),
+        TokenType::Integer(n)
+        | TokenType::NegativeInteger(n)
+        | TokenType::SuffixedInteger { value: n, .. } => json!(n),
+        TokenType::String(string) => json!(string),
+        TokenType::InterpolatedString(parts) => parts
+            .iter()
+            .map(|part| match part {
+                StringPart::Literal(literal) => json!({ "literal": literal }),
+                StringPart::Expr(expr) => json!({ "expr": expr }),
+            })
+            .collect(),
+        TokenType::Lifetime(name) => json!(name),
+        TokenType::Comment(text) | TokenType::BlockComment(text) => json!(text),
+        TokenType::Primitive(primitive) => json!(primitive.to_string()),
+        _ => Value::Null,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn dumps_token_shape() {
+        let dump: Value = serde_json::from_str(&dump_tokens_json("var x")).unwrap();
+
+        assert_eq!(
+            dump,
+            json!({
+                "tokens": [
+                    {
+                        "type": "Var",
+                        "value": null,
+                        "line": 1,
+                        "col": 3,
+                        "span": { "start": 0, "end": 3 },
+                    },
+                    {
+                        "type": "Identifier",
+                        "value": "x",
+                        "line": 1,
+                        "col": 5,
+                        "span": { "start": 4, "end": 5 },
+                    },
+                ],
+                "errors": [],
+            })
+        );
+    }
+
+    #[test]
+    fn dumps_suffixed_literals() {
+        let dump: Value = serde_json::from_str(&dump_tokens_json("42u8 2.5f32 7")).unwrap();
+        let fields: Vec<_> = dump["tokens"]
+            .as_array()
+            .unwrap()
+            .iter()
+            .map(|token| (&token["type"], &token["value"], &token["suffix"]))
+            .collect();
+
+        assert_eq!(
+            fields,
+            [
+                (&json!("SuffixedInteger"), &json!(42), &json!("u8")),
+                (&json!("SuffixedDecimal"), &json!(2.5), &json!("f32")),
+                (&json!("Integer"), &json!(7), &Value::Null),
+            ]
+        );
+    }
+
+    #[test]
+    fn dumps_lexer_errors() {
+        let dump: Value = serde_json::from_str(&dump_tokens_json("var ~ = 1;")).unwrap();
+
+        assert_eq!(dump["tokens"].as_array().unwrap().len(), 4);
+        assert_eq!(
+            dump["errors"],
+            json!([{
+                "message": "[line 1: 5] Unknown character '~'",
+                "line": 1,
+                "col": 5,
+                "offset": 4,
+            }])
+        );
+    }
+}
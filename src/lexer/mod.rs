@@ -1,179 +1,518 @@
-use std::collections::HashMap;
+use std::borrow::Cow;
 
 use error::Result;
-use lazy_static::lazy_static;
 
-use crate::lexer::cursor::Peekable;
+use crate::{lexer::cursor::Peekable, span::Span};
 
-use self::{cursor::Cursor, error::LexerError};
+use self::{cursor::Cursor, error::LexerError, options::LexerOptions};
 
+pub mod adapters;
+pub mod brackets;
+#[cfg(feature = "serde")]
+pub mod cache;
 pub mod cursor;
+#[cfg(feature = "codespan")]
+pub mod diagnostic;
 pub mod error;
+pub mod incremental;
+#[cfg(feature = "serde")]
+pub mod json;
+pub mod options;
+pub mod stream;
 
-lazy_static! {
-    static ref KEYWORDS: HashMap<&'static str, TokenType> = {
-        let mut map = HashMap::new();
-        map.insert("break", TokenType::Break);
-        map.insert("continue", TokenType::Continue);
-        map.insert("default", TokenType::Default);
-        map.insert("else", TokenType::Else);
-        map.insert("enum", TokenType::Enum);
-        map.insert("false", TokenType::False);
-        map.insert("fn", TokenType::Fn);
-        map.insert("for", TokenType::For);
-        map.insert("if", TokenType::If);
-        map.insert("match", TokenType::Match);
-        map.insert("return", TokenType::Return);
-        map.insert("struct", TokenType::Struct);
-        map.insert("switch", TokenType::Switch);
-        map.insert("true", TokenType::True);
-        map.insert("var", TokenType::Var);
-        map.insert("while", TokenType::While);
+/// Resolves a lexeme to its keyword token, or `None` if it is an ordinary identifier
+fn keyword<S>(lexeme: &str) -> Option<TokenType<S>> {
+    let ttype = match lexeme {
+        "break" => TokenType::Break,
+        "continue" => TokenType::Continue,
+        "default" => TokenType::Default,
+        "else" => TokenType::Else,
+        "enum" => TokenType::Enum,
+        "false" => TokenType::Bool(false),
+        "fn" => TokenType::Fn,
+        "for" => TokenType::For,
+        "if" => TokenType::If,
+        "in" => TokenType::In,
+        "loop" => TokenType::Loop,
+        "match" => TokenType::Match,
+        "null" => TokenType::Null,
+        "return" => TokenType::Return,
+        "struct" => TokenType::Struct,
+        "switch" => TokenType::Switch,
+        "true" => TokenType::Bool(true),
+        "var" => TokenType::Var,
+        "while" => TokenType::While,
+        "_" => TokenType::Underscore,
 
         // Types
-        map.insert("int", TokenType::Primitive(PrimitiveType::Int));
-        map.insert("uint", TokenType::Primitive(PrimitiveType::UInt));
-        map.insert("float", TokenType::Primitive(PrimitiveType::Float));
-        map.insert("bool", TokenType::Primitive(PrimitiveType::Bool));
-        map.insert("char", TokenType::Primitive(PrimitiveType::Char));
-        map
+        "int" => TokenType::Primitive(PrimitiveType::Int),
+        "uint" => TokenType::Primitive(PrimitiveType::UInt),
+        "isize" => TokenType::Primitive(PrimitiveType::Isize),
+        "usize" => TokenType::Primitive(PrimitiveType::Usize),
+        "float" => TokenType::Primitive(PrimitiveType::Float),
+        "bool" => TokenType::Primitive(PrimitiveType::Bool),
+        "char" => TokenType::Primitive(PrimitiveType::Char),
+        "void" => TokenType::Primitive(PrimitiveType::Void),
+        _ => return None,
     };
+
+    Some(ttype)
+}
+
+/// Whether a `-` after a token of type `ttype` is in prefix position, i.e. where an expression
+/// starts rather than where one could be continued
+fn precedes_prefix<S>(ttype: &TokenType<S>) -> bool {
+    matches!(
+        ttype,
+        TokenType::LeftParen
+            | TokenType::LeftBracket
+            | TokenType::LeftBrace
+            | TokenType::Comma
+            | TokenType::Semicolon
+            | TokenType::Colon
+            | TokenType::FatArrow
+            | TokenType::Return
+            | TokenType::Match
+            | TokenType::In
+    ) || ttype.category() == TokenCategory::Operator
+}
+
+/// The ASCII char written as the two hex digits of a `\xNN` escape
+fn hex_escape_value(digits: &str) -> Option<char> {
+    Some(digits)
+        .filter(|digits| digits.len() == 2 && digits.bytes().all(|byte| byte.is_ascii_hexdigit()))
+        .and_then(|digits| u8::from_str_radix(digits, 16).ok())
+        .filter(u8::is_ascii)
+        .map(char::from)
+}
+
+/// Decodes the `\xNN` escapes in the text of a string literal that lexed without errors. Any
+/// other backslash, including the one of an escaped `\$`, is kept as written.
+fn decode_escapes(text: &str) -> Cow<'_, str> {
+    if !text.contains("\\x") {
+        return Cow::Borrowed(text);
+    }
+
+    let mut decoded = String::with_capacity(text.len());
+    let mut rest = text;
+    while let Some(backslash) = rest.find('\\') {
+        decoded.push_str(&rest[..backslash]);
+        rest = &rest[backslash + 1..];
+
+        match rest.strip_prefix('x').and_then(|after| after.get(..2)) {
+            Some(digits) => {
+                decoded.push(hex_escape_value(digits).expect("escapes were checked while lexing"));
+                rest = &rest[3..];
+            }
+            None => {
+                decoded.push('\\');
+                // Keeps the `$` of a `\$` from being read as part of a following escape
+                if let Some(after) = rest.strip_prefix('$') {
+                    decoded.push('$');
+                    rest = after;
+                }
+            }
+        }
+    }
+    decoded.push_str(rest);
+
+    Cow::Owned(decoded)
 }
 
-pub struct Lexer {
-    source: Cursor,
+/// Spells a decimal so that it lexes as the same decimal again: always positionally, as the lexer
+/// has no exponent syntax, and with a `.` even when it is a whole number
+pub(crate) fn decimal_spelling(n: f64) -> String {
+    let digits = n.to_string();
+    if digits.contains('.') {
+        digits
+    } else {
+        format!("{digits}.0")
+    }
+}
+
+/// Writes text back as the inside of a string literal that [decode_escapes] turns into the same
+/// text. A `"`, a `${` and a backslash that would start an escape or sit right before whatever
+/// follows the text are written as `\xNN` escapes, everything else as it is.
+pub(crate) fn encode_escapes(text: &str) -> Cow<'_, str> {
+    let needs_escape = |index: usize, ch: char| {
+        let next = text[index + ch.len_utf8()..].chars().next();
+        let after_backslash = text[..index].ends_with('\\');
+        match ch {
+            '"' => true,
+            '\\' => matches!(next, None | Some('x')),
+            '$' => next == Some('{') && !after_backslash,
+            _ => false,
+        }
+    };
+
+    if !text
+        .char_indices()
+        .any(|(index, ch)| needs_escape(index, ch))
+    {
+        return Cow::Borrowed(text);
+    }
+
+    let mut encoded = String::with_capacity(text.len());
+    for (index, ch) in text.char_indices() {
+        if needs_escape(index, ch) {
+            encoded.push_str(&format!("\\x{:02X}", u32::from(ch)));
+        } else {
+            encoded.push(ch);
+        }
+    }
+
+    Cow::Owned(encoded)
+}
+
+pub struct Lexer<'src> {
+    source: Cursor<'src>,
+    /// Byte offset of the start of the token being scanned
     start: usize,
-    current: usize,
     line: usize,
     col: usize,
+    options: LexerOptions,
+    /// Set once the `Eof` token has been yielded, so iteration stops afterwards
+    finished: bool,
+    /// A token lexed by [Lexer::peek_token] that has not been handed out yet
+    peeked: Option<Result<BorrowedToken<'src>>>,
+    /// Whether a `-` scanned now would be in prefix position, see
+    /// [LexerOptions::fold_negative_literals]
+    prefix_position: bool,
 }
 
-impl Lexer {
-    pub fn new(source: &str) -> Self {
+impl<'src> Lexer<'src> {
+    pub fn new(source: &'src str) -> Self {
+        Self::with_options(source, LexerOptions::default())
+    }
+
+    pub fn with_options(source: &'src str, options: LexerOptions) -> Self {
         Self {
             source: Cursor::new(source),
             start: 0,
-            current: 0,
             line: 1,
             col: 0,
+            options,
+            finished: false,
+            peeked: None,
+            prefix_position: true,
         }
     }
 
+    /// Starts lexing `source` from the beginning, as if this lexer had just been created for it
+    /// with the same options, so that one lexer can be reused to tokenize many sources in turn
+    pub fn reset(&mut self, source: &'src str) {
+        let options = std::mem::take(&mut self.options);
+        *self = Self::with_options(source, options);
+    }
+
+    /// Tokenizes the whole source. This never panics, whatever the input, every problem with the
+    /// source is reported as an error in place of the token it affects (see `fuzz/`).
     pub fn tokenize(&mut self) -> Vec<Result<Token>> {
-        let mut results = Vec::new();
+        let mut tokens = Vec::new();
+        self.tokenize_into(&mut tokens);
 
-        loop {
-            let token = self.next_token();
-            if let Ok(ref token) = token {
-                if token.ttype == TokenType::Eof {
-                    break;
-                }
+        tokens
+    }
+
+    /// Tokenizes the whole source like [tokenize](Self::tokenize), but into `buf`, replacing
+    /// whatever it held, so that one buffer can be reused across many sources without
+    /// reallocating
+    pub fn tokenize_into(&mut self, buf: &mut Vec<Result<Token>>) {
+        buf.clear();
+        buf.extend(
+            self.by_ref()
+                .filter(|token| {
+                    !matches!(
+                        token,
+                        Ok(Token {
+                            ttype: TokenType::Eof,
+                            ..
+                        })
+                    )
+                })
+                .map(|token| token.map(BorrowedToken::into_owned)),
+        );
+    }
+
+    /// Tokenizes the whole source like [tokenize](Self::tokenize), but with the tokens and the
+    /// errors in separate lists, each in source order. Lexing carries on after an error, so a
+    /// bad char only costs the token it appears in.
+    pub fn tokenize_with_recovery(&mut self) -> (Vec<Token>, Vec<LexerError>) {
+        let mut tokens = Vec::new();
+        let mut errors = Vec::new();
+
+        for result in self.tokenize_lazy() {
+            match result {
+                Ok(token) => tokens.push(token),
+                Err(error) => errors.push(error),
             }
-            results.push(token);
         }
 
-        results
+        (tokens, errors)
+    }
+
+    /// Tokenizes the source without copying it, identifier and string payloads are slices of the
+    /// source this lexer was created with. Only strings whose escapes had to be decoded own their
+    /// text.
+    pub fn tokenize_borrowed(&mut self) -> Vec<Result<BorrowedToken<'src>>> {
+        self.by_ref()
+            .filter(|token| {
+                !matches!(
+                    token,
+                    Ok(Token {
+                        ttype: TokenType::Eof,
+                        ..
+                    })
+                )
+            })
+            .collect()
+    }
+
+    /// Lexes tokens on demand, ending before the `Eof` like [tokenize](Self::tokenize). Unlike
+    /// iterating the lexer itself this only borrows it, so it can still be used once the caller
+    /// stops early, e.g. to read its [position](Self::position) or carry on lexing.
+    pub fn tokenize_lazy(&mut self) -> impl Iterator<Item = Result<Token>> + use<'_, 'src> {
+        self.by_ref()
+            .take_while(|token| {
+                !matches!(
+                    token,
+                    Ok(Token {
+                        ttype: TokenType::Eof,
+                        ..
+                    })
+                )
+            })
+            .map(|token| token.map(BorrowedToken::into_owned))
+    }
+
+    /// Pairs every token with its leading trivia, the source text between it and the previous
+    /// token exactly as written: whitespace, newlines and comments, along with a leading shebang.
+    /// Comments are therefore not yielded as tokens of their own, and whatever follows the last
+    /// token is the trivia of the `Eof`.
+    ///
+    /// A token that fails to lex and the trivia before it are not part of any trivia.
+    pub fn with_leading_trivia(
+        mut self,
+    ) -> impl Iterator<Item = Result<(&'src str, BorrowedToken<'src>)>> {
+        let source = self.source.as_str();
+        let mut trivia_start = self.source.offset();
+
+        std::iter::from_fn(move || loop {
+            match self.next()? {
+                Ok(token) if token.ttype.category() == TokenCategory::Comment => {}
+                Ok(token) => {
+                    let trivia = &source[trivia_start..token.span.start];
+                    trivia_start = token.span.end;
+                    return Some(Ok((trivia, token)));
+                }
+                Err(error) => {
+                    trivia_start = self.source.offset();
+                    return Some(Err(error));
+                }
+            }
+        })
+    }
+
+    /// The line and column of the last char the lexer consumed, `(1, 0)` before it has consumed
+    /// any. A token lexed by [peek_token](Self::peek_token) counts as consumed.
+    pub fn position(&self) -> (usize, usize) {
+        (self.line, self.col)
+    }
+
+    /// Lexes the next token without consuming it, so that it is returned again by the following
+    /// call to [Iterator::next]. Peeking repeatedly returns the same token.
+    pub fn peek_token(&mut self) -> core::result::Result<&BorrowedToken<'src>, &LexerError> {
+        let token = match self.peeked.take() {
+            Some(token) => token,
+            None => self.scan_token(),
+        };
+
+        self.peeked.insert(token).as_ref()
+    }
+
+    fn next_token(&mut self) -> Result<BorrowedToken<'src>> {
+        match self.peeked.take() {
+            Some(token) => token,
+            None => self.scan_token(),
+        }
     }
 
     #[inline]
-    fn next_token(&mut self) -> Result<Token> {
+    fn scan_token(&mut self) -> Result<BorrowedToken<'src>> {
+        if self.source.offset() == 0 {
+            self.skip_shebang();
+        }
+        self.skip_whitespace();
+        self.start = self.source.offset();
+
         let Some(ch) = self.advance() else {
             return Ok(Token {
                 ttype: TokenType::Eof,
                 line: self.line,
                 col: self.col,
+                span: Span::new(self.start, self.start),
             });
         };
 
         let ttype = match ch {
             '+' => match self.source.peek_nth(0) {
-                Some(next) if next == '=' => {
+                Some('=') => {
                     self.advance();
                     TokenType::AddEqual
                 }
                 _ => TokenType::Add,
             },
             '-' => match self.source.peek_nth(0) {
-                Some(next) if next == '=' => {
+                Some('=') => {
                     self.advance();
-                    TokenType::LessEqual
+                    TokenType::MinusEqual
                 }
-                Some(next) if next == '>' => {
+                Some('>') => {
                     self.advance();
                     TokenType::Arrow
                 }
-                _ => TokenType::Less,
+                Some('0'..='9')
+                    if self.options.fold_negative_literals
+                        && self.prefix_position
+                        && !self.number_has_suffix() =>
+                {
+                    self.advance();
+                    match self.handle_number()? {
+                        TokenType::Integer(n) => TokenType::NegativeInteger(n),
+                        TokenType::Decimal(n) => TokenType::Decimal(-n),
+                        ttype => unreachable!("numbers lex to integers or decimals, got {ttype:?}"),
+                    }
+                }
+                _ => TokenType::Minus,
             },
             '*' => match self.source.peek_nth(0) {
-                Some(next) if next == '=' => {
+                Some('*') => {
+                    self.advance();
+                    match self.source.peek_nth(0) {
+                        Some('=') => {
+                            self.advance();
+                            TokenType::StarStarEqual
+                        }
+                        _ => TokenType::StarStar,
+                    }
+                }
+                Some('=') => {
                     self.advance();
                     TokenType::StarEqual
                 }
                 _ => TokenType::Star,
             },
             '/' => match self.source.peek_nth(0) {
-                Some(next) if next == '=' => {
+                Some('=') => {
                     self.advance();
                     TokenType::SlashEqual
                 }
-                Some(next) if next == '/' => {
-                    self.handle_comment();
-                    TokenType::Comment
-                }
+                Some('/') => self.handle_comment(),
+                Some('*') => self.handle_block_comment()?,
                 _ => TokenType::Slash,
             },
             '%' => match self.source.peek_nth(0) {
-                Some(next) if next == '=' => {
+                Some('=') => {
                     self.advance();
                     TokenType::ModuloEqual
                 }
                 _ => TokenType::Modulo,
             },
             '!' => match self.source.peek_nth(0) {
-                Some(next) if next == '=' => {
+                Some('=') => {
                     self.advance();
                     TokenType::BangEqual
                 }
                 _ => TokenType::Bang,
             },
             '=' => match self.source.peek_nth(0) {
-                Some(next) if next == '=' => {
+                Some('=') => {
                     self.advance();
                     TokenType::EqualEqual
                 }
+                Some('>') => {
+                    self.advance();
+                    TokenType::FatArrow
+                }
                 _ => TokenType::Equal,
             },
             '>' => match self.source.peek_nth(0) {
-                Some(next) if next == '=' => {
+                Some('=') => {
                     self.advance();
                     TokenType::GreaterEqual
                 }
                 _ => TokenType::Greater,
             },
             '<' => match self.source.peek_nth(0) {
-                Some(next) if next == '=' => {
+                Some('=') => {
+                    self.advance();
+                    match self.source.peek_nth(0) {
+                        Some('>') => {
+                            self.advance();
+                            TokenType::Spaceship
+                        }
+                        _ => TokenType::LessEqual,
+                    }
+                }
+                Some('<') => {
                     self.advance();
-                    TokenType::LessEqual
+                    TokenType::LessLess
                 }
                 _ => TokenType::Less,
             },
             '&' => match self.source.peek_nth(0) {
-                Some(next) if next == '&' => {
+                Some('&') => {
                     self.advance();
                     TokenType::LogicalAnd
                 }
                 _ => TokenType::Ampersand,
             },
             '|' => match self.source.peek_nth(0) {
-                Some(next) if next == '|' => {
+                Some('|') => {
                     self.advance();
                     TokenType::LogicalOr
                 }
+                Some('>') => {
+                    self.advance();
+                    TokenType::PipeGreater
+                }
                 _ => TokenType::Bar,
             },
             ':' => TokenType::Colon,
             ',' => TokenType::Comma,
-            '.' => TokenType::Dot,
+            '.' => match self.source.peek_nth(0) {
+                Some('.') => {
+                    self.advance();
+                    match self.source.peek_nth(0) {
+                        Some('=') => {
+                            self.advance();
+                            TokenType::DotDotEqual
+                        }
+                        Some('.') => {
+                            self.advance();
+                            TokenType::DotDotDot
+                        }
+                        _ => TokenType::DotDot,
+                    }
+                }
+                _ => TokenType::Dot,
+            },
+            '?' => match self.source.peek_nth(0) {
+                Some('?') => {
+                    self.advance();
+                    match self.source.peek_nth(0) {
+                        Some('=') => {
+                            self.advance();
+                            TokenType::QuestionQuestionEqual
+                        }
+                        _ => TokenType::QuestionQuestion,
+                    }
+                }
+                _ => TokenType::Question,
+            },
+            '#' => TokenType::Hash,
+            '@' => TokenType::At,
+            '$' => TokenType::Dollar,
             '{' => TokenType::LeftBrace,
             '[' => TokenType::LeftBracket,
             '(' => TokenType::LeftParen,
@@ -184,52 +523,171 @@ impl Lexer {
 
             '\'' => self.handle_char()?,
             '"' => self.handle_string()?,
-            ch if ch.is_numeric() => self.handle_number()?,
+            ch if ch.is_ascii_digit() => self.handle_number()?,
             ch if ch.is_alphanumeric() || ch == '_' => self.handle_identifier()?,
 
-            '\n' => {
-                self.line += 1;
-                self.col = 1;
-                return self.next_token();
-            }
-
-            ch if ch.is_whitespace() => {
-                self.start = self.current;
-                return self.next_token();
-            }
             _ => {
                 return Err(LexerError::UnknownCharacter {
                     line: self.line,
                     col: self.col,
+                    offset: self.start,
                     character: ch,
                 })
             }
         };
 
-        self.start = self.current;
+        if ttype.category() != TokenCategory::Comment {
+            self.prefix_position = precedes_prefix(&ttype);
+        }
 
         Ok(Token {
             ttype,
             line: self.line,
             col: self.col,
+            span: Span::new(self.start, self.source.offset()),
         })
     }
 
-    fn handle_comment(&mut self) {
-        _ = self.source.next().expect("second slash in comment start");
+    fn skip_whitespace(&mut self) {
+        while let Some(byte) = self.source.peek_byte() {
+            if byte == b'\\' && self.options.line_continuations {
+                let rest = self.source.rest();
+                if rest.starts_with("\\\n") || rest.starts_with("\\\r") {
+                    // Only the backslash, the newline after it is whitespace anyway
+                    self.advance();
+                    continue;
+                }
+            }
+
+            let is_whitespace = if byte.is_ascii() {
+                byte.is_ascii_whitespace()
+            } else {
+                self.source.peek().is_some_and(char::is_whitespace)
+            };
+
+            if !is_whitespace {
+                break;
+            }
+            self.advance();
+        }
+    }
+
+    /// Skips a `#!` line at the very start of the source, so that scripts can be run directly.
+    /// `#![` is left alone as it starts an attribute rather than a shebang.
+    fn skip_shebang(&mut self) {
+        let rest = self.source.rest();
+        if !rest.starts_with("#!") || rest.starts_with("#![") {
+            return;
+        }
+
+        while self
+            .source
+            .peek()
+            .is_some_and(|ch| ch != '\n' && ch != '\r')
+        {
+            self.advance();
+        }
+    }
+
+    fn handle_comment(&mut self) -> TokenType<Cow<'src, str>> {
+        _ = self.advance().expect("second slash in comment start");
 
-        while let Some(ch) = self.source.peek_nth(1) {
-            if ch == '\n' {
+        while let Some(ch) = self.source.peek() {
+            if ch == '\n' || ch == '\r' {
                 break;
             }
             self.advance();
         }
+
+        TokenType::Comment(self.get_lexeme()[2..].into())
+    }
+
+    /// Lexes a `/* ... */` comment after its `/` has been consumed. Block comments do not nest, so
+    /// the first `*/` closes it.
+    fn handle_block_comment(&mut self) -> Result<TokenType<Cow<'src, str>>> {
+        let (open_line, open_col) = (self.line, self.col);
+        _ = self.advance().expect("star in block comment start");
+
+        loop {
+            match self.advance() {
+                Some('*') if self.source.peek() == Some('/') => {
+                    self.advance();
+                    break;
+                }
+                Some(_) => {}
+                None => {
+                    return Err(LexerError::UnterminatedComment {
+                        line: open_line,
+                        col: open_col,
+                        offset: self.start,
+                    })
+                }
+            }
+        }
+
+        let lexeme = self.get_lexeme();
+        Ok(TokenType::BlockComment(lexeme[2..lexeme.len() - 2].into()))
     }
 
-    fn handle_char(&mut self) -> Result<TokenType> {
+    fn handle_char(&mut self) -> Result<TokenType<Cow<'src, str>>> {
+        if self.source.rest().starts_with("\\x") {
+            self.advance();
+            return match self.hex_escape() {
+                Ok(ch) => {
+                    self.consume('\'')?;
+                    Ok(TokenType::Character(ch))
+                }
+                Err(error) => {
+                    // Skip the rest of the literal so that lexing carries on after it
+                    while self
+                        .source
+                        .peek()
+                        .is_some_and(|ch| ch != '\'' && !ch.is_whitespace())
+                    {
+                        self.advance();
+                    }
+                    if self.source.peek() == Some('\'') {
+                        self.advance();
+                    }
+                    Err(error)
+                }
+            };
+        }
+
+        // A literal holding several scalars, such as a flag emoji or a letter with a combining
+        // accent, would otherwise lex as its first scalar followed by a confusing error
+        let rest = self.source.rest();
+        let quoted = rest
+            .find(|ch: char| ch == '\'' || ch.is_whitespace())
+            .filter(|&end| rest[end..].starts_with('\''))
+            .map(|end| &rest[..end]);
+        if let Some(quoted) = quoted.filter(|quoted| quoted.chars().nth(1).is_some()) {
+            let (line, col) = (self.line, self.col);
+            for _ in 0..=quoted.chars().count() {
+                self.advance();
+            }
+
+            return Err(LexerError::MultiScalarChar {
+                line,
+                col,
+                offset: self.start,
+            });
+        }
+
+        // A name after the quote that is not closed straight away is a lifetime or loop label
+        if self
+            .source
+            .peek()
+            .is_some_and(|ch| ch.is_alphabetic() || ch == '_')
+            && self.source.peek_nth(1) != Some('\'')
+        {
+            return Ok(self.handle_lifetime());
+        }
+
         let ch = self.advance().ok_or_else(|| LexerError::UnexpectedEof {
             line: self.line,
             col: self.col,
+            offset: self.last_offset(),
             expected: "a character".to_owned(),
         })?;
 
@@ -238,42 +696,208 @@ impl Lexer {
         Ok(TokenType::Character(ch))
     }
 
-    fn handle_string(&mut self) -> Result<TokenType> {
-        while let Some(ch) = self.source.peek_nth(1) {
-            if ch == '\n' {
-                return Err(LexerError::UnexpectedCharacter {
-                    line: self.line,
-                    col: self.col,
-                    expected: "a valid string".to_owned(),
-                    got: ch,
-                });
-            }
+    /// Decodes a `\xNN` escape after its backslash has been consumed. The two hex digits must
+    /// encode an ASCII char, i.e. be at most `7F`, and are only consumed if they do.
+    fn hex_escape(&mut self) -> Result<char> {
+        let (line, col, offset) = (self.line, self.col, self.last_offset());
+        self.advance();
 
-            if ch == '"' {
-                break;
-            }
+        let value = self
+            .source
+            .rest()
+            .get(..2)
+            .and_then(hex_escape_value)
+            .ok_or(LexerError::InvalidHexEscape { line, col, offset })?;
+        self.advance();
+        self.advance();
 
-            // TODO: Implement escape sequences
+        Ok(value)
+    }
 
-            self.source.next();
+    /// Scans a lifetime or label such as `'outer` after its `'` has been consumed
+    fn handle_lifetime(&mut self) -> TokenType<Cow<'src, str>> {
+        while self
+            .source
+            .peek()
+            .is_some_and(|ch| ch.is_alphanumeric() || ch == '_')
+        {
+            self.advance();
         }
 
-        let string = self
+        let name = self
             .source
-            .substring(self.start, self.current)
+            .substring(self.start + 1, self.source.offset())
             .expect("start and current should be valid");
 
-        Ok(TokenType::String(string))
+        TokenType::Lifetime(name.into())
+    }
+
+    /// Scans a string literal after its opening `"` has been consumed. Strings may span multiple
+    /// lines, so an unterminated string runs all the way to the end of the file.
+    fn handle_string(&mut self) -> Result<TokenType<Cow<'src, str>>> {
+        // The opening quote has just been consumed, so this is where it sits
+        let (open_line, open_col) = (self.line, self.col);
+
+        let mut parts = Vec::new();
+        let mut literal_start = self.start + 1;
+        // Reported once the string is closed, so that lexing carries on after it
+        let mut escape_error = None;
+
+        loop {
+            match self.advance() {
+                Some('"') => break,
+                // An escaped `\$` is kept as written and never starts an interpolation
+                Some('\\') if self.source.peek() == Some('$') => {
+                    self.advance();
+                }
+                // Only checked here, the escapes are decoded once the string is closed
+                Some('\\') if self.source.peek() == Some('x') => {
+                    if let Err(error) = self.hex_escape() {
+                        escape_error.get_or_insert(error);
+                    }
+                }
+                Some('$') if self.source.peek() == Some('{') => {
+                    let dollar = self.source.offset() - 1;
+                    self.push_literal_part(&mut parts, literal_start, dollar);
+
+                    self.advance();
+                    let expr_start = self.source.offset();
+                    self.skip_interpolation()?;
+                    let expr = self
+                        .source
+                        .substring(expr_start, self.source.offset() - 1)
+                        .expect("interpolation bounds should be valid");
+                    parts.push(StringPart::Expr(expr.into()));
+
+                    literal_start = self.source.offset();
+                }
+                // TODO: Implement escape sequences
+                Some(_) => {}
+                None => {
+                    return Err(LexerError::UnterminatedString {
+                        line: open_line,
+                        col: open_col,
+                        offset: self.start,
+                    })
+                }
+            }
+        }
+
+        if let Some(error) = escape_error {
+            return Err(error);
+        }
+
+        // Strip the closing quote
+        let end = self.source.offset() - 1;
+
+        if let Some(max) = self.options.max_string_len {
+            if end - (self.start + 1) > max {
+                return Err(LexerError::StringTooLong {
+                    line: open_line,
+                    col: open_col,
+                    offset: self.start,
+                    max,
+                });
+            }
+        }
+
+        if parts.is_empty() {
+            return Ok(TokenType::String(self.string_text(literal_start, end)));
+        }
+
+        self.push_literal_part(&mut parts, literal_start, end);
+        Ok(TokenType::InterpolatedString(parts))
+    }
+
+    /// Pushes the text between two byte offsets of a string literal as a part, unless it is empty
+    fn push_literal_part(
+        &self,
+        parts: &mut Vec<StringPart<Cow<'src, str>>>,
+        start: usize,
+        end: usize,
+    ) {
+        if start < end {
+            parts.push(StringPart::Literal(self.string_text(start, end)));
+        }
+    }
+
+    /// The text of a string literal between two byte offsets, with its escapes decoded and its
+    /// `\r\n` line endings turned into `\n` unless [LexerOptions::keep_crlf_in_strings] is set
+    fn string_text(&self, start: usize, end: usize) -> Cow<'src, str> {
+        let text = decode_escapes(
+            self.source
+                .substring(start, end)
+                .expect("literal bounds should be valid"),
+        );
+
+        if self.options.keep_crlf_in_strings || !text.contains("\r\n") {
+            return text;
+        }
+        Cow::Owned(text.replace("\r\n", "\n"))
     }
 
-    fn handle_number(&mut self) -> Result<TokenType> {
+    /// Consumes an interpolated expression up to and including the `}` closing it, after its
+    /// `${` has been consumed. Braces and string literals inside the expression are skipped over
+    /// so that neither can close it early.
+    fn skip_interpolation(&mut self) -> Result<()> {
+        // The `{` has just been consumed, so the `$` sits right before it
+        let (open_line, open_col) = (self.line, self.col - 1);
+        let unterminated = LexerError::UnterminatedInterpolation {
+            line: open_line,
+            col: open_col,
+            offset: self.source.offset() - 2,
+        };
+
+        let mut depth = 0;
+        loop {
+            match self.advance() {
+                Some('{') => depth += 1,
+                Some('}') if depth == 0 => return Ok(()),
+                Some('}') => depth -= 1,
+                Some('"') => loop {
+                    match self.advance() {
+                        Some('"') => break,
+                        Some(_) => {}
+                        None => return Err(unterminated),
+                    }
+                },
+                Some(_) => {}
+                None => return Err(unterminated),
+            }
+        }
+    }
+
+    fn handle_number(&mut self) -> Result<TokenType<Cow<'src, str>>> {
+        // A folded negative literal starts with a `-`
+        if self.get_lexeme().trim_start_matches('-') == "0" {
+            match self.source.peek() {
+                Some('x' | 'X') => return self.handle_hex_number(),
+                Some('o' | 'O') => return self.handle_radix_integer(8),
+                Some('b' | 'B') => return self.handle_radix_integer(2),
+                _ => {}
+            }
+        }
+
         let mut is_float = false;
         while let Some(ch) = self.source.peek_nth(0) {
             match ch {
                 '0'..='9' => {
                     self.advance();
                 }
-                '.' => {
+                // A digit separator may only sit between two digits, e.g. `1_000`
+                '_' => {
+                    self.advance();
+                    if !self.source.peek().is_some_and(|ch| ch.is_ascii_digit()) {
+                        return Err(self.invalid_number_separator());
+                    }
+                }
+                // Only a digit may follow the decimal point, so that `0..10` lexes as a range
+                '.' if !is_float
+                    && self
+                        .source
+                        .peek_nth(1)
+                        .is_some_and(|ch| ch.is_ascii_digit()) =>
+                {
                     is_float = true;
                     self.advance();
                 }
@@ -281,93 +905,481 @@ impl Lexer {
             }
         }
 
-        let lexeme = self.get_lexeme();
-        let msg = "parsing should never fail";
+        // Letters straight after a literal are a type suffix such as `42u8`, or otherwise almost
+        // always a typo such as `123abc`, which would silently lex as a number followed by an
+        // identifier
+        if self.source.peek().is_some_and(char::is_alphabetic) {
+            let digits_end = self.source.offset();
+            self.skip_word();
+
+            let suffix = self
+                .source
+                .substring(digits_end, self.source.offset())
+                .unwrap_or_default();
+            if let Some(suffix) = FloatSuffix::from_spelling(suffix) {
+                return self.suffixed_decimal(digits_end, suffix);
+            }
+
+            return match IntSuffix::from_spelling(suffix) {
+                Some(suffix) if !is_float => self.suffixed_integer(digits_end, suffix),
+                _ => Err(self.invalid_number_suffix()),
+            };
+        }
+
+        // A folded negative literal starts with a `-`, the caller negates the value
+        let lexeme = strip_separators(self.get_lexeme().trim_start_matches('-'));
 
+        // The lexeme is only ever digits with at most one `.` between them once separators are
+        // stripped, so the only way parsing can fail is an integer that does not fit
         if is_float {
-            Ok(TokenType::Decimal(lexeme.parse::<f64>().expect(msg)))
+            let n = lexeme.parse::<f64>().unwrap_or(f64::INFINITY);
+            Ok(TokenType::Decimal(n))
         } else {
-            Ok(TokenType::Integer(lexeme.parse::<u64>().expect(msg)))
+            lexeme
+                .parse::<u64>()
+                .map(TokenType::Integer)
+                .map_err(|_| LexerError::IntegerOverflow {
+                    line: self.line,
+                    col: self.col,
+                    offset: self.last_offset(),
+                })
         }
     }
 
-    fn handle_identifier(&mut self) -> Result<TokenType> {
-        while let Some(ch) = self.source.peek_nth(0) {
-            if ch.is_alphanumeric() || ch == '_' {
-                self.advance();
-            } else {
-                break;
+    /// Lexes a hex literal after its leading `0`, either an integer such as `0xFF_FF` or a
+    /// C99-style hex float such as `0x1.8p3`, whose mantissa is in hex and whose exponent is a
+    /// power of two in decimal. A hex literal with a fraction must have an exponent.
+    fn handle_hex_number(&mut self) -> Result<TokenType<Cow<'src, str>>> {
+        self.advance();
+        let digits_start = self.source.offset();
+
+        let mut mantissa = 0f64;
+        let mut has_digits = false;
+        let mut fraction_digits: Option<i32> = None;
+        loop {
+            match self.source.peek() {
+                Some(ch) if ch.is_ascii_hexdigit() => {
+                    let digit = ch.to_digit(16).expect("char should be a hex digit");
+                    mantissa = mantissa * 16.0 + f64::from(digit);
+                    has_digits = true;
+                    if let Some(count) = fraction_digits.as_mut() {
+                        *count += 1;
+                    }
+                    self.advance();
+                }
+                Some('_') => {
+                    self.advance();
+                    if !has_digits || !self.source.peek().is_some_and(|ch| ch.is_ascii_hexdigit()) {
+                        return Err(self.invalid_number_separator());
+                    }
+                }
+                Some('.')
+                    if fraction_digits.is_none()
+                        && self
+                            .source
+                            .peek_nth(1)
+                            .is_some_and(|ch| ch.is_ascii_hexdigit()) =>
+                {
+                    fraction_digits = Some(0);
+                    self.advance();
+                }
+                _ => break,
             }
         }
 
-        let lexeme = self.get_lexeme();
+        let has_exponent = matches!(self.source.peek(), Some('p' | 'P'));
+        if has_digits && fraction_digits.is_none() && !has_exponent {
+            return self.radix_integer(digits_start, 16);
+        }
+        if !has_digits || !has_exponent {
+            self.skip_word();
+            return Err(if has_digits && fraction_digits.is_some() {
+                self.missing_hex_exponent()
+            } else {
+                self.invalid_number_suffix()
+            });
+        }
+        self.advance();
 
-        Ok(KEYWORDS
-            .get(lexeme.as_str())
-            .cloned()
-            .unwrap_or(TokenType::Identifier(lexeme)))
-    }
+        let negative = match self.source.peek() {
+            Some(sign @ ('+' | '-')) => {
+                self.advance();
+                sign == '-'
+            }
+            _ => false,
+        };
 
-    fn consume(&mut self, target: char) -> Result<char> {
-        let next = self
-            .source
-            .peek_nth(1)
-            .ok_or_else(|| LexerError::UnexpectedEof {
-                line: self.line,
-                col: self.col,
-                expected: format!("{target}"),
-            })?;
+        let mut exponent: i32 = 0;
+        let mut has_exponent = false;
+        while let Some(digit) = self.source.peek().and_then(|ch| ch.to_digit(10)) {
+            exponent = exponent.saturating_mul(10).saturating_add(digit as i32);
+            has_exponent = true;
+            self.advance();
+        }
 
-        if next != target {
-            return Err(LexerError::UnexpectedCharacter {
-                line: self.line,
-                col: self.col,
-                expected: target.to_string(),
-                got: next,
-            });
+        if !has_exponent {
+            self.skip_word();
+            return Err(self.missing_hex_exponent());
+        }
+        // Type suffixes are not allowed, `f` being a hex digit makes them ambiguous
+        if self.source.peek().is_some_and(char::is_alphabetic) {
+            self.skip_word();
+            return Err(self.invalid_number_suffix());
         }
 
-        Ok(self.advance().expect("next char should exist"))
-    }
+        if negative {
+            exponent = -exponent;
+        }
+        let exponent = exponent.saturating_sub(4 * fraction_digits.unwrap_or(0));
+        // Avoids `0 * inf` for a zero mantissa with a huge exponent
+        let value = if mantissa == 0.0 {
+            0.0
+        } else {
+            mantissa * 2f64.powi(exponent)
+        };
 
-    fn advance(&mut self) -> Option<char> {
-        let next = self.source.next();
-        next.inspect(|ch| match ch {
-            '\n' => {
-                self.col = 1;
-                self.line += 1;
-                self.current = 0;
-                self.start = 0;
-            }
-            _ => {
-                self.col += 1;
-                self.current += 1;
-            }
-        })
+        Ok(TokenType::Decimal(value))
     }
 
-    fn get_lexeme(&self) -> String {
-        self.source
-            .substring(self.start, self.current)
-            .expect("start and current should always be valid")
-    }
-}
+    /// Lexes an integer in base `radix` such as `0b1010` or `0o17` after its leading `0`
+    fn handle_radix_integer(&mut self, radix: u32) -> Result<TokenType<Cow<'src, str>>> {
+        self.advance();
+        let digits_start = self.source.offset();
 
-#[derive(Debug, PartialEq)]
-pub struct Token {
-    ttype: TokenType,
-    line: usize,
-    col: usize,
+        loop {
+            match self.source.peek() {
+                Some(ch) if ch.is_digit(radix) => {
+                    self.advance();
+                }
+                // A digit separator may only sit between two digits, e.g. `0b1_0`
+                Some('_') => {
+                    let after_digit = self.source.offset() > digits_start;
+                    self.advance();
+                    if !after_digit || !self.source.peek().is_some_and(|ch| ch.is_digit(radix)) {
+                        return Err(self.invalid_number_separator());
+                    }
+                }
+                _ => break,
+            }
+        }
+
+        if self.source.offset() == digits_start {
+            self.skip_word();
+            return Err(self.invalid_number_suffix());
+        }
+        self.radix_integer(digits_start, radix)
+    }
+
+    /// Finishes an integer in base `radix` whose digits start at the byte offset `digits_start`.
+    /// Such integers take no type suffix, `f` being a hex digit makes them ambiguous, so any
+    /// letters or digits straight after them are an invalid suffix, e.g. the `2` of `0b12`. Hex
+    /// digits ending in a float suffix such as `0xFFf32` are rejected too, rather than quietly
+    /// read as a different integer.
+    fn radix_integer(
+        &mut self,
+        digits_start: usize,
+        radix: u32,
+    ) -> Result<TokenType<Cow<'src, str>>> {
+        if self.source.peek().is_some_and(char::is_alphanumeric) {
+            self.skip_word();
+            return Err(self.invalid_number_suffix());
+        }
+
+        let digits = self
+            .source
+            .substring(digits_start, self.source.offset())
+            .expect("digits should be within the lexeme");
+        if radix == 16 && (digits.ends_with("f32") || digits.ends_with("f64")) {
+            return Err(self.invalid_number_suffix());
+        }
+
+        u64::from_str_radix(&strip_separators(digits), radix)
+            .map(TokenType::Integer)
+            .map_err(|_| LexerError::IntegerOverflow {
+                line: self.line,
+                col: self.col,
+                offset: self.last_offset(),
+            })
+    }
+
+    /// Consumes the rest of a run of alphanumeric chars and underscores
+    fn skip_word(&mut self) {
+        while self
+            .source
+            .peek()
+            .is_some_and(|ch| ch.is_alphanumeric() || ch == '_')
+        {
+            self.advance();
+        }
+    }
+
+    /// Consumes the rest of a number with a misplaced `_`, the last char consumed being the `_`
+    fn invalid_number_separator(&mut self) -> LexerError {
+        let (line, col, offset) = (self.line, self.col, self.last_offset());
+        loop {
+            match self.source.peek() {
+                Some(ch) if ch.is_alphanumeric() || ch == '_' => {}
+                Some('.')
+                    if self
+                        .source
+                        .peek_nth(1)
+                        .is_some_and(|ch| ch.is_ascii_digit()) => {}
+                _ => break,
+            }
+            self.advance();
+        }
+
+        LexerError::InvalidNumberSeparator { line, col, offset }
+    }
+
+    fn invalid_number_suffix(&self) -> LexerError {
+        LexerError::InvalidNumberSuffix {
+            line: self.line,
+            col: self.col,
+            offset: self.last_offset(),
+            lexeme: self.get_lexeme().to_owned(),
+        }
+    }
+
+    fn missing_hex_exponent(&self) -> LexerError {
+        LexerError::MissingHexExponent {
+            line: self.line,
+            col: self.col,
+            offset: self.last_offset(),
+            lexeme: self.get_lexeme().to_owned(),
+        }
+    }
+
+    /// Finishes an integer literal with a type suffix, whose digits end at the byte offset
+    /// `digits_end`
+    fn suffixed_integer(
+        &self,
+        digits_end: usize,
+        suffix: IntSuffix,
+    ) -> Result<TokenType<Cow<'src, str>>> {
+        let digits = self
+            .source
+            .substring(self.start, digits_end)
+            .expect("digits should be within the lexeme");
+
+        match strip_separators(digits).parse::<u64>() {
+            Ok(value) if value <= suffix.max() => Ok(TokenType::SuffixedInteger { value, suffix }),
+            Ok(_) => Err(LexerError::NumberOutOfRange {
+                line: self.line,
+                col: self.col,
+                offset: self.last_offset(),
+                lexeme: self.get_lexeme().to_owned(),
+            }),
+            Err(_) => Err(LexerError::IntegerOverflow {
+                line: self.line,
+                col: self.col,
+                offset: self.last_offset(),
+            }),
+        }
+    }
+
+    /// Finishes a literal with a float type suffix, which makes it a float even without a
+    /// fractional part, e.g. `2f64`
+    fn suffixed_decimal(
+        &self,
+        digits_end: usize,
+        suffix: FloatSuffix,
+    ) -> Result<TokenType<Cow<'src, str>>> {
+        let digits = self
+            .source
+            .substring(self.start, digits_end)
+            .expect("digits should be within the lexeme");
+        let value = strip_separators(digits)
+            .parse::<f64>()
+            .unwrap_or(f64::INFINITY);
+
+        if suffix == FloatSuffix::F32 && (value as f32).is_infinite() {
+            return Err(LexerError::NumberOutOfRange {
+                line: self.line,
+                col: self.col,
+                offset: self.last_offset(),
+                lexeme: self.get_lexeme().to_owned(),
+            });
+        }
+
+        Ok(TokenType::SuffixedDecimal { value, suffix })
+    }
+
+    /// Whether the number starting at the next char ends in letters, such as a type suffix. The
+    /// letters of a hex, octal or binary literal are part of it, such literals never take one.
+    fn number_has_suffix(&self) -> bool {
+        let rest = self.source.rest();
+        if rest.starts_with('0') && rest[1..].starts_with(['x', 'X', 'o', 'O', 'b', 'B']) {
+            return false;
+        }
+        let digits = |s: &str| {
+            s.trim_start_matches(|ch: char| ch.is_ascii_digit() || ch == '_')
+                .len()
+        };
+
+        let mut after = &rest[rest.len() - digits(rest)..];
+        if let Some(fraction) = after.strip_prefix('.') {
+            if fraction.starts_with(|ch: char| ch.is_ascii_digit()) {
+                after = &fraction[fraction.len() - digits(fraction)..];
+            }
+        }
+
+        after.starts_with(char::is_alphabetic)
+    }
+
+    fn handle_identifier(&mut self) -> Result<TokenType<Cow<'src, str>>> {
+        while let Some(byte) = self.source.peek_byte() {
+            let is_identifier = if byte.is_ascii() {
+                byte.is_ascii_alphanumeric() || byte == b'_'
+            } else {
+                self.source.peek().is_some_and(char::is_alphanumeric)
+            };
+
+            if !is_identifier {
+                break;
+            }
+            self.advance();
+        }
+
+        let lexeme = self.get_lexeme();
+
+        Ok(keyword(lexeme).unwrap_or(TokenType::Identifier(lexeme.into())))
+    }
+
+    fn consume(&mut self, target: char) -> Result<char> {
+        let next = self
+            .source
+            .peek()
+            .ok_or_else(|| LexerError::UnexpectedEof {
+                line: self.line,
+                col: self.col,
+                offset: self.last_offset(),
+                expected: format!("{target}"),
+            })?;
+
+        if next != target {
+            return Err(LexerError::UnexpectedCharacter {
+                line: self.line,
+                col: self.col,
+                offset: self.last_offset(),
+                expected: target.to_string(),
+                got: next,
+            });
+        }
+
+        Ok(self.advance().expect("next char should exist"))
+    }
+
+    fn advance(&mut self) -> Option<char> {
+        let next = self.source.next();
+        next.inspect(|ch| match ch {
+            // A `\r` breaks the line by itself unless it starts a `\r\n`, which breaks it once
+            '\n' | '\r' if *ch == '\n' || self.source.peek_byte() != Some(b'\n') => {
+                self.col = 0;
+                self.line += 1;
+            }
+            '\r' => {}
+            ch => self.col += self.options.column_encoding.width(*ch),
+        })
+    }
+
+    /// The byte offset of the last char consumed, which is where `line` and `col` point
+    fn last_offset(&self) -> usize {
+        let last = self.get_lexeme().chars().next_back();
+        self.source.offset() - last.map_or(0, char::len_utf8)
+    }
+
+    fn get_lexeme(&self) -> &'src str {
+        self.source
+            .substring(self.start, self.source.offset())
+            .expect("start and current should always be valid")
+    }
+}
+
+/// Yields every token followed by exactly one `Eof`, after which iteration ends. Errors are
+/// yielded in place of the token that failed to lex and lexing carries on after them.
+impl<'src> Iterator for Lexer<'src> {
+    type Item = Result<BorrowedToken<'src>>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.finished {
+            return None;
+        }
+
+        let token = self.next_token();
+        if matches!(
+            token,
+            Ok(Token {
+                ttype: TokenType::Eof,
+                ..
+            })
+        ) {
+            self.finished = true;
+        }
+
+        Some(token)
+    }
+}
+
+impl std::iter::FusedIterator for Lexer<'_> {}
+
+/// A token whose identifier and string payloads are of type `S`, owned [String]s by default
+#[derive(Debug, PartialEq, Clone)]
+pub struct Token<S = String> {
+    pub ttype: TokenType<S>,
+    pub line: usize,
+    pub col: usize,
+    pub span: Span,
 }
 
-impl std::fmt::Display for Token {
+/// A [Token] borrowing its payloads from the source it was lexed from, unless lexing changed
+/// them, e.g. by decoding the escapes of a string
+pub type BorrowedToken<'src> = Token<Cow<'src, str>>;
+
+impl BorrowedToken<'_> {
+    pub fn into_owned(self) -> Token {
+        Token {
+            ttype: self.ttype.into_owned(),
+            line: self.line,
+            col: self.col,
+            span: self.span,
+        }
+    }
+}
+
+impl<S: PartialEq> Token<S> {
+    /// Whether both tokens are of the same type, wherever they are in the source
+    pub fn same_type(&self, other: &Self) -> bool {
+        self.ttype == other.ttype
+    }
+}
+
+impl<S: std::fmt::Debug> std::fmt::Display for Token<S> {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         write!(f, "({}:{} {:?})", self.line, self.col, self.ttype)
     }
 }
 
+/// A piece of an interpolated string literal
+#[derive(Debug, PartialEq, Clone)]
+pub enum StringPart<S = String> {
+    /// Text outside of any `${...}`, with its `\xNN` escapes decoded
+    Literal(S),
+    /// The source of an expression between `${` and `}`
+    Expr(S),
+}
+
+impl StringPart<Cow<'_, str>> {
+    pub fn into_owned(self) -> StringPart {
+        match self {
+            Self::Literal(literal) => StringPart::Literal(literal.into_owned()),
+            Self::Expr(expr) => StringPart::Expr(expr.into_owned()),
+        }
+    }
+}
+
 #[derive(Debug, PartialEq, Clone)]
-pub enum TokenType {
+pub enum TokenType<S = String> {
     Add,
     AddEqual,
     Minus,
@@ -378,6 +1390,8 @@ pub enum TokenType {
     SlashEqual,
     Star,
     StarEqual,
+    StarStar,
+    StarStarEqual,
 
     Bang,
     BangEqual,
@@ -387,15 +1401,41 @@ pub enum TokenType {
     GreaterEqual,
     Less,
     LessEqual,
+    /// <<, shifting left
+    LessLess,
+    /// <=>, comparing to less, equal or greater in one go
+    Spaceship,
 
     Ampersand,
     /// ->
     Arrow,
+    /// @, binding a name to what a pattern matched
+    At,
+    /// $, reserved for interpolating names in macros
+    Dollar,
+    /// =>
+    FatArrow,
     /// |
     Bar,
+    /// |>, piping the value on its left into the function on its right
+    PipeGreater,
+    /// ?
+    Question,
+    /// ??, the value on its left unless that is null
+    QuestionQuestion,
+    /// ??=, assigning only if the target is null
+    QuestionQuestionEqual,
     Colon,
     Comma,
     Dot,
+    /// ..
+    DotDot,
+    /// ..=
+    DotDotEqual,
+    /// ..., for variadic parameters and spreading
+    DotDotDot,
+    /// #
+    Hash,
     LeftBrace,
     LeftBracket,
     LeftParen,
@@ -407,11 +1447,31 @@ pub enum TokenType {
     Semicolon,
 
     // Literals
+    /// `true` or `false`
+    Bool(bool),
     Character(char),
     Decimal(f64),
-    Identifier(String),
+    Identifier(S),
     Integer(u64),
-    String(String),
+    String(S),
+    /// A string literal containing at least one `${...}`, e.g. `"hello ${name}"`, split into its
+    /// parts in source order
+    InterpolatedString(Vec<StringPart<S>>),
+    /// An integer with a type suffix, e.g. `42u8`
+    SuffixedInteger {
+        value: u64,
+        suffix: IntSuffix,
+    },
+    /// A float with a type suffix, e.g. `3.14f32` or `2f64`
+    SuffixedDecimal {
+        value: f64,
+        suffix: FloatSuffix,
+    },
+    /// A negative integer such as `-5` holding its magnitude, only produced with
+    /// [LexerOptions::fold_negative_literals]
+    NegativeInteger(u64),
+    /// A lifetime or loop label, e.g. `'outer`, without the leading `'`
+    Lifetime(S),
 
     Primitive(PrimitiveType),
 
@@ -421,18 +1481,449 @@ pub enum TokenType {
     Default,
     Else,
     Enum,
-    False,
     Fn,
     For,
     If,
+    In,
+    Loop,
     Match,
+    Null,
     Return,
     Struct,
     Switch,
-    True,
     Var,
     While,
+    Underscore,
+
+    /// A `// ...` comment, holding the text after the `//` up to the end of the line
+    Comment(S),
+    /// A `/* ... */` comment, holding the text between the delimiters
+    BlockComment(S),
+    Eof,
+}
+
+impl<S> TokenType<S> {
+    /// Buckets the token into a broad [TokenCategory], e.g. for syntax highlighting
+    pub fn category(&self) -> TokenCategory {
+        match self {
+            Self::Add
+            | Self::AddEqual
+            | Self::Minus
+            | Self::MinusEqual
+            | Self::Modulo
+            | Self::ModuloEqual
+            | Self::Slash
+            | Self::SlashEqual
+            | Self::Star
+            | Self::StarEqual
+            | Self::StarStar
+            | Self::StarStarEqual
+            | Self::Bang
+            | Self::BangEqual
+            | Self::Equal
+            | Self::EqualEqual
+            | Self::Greater
+            | Self::GreaterEqual
+            | Self::Less
+            | Self::LessEqual
+            | Self::LessLess
+            | Self::Spaceship
+            | Self::Ampersand
+            | Self::Bar
+            | Self::PipeGreater
+            | Self::Question
+            | Self::QuestionQuestion
+            | Self::QuestionQuestionEqual
+            | Self::Dot
+            | Self::DotDot
+            | Self::DotDotEqual
+            | Self::DotDotDot
+            | Self::LogicalAnd
+            | Self::LogicalOr => TokenCategory::Operator,
+
+            Self::Arrow
+            | Self::At
+            | Self::Dollar
+            | Self::FatArrow
+            | Self::Colon
+            | Self::Comma
+            | Self::Hash
+            | Self::LeftBrace
+            | Self::LeftBracket
+            | Self::LeftParen
+            | Self::RightBrace
+            | Self::RightBracket
+            | Self::RightParen
+            | Self::Semicolon => TokenCategory::Delimiter,
+
+            Self::Character(_)
+            | Self::Decimal(_)
+            | Self::Integer(_)
+            | Self::NegativeInteger(_)
+            | Self::SuffixedInteger { .. }
+            | Self::SuffixedDecimal { .. }
+            | Self::String(_)
+            | Self::InterpolatedString(_)
+            | Self::Bool(_) => TokenCategory::Literal,
+
+            Self::Identifier(_) | Self::Lifetime(_) => TokenCategory::Identifier,
+
+            Self::Primitive(_)
+            | Self::Break
+            | Self::Continue
+            | Self::Default
+            | Self::Else
+            | Self::Enum
+            | Self::Fn
+            | Self::For
+            | Self::If
+            | Self::In
+            | Self::Loop
+            | Self::Match
+            | Self::Null
+            | Self::Return
+            | Self::Struct
+            | Self::Switch
+            | Self::Var
+            | Self::While
+            | Self::Underscore => TokenCategory::Keyword,
+
+            Self::Comment(_) | Self::BlockComment(_) => TokenCategory::Comment,
+            Self::Eof => TokenCategory::Eof,
+        }
+    }
+
+    pub fn is_keyword(&self) -> bool {
+        self.category() == TokenCategory::Keyword
+    }
+
+    pub fn is_operator(&self) -> bool {
+        self.category() == TokenCategory::Operator
+    }
+
+    pub fn is_literal(&self) -> bool {
+        self.category() == TokenCategory::Literal
+    }
+}
+
+impl<S: PartialEq> TokenType<S> {
+    /// Compares two token types structurally, treating any two numeric literals of the same kind
+    /// as equal regardless of their values
+    pub fn eq_ignoring_values(&self, other: &Self) -> bool {
+        match (self, other) {
+            (Self::Decimal(_), Self::Decimal(_))
+            | (Self::Integer(_), Self::Integer(_))
+            | (Self::NegativeInteger(_), Self::NegativeInteger(_)) => true,
+            (Self::SuffixedInteger { suffix: a, .. }, Self::SuffixedInteger { suffix: b, .. }) => {
+                a == b
+            }
+            (Self::SuffixedDecimal { suffix: a, .. }, Self::SuffixedDecimal { suffix: b, .. }) => {
+                a == b
+            }
+            _ => self == other,
+        }
+    }
+}
+
+impl TokenType {
+    /// Whether `word` lexes as a keyword rather than an identifier, so that it can never name
+    /// anything. This includes `true`, `false`, `_` and the primitive type names.
+    pub fn is_reserved_word(word: &str) -> bool {
+        keyword::<String>(word).is_some()
+    }
+}
+
+/// Renders the token as it would be spelled in source code
+impl<S: std::fmt::Display> std::fmt::Display for TokenType<S> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let spelling = match self {
+            Self::Bool(b) => return write!(f, "{b}"),
+            Self::Character(c) => return write!(f, "'{c}'"),
+            Self::Decimal(n) => return write!(f, "{}", decimal_spelling(*n)),
+            Self::Identifier(identifier) => return write!(f, "{identifier}"),
+            Self::Integer(n) => return write!(f, "{n}"),
+            Self::NegativeInteger(n) => return write!(f, "-{n}"),
+            Self::SuffixedInteger { value, suffix } => return write!(f, "{value}{suffix}"),
+            Self::SuffixedDecimal { value, suffix } => {
+                return write!(f, "{}{suffix}", decimal_spelling(*value))
+            }
+            Self::String(string) => {
+                return write!(f, "\"{}\"", encode_escapes(&string.to_string()))
+            }
+            Self::InterpolatedString(parts) => {
+                write!(f, "\"")?;
+                for part in parts {
+                    match part {
+                        StringPart::Literal(literal) => {
+                            write!(f, "{}", encode_escapes(&literal.to_string()))?
+                        }
+                        StringPart::Expr(expr) => write!(f, "${{{expr}}}")?,
+                    }
+                }
+                return write!(f, "\"");
+            }
+            Self::Lifetime(name) => return write!(f, "'{name}"),
+            Self::Comment(text) => return write!(f, "//{text}"),
+            Self::BlockComment(text) => return write!(f, "/*{text}*/"),
+            Self::Primitive(primitive) => return write!(f, "{primitive}"),
+
+            Self::Add => "+",
+            Self::AddEqual => "+=",
+            Self::Minus => "-",
+            Self::MinusEqual => "-=",
+            Self::Modulo => "%",
+            Self::ModuloEqual => "%=",
+            Self::Slash => "/",
+            Self::SlashEqual => "/=",
+            Self::Star => "*",
+            Self::StarEqual => "*=",
+            Self::StarStar => "**",
+            Self::StarStarEqual => "**=",
+            Self::Bang => "!",
+            Self::BangEqual => "!=",
+            Self::Equal => "=",
+            Self::EqualEqual => "==",
+            Self::Greater => ">",
+            Self::GreaterEqual => ">=",
+            Self::Less => "<",
+            Self::LessEqual => "<=",
+            Self::LessLess => "<<",
+            Self::Spaceship => "<=>",
+            Self::Ampersand => "&",
+            Self::Arrow => "->",
+            Self::FatArrow => "=>",
+            Self::Bar => "|",
+            Self::PipeGreater => "|>",
+            Self::Question => "?",
+            Self::QuestionQuestion => "??",
+            Self::QuestionQuestionEqual => "??=",
+            Self::Colon => ":",
+            Self::Comma => ",",
+            Self::Dot => ".",
+            Self::DotDot => "..",
+            Self::DotDotEqual => "..=",
+            Self::DotDotDot => "...",
+            Self::Hash => "#",
+            Self::At => "@",
+            Self::Dollar => "$",
+            Self::LeftBrace => "{",
+            Self::LeftBracket => "[",
+            Self::LeftParen => "(",
+            Self::LogicalAnd => "&&",
+            Self::LogicalOr => "||",
+            Self::RightBrace => "}",
+            Self::RightBracket => "]",
+            Self::RightParen => ")",
+            Self::Semicolon => ";",
+            Self::Break => "break",
+            Self::Continue => "continue",
+            Self::Default => "default",
+            Self::Else => "else",
+            Self::Enum => "enum",
+            Self::Fn => "fn",
+            Self::For => "for",
+            Self::If => "if",
+            Self::In => "in",
+            Self::Loop => "loop",
+            Self::Match => "match",
+            Self::Null => "null",
+            Self::Return => "return",
+            Self::Struct => "struct",
+            Self::Switch => "switch",
+            Self::Var => "var",
+            Self::While => "while",
+            Self::Underscore => "_",
+            Self::Eof => "",
+        };
+
+        f.write_str(spelling)
+    }
+}
+
+/// Renders tokens back into source code, separating them with single spaces.
+///
+/// Whitespace is normalized rather than preserved, so the output is not the original text, but
+/// lexing it again yields the same token types.
+pub fn tokens_to_source<S: std::fmt::Display>(tokens: &[Token<S>]) -> String {
+    let mut source = String::new();
+
+    for token in tokens {
+        if !source.is_empty() && !source.ends_with('\n') {
+            source.push(' ');
+        }
+        source.push_str(&token.ttype.to_string());
+
+        // A comment runs to the end of its line, so nothing may follow it on the same line
+        if matches!(token.ttype, TokenType::Comment(_)) {
+            source.push('\n');
+        }
+    }
+
+    source
+}
+
+/// Whether `tokens` are of exactly the types in `ttypes`, in order, ignoring their positions
+pub fn tokens_match<S: PartialEq>(tokens: &[Token<S>], ttypes: &[TokenType<S>]) -> bool {
+    tokens.len() == ttypes.len()
+        && tokens
+            .iter()
+            .zip(ttypes)
+            .all(|(token, ttype)| &token.ttype == ttype)
+}
+
+/// Counts the tokens and errors in `source` without keeping any token, for when only the size of
+/// the output matters. Identifiers and strings are never copied out of the source, which makes
+/// this much cheaper than `tokenize().len()`. The final `Eof` is not counted, as in
+/// [Lexer::tokenize].
+pub fn count_tokens(source: &str) -> (usize, usize) {
+    let mut tokens = 0;
+    let mut errors = 0;
+
+    for token in Lexer::new(source) {
+        match token {
+            Ok(Token {
+                ttype: TokenType::Eof,
+                ..
+            }) => {}
+            Ok(_) => tokens += 1,
+            Err(_) => errors += 1,
+        }
+    }
+
+    (tokens, errors)
+}
+
+/// The types of the tokens in `source` without their positions, stopping at the first error. The
+/// final `Eof` is left out, as in [Lexer::tokenize].
+pub fn tokenize_types(source: &str) -> Result<Vec<TokenType>> {
+    Lexer::new(source)
+        .filter(|token| !matches!(token, Ok(token) if token.ttype == TokenType::Eof))
+        .map(|token| token.map(|token| token.ttype.into_owned()))
+        .collect()
+}
+
+/// Formats lexer output one token per line as `line:col start..end Type`, with the payload of
+/// literals and identifiers decoded, and errors as `error: message`.
+///
+/// The format is stable so that it can be compared against a snapshot in tests.
+pub fn format_tokens<S: std::fmt::Debug>(tokens: &[Result<Token<S>>]) -> String {
+    let mut formatted = String::new();
+
+    for token in tokens {
+        let line = match token {
+            Ok(token) => format!(
+                "{}:{} {}..{} {:?}",
+                token.line, token.col, token.span.start, token.span.end, token.ttype
+            ),
+            Err(error) => format!("error: {error}"),
+        };
+        formatted.push_str(&line);
+        formatted.push('\n');
+    }
+
+    formatted
+}
 
+impl TokenType<Cow<'_, str>> {
+    pub fn into_owned(self) -> TokenType {
+        match self {
+            Self::Identifier(identifier) => TokenType::Identifier(identifier.into_owned()),
+            Self::String(string) => TokenType::String(string.into_owned()),
+            Self::InterpolatedString(parts) => TokenType::InterpolatedString(
+                parts.into_iter().map(StringPart::into_owned).collect(),
+            ),
+            Self::Lifetime(name) => TokenType::Lifetime(name.into_owned()),
+            Self::Comment(text) => TokenType::Comment(text.into_owned()),
+            Self::BlockComment(text) => TokenType::BlockComment(text.into_owned()),
+
+            Self::Add => TokenType::Add,
+            Self::AddEqual => TokenType::AddEqual,
+            Self::Minus => TokenType::Minus,
+            Self::MinusEqual => TokenType::MinusEqual,
+            Self::Modulo => TokenType::Modulo,
+            Self::ModuloEqual => TokenType::ModuloEqual,
+            Self::Slash => TokenType::Slash,
+            Self::SlashEqual => TokenType::SlashEqual,
+            Self::Star => TokenType::Star,
+            Self::StarEqual => TokenType::StarEqual,
+            Self::StarStar => TokenType::StarStar,
+            Self::StarStarEqual => TokenType::StarStarEqual,
+            Self::Bang => TokenType::Bang,
+            Self::BangEqual => TokenType::BangEqual,
+            Self::Equal => TokenType::Equal,
+            Self::EqualEqual => TokenType::EqualEqual,
+            Self::Greater => TokenType::Greater,
+            Self::GreaterEqual => TokenType::GreaterEqual,
+            Self::Less => TokenType::Less,
+            Self::LessEqual => TokenType::LessEqual,
+            Self::LessLess => TokenType::LessLess,
+            Self::Spaceship => TokenType::Spaceship,
+            Self::Ampersand => TokenType::Ampersand,
+            Self::Arrow => TokenType::Arrow,
+            Self::FatArrow => TokenType::FatArrow,
+            Self::Bar => TokenType::Bar,
+            Self::PipeGreater => TokenType::PipeGreater,
+            Self::Question => TokenType::Question,
+            Self::QuestionQuestion => TokenType::QuestionQuestion,
+            Self::QuestionQuestionEqual => TokenType::QuestionQuestionEqual,
+            Self::Colon => TokenType::Colon,
+            Self::Comma => TokenType::Comma,
+            Self::Dot => TokenType::Dot,
+            Self::DotDot => TokenType::DotDot,
+            Self::DotDotEqual => TokenType::DotDotEqual,
+            Self::DotDotDot => TokenType::DotDotDot,
+            Self::Hash => TokenType::Hash,
+            Self::At => TokenType::At,
+            Self::Dollar => TokenType::Dollar,
+            Self::LeftBrace => TokenType::LeftBrace,
+            Self::LeftBracket => TokenType::LeftBracket,
+            Self::LeftParen => TokenType::LeftParen,
+            Self::LogicalAnd => TokenType::LogicalAnd,
+            Self::LogicalOr => TokenType::LogicalOr,
+            Self::RightBrace => TokenType::RightBrace,
+            Self::RightBracket => TokenType::RightBracket,
+            Self::RightParen => TokenType::RightParen,
+            Self::Semicolon => TokenType::Semicolon,
+            Self::Character(c) => TokenType::Character(c),
+            Self::Decimal(n) => TokenType::Decimal(n),
+            Self::Integer(n) => TokenType::Integer(n),
+            Self::NegativeInteger(n) => TokenType::NegativeInteger(n),
+            Self::SuffixedInteger { value, suffix } => TokenType::SuffixedInteger { value, suffix },
+            Self::SuffixedDecimal { value, suffix } => TokenType::SuffixedDecimal { value, suffix },
+            Self::Primitive(primitive) => TokenType::Primitive(primitive),
+            Self::Break => TokenType::Break,
+            Self::Continue => TokenType::Continue,
+            Self::Default => TokenType::Default,
+            Self::Else => TokenType::Else,
+            Self::Enum => TokenType::Enum,
+            Self::Fn => TokenType::Fn,
+            Self::For => TokenType::For,
+            Self::If => TokenType::If,
+            Self::In => TokenType::In,
+            Self::Loop => TokenType::Loop,
+            Self::Match => TokenType::Match,
+            Self::Null => TokenType::Null,
+            Self::Return => TokenType::Return,
+            Self::Struct => TokenType::Struct,
+            Self::Switch => TokenType::Switch,
+            Self::Bool(b) => TokenType::Bool(b),
+            Self::Var => TokenType::Var,
+            Self::While => TokenType::While,
+            Self::Underscore => TokenType::Underscore,
+            Self::Eof => TokenType::Eof,
+        }
+    }
+}
+
+/// Broad classification of a [TokenType]
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub enum TokenCategory {
+    Keyword,
+    Operator,
+    Literal,
+    Identifier,
+    /// Punctuation that separates or groups other tokens, e.g. `(`, `;` and `->`
+    Delimiter,
     Comment,
     Eof,
 }
@@ -442,7 +1933,2388 @@ pub enum PrimitiveType {
     // Types
     Int,
     UInt,
+    /// A signed integer the size of a pointer on the target platform
+    Isize,
+    /// An unsigned integer the size of a pointer on the target platform
+    Usize,
     Float,
     Bool,
     Char,
+    /// The type of functions that return nothing, spelled `void` rather than `()`
+    Void,
+}
+
+impl PrimitiveType {
+    /// The primitive type spelled exactly as `spelling`, e.g. `int`
+    pub fn from_spelling(spelling: &str) -> Option<Self> {
+        match keyword::<&str>(spelling) {
+            Some(TokenType::Primitive(primitive)) => Some(primitive),
+            _ => None,
+        }
+    }
+
+    /// The number of bytes a value of this type occupies, platform sized integers take the width
+    /// of a pointer on the host
+    pub fn size_bytes(&self) -> usize {
+        match self {
+            Self::Int | Self::UInt | Self::Float => 8,
+            Self::Isize | Self::Usize => std::mem::size_of::<usize>(),
+            Self::Bool => 1,
+            Self::Char => 4,
+            Self::Void => 0,
+        }
+    }
+}
+
+/// Removes the digit separators from a number, e.g. `1_000` to `1000`
+fn strip_separators(number: &str) -> Cow<'_, str> {
+    if number.contains('_') {
+        Cow::Owned(number.replace('_', ""))
+    } else {
+        Cow::Borrowed(number)
+    }
+}
+
+/// The type suffix of an integer literal such as `42u8`
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub enum IntSuffix {
+    I8,
+    I16,
+    I32,
+    I64,
+    U8,
+    U16,
+    U32,
+    U64,
+    /// `int`, the same as `i64`
+    Int,
+    /// `uint`, the same as `u64`
+    UInt,
+}
+
+impl IntSuffix {
+    pub fn from_spelling(spelling: &str) -> Option<Self> {
+        let suffix = match spelling {
+            "i8" => Self::I8,
+            "i16" => Self::I16,
+            "i32" => Self::I32,
+            "i64" => Self::I64,
+            "u8" => Self::U8,
+            "u16" => Self::U16,
+            "u32" => Self::U32,
+            "u64" => Self::U64,
+            "int" => Self::Int,
+            "uint" => Self::UInt,
+            _ => return None,
+        };
+
+        Some(suffix)
+    }
+
+    /// The largest value a literal with this suffix may have
+    pub fn max(self) -> u64 {
+        match self {
+            Self::I8 => i8::MAX as u64,
+            Self::I16 => i16::MAX as u64,
+            Self::I32 => i32::MAX as u64,
+            Self::I64 | Self::Int => i64::MAX as u64,
+            Self::U8 => u8::MAX.into(),
+            Self::U16 => u16::MAX.into(),
+            Self::U32 => u32::MAX.into(),
+            Self::U64 | Self::UInt => u64::MAX,
+        }
+    }
+}
+
+impl std::fmt::Display for IntSuffix {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let spelling = match self {
+            Self::I8 => "i8",
+            Self::I16 => "i16",
+            Self::I32 => "i32",
+            Self::I64 => "i64",
+            Self::U8 => "u8",
+            Self::U16 => "u16",
+            Self::U32 => "u32",
+            Self::U64 => "u64",
+            Self::Int => "int",
+            Self::UInt => "uint",
+        };
+
+        f.write_str(spelling)
+    }
+}
+
+/// The type suffix of a float literal such as `3.14f32`
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub enum FloatSuffix {
+    F32,
+    F64,
+}
+
+impl FloatSuffix {
+    pub fn from_spelling(spelling: &str) -> Option<Self> {
+        match spelling {
+            "f32" => Some(Self::F32),
+            "f64" => Some(Self::F64),
+            _ => None,
+        }
+    }
+}
+
+impl std::fmt::Display for FloatSuffix {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::F32 => f.write_str("f32"),
+            Self::F64 => f.write_str("f64"),
+        }
+    }
+}
+
+impl std::fmt::Display for PrimitiveType {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let spelling = match self {
+            Self::Int => "int",
+            Self::UInt => "uint",
+            Self::Isize => "isize",
+            Self::Usize => "usize",
+            Self::Float => "float",
+            Self::Bool => "bool",
+            Self::Char => "char",
+            Self::Void => "void",
+        };
+
+        f.write_str(spelling)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{options::ColumnEncoding, *};
+
+    const MAIN_SAMPLE: &str = "fn math() -> int { var a: int = 3.14159; print(a <= 1); if (a & 1 > 0 && a != 2 || false) { return a; } else { return 1117; } }";
+
+    #[test]
+    fn category_of_keywords() {
+        assert_eq!(TokenType::<String>::Fn.category(), TokenCategory::Keyword);
+        assert_eq!(
+            TokenType::<String>::Primitive(PrimitiveType::Int).category(),
+            TokenCategory::Keyword
+        );
+        assert!(TokenType::<String>::While.is_keyword());
+    }
+
+    #[test]
+    fn category_of_operators() {
+        assert_eq!(TokenType::<String>::Add.category(), TokenCategory::Operator);
+        assert_eq!(
+            TokenType::<String>::LogicalAnd.category(),
+            TokenCategory::Operator
+        );
+        assert!(TokenType::<String>::BangEqual.is_operator());
+        assert!(!TokenType::<String>::Semicolon.is_operator());
+    }
+
+    #[test]
+    fn category_of_literals() {
+        assert_eq!(
+            TokenType::<String>::Integer(1).category(),
+            TokenCategory::Literal
+        );
+        assert_eq!(
+            TokenType::String("hi".to_owned()).category(),
+            TokenCategory::Literal
+        );
+        assert!(TokenType::<String>::Decimal(2.5).is_literal());
+        assert!(TokenType::<String>::Character('a').is_literal());
+        assert!(!TokenType::Identifier("a".to_owned()).is_literal());
+    }
+
+    #[test]
+    fn category_of_identifiers() {
+        assert_eq!(
+            TokenType::Identifier("math".to_owned()).category(),
+            TokenCategory::Identifier
+        );
+    }
+
+    #[test]
+    fn category_of_delimiters() {
+        assert_eq!(
+            TokenType::<String>::LeftParen.category(),
+            TokenCategory::Delimiter
+        );
+        assert_eq!(
+            TokenType::<String>::Arrow.category(),
+            TokenCategory::Delimiter
+        );
+        assert_eq!(
+            TokenType::<String>::Semicolon.category(),
+            TokenCategory::Delimiter
+        );
+    }
+
+    #[test]
+    fn category_of_comment_and_eof() {
+        assert_eq!(
+            TokenType::Comment(String::new()).category(),
+            TokenCategory::Comment
+        );
+        assert_eq!(TokenType::<String>::Eof.category(), TokenCategory::Eof);
+    }
+
+    #[test]
+    fn eq_ignoring_values_ignores_numeric_payloads() {
+        let a: TokenType = TokenType::Decimal(1.5);
+        let b = TokenType::Decimal(2.25);
+
+        assert!(a.eq_ignoring_values(&b));
+        assert_ne!(a, b);
+        assert!(TokenType::<String>::Integer(1).eq_ignoring_values(&TokenType::Integer(2)));
+    }
+
+    #[test]
+    fn eq_ignoring_values_still_compares_kinds_and_other_payloads() {
+        assert!(!TokenType::<String>::Decimal(1.0).eq_ignoring_values(&TokenType::Integer(1)));
+        assert!(!TokenType::Identifier("a".to_owned())
+            .eq_ignoring_values(&TokenType::Identifier("b".to_owned())));
+        assert!(TokenType::<String>::Add.eq_ignoring_values(&TokenType::Add));
+    }
+
+    /// Declares the keyword table along with a match over every `TokenType` variant. The match
+    /// has no wildcard arm, so adding a variant fails to compile until it is listed here either
+    /// as a keyword with its spelling or as something else.
+    macro_rules! keyword_table {
+        (
+            keywords: { $($spelling:literal => $keyword:ident,)* }
+            others: { $($other:pat,)* }
+        ) => {
+            const KEYWORDS: &[(&str, TokenType)] = &[$(($spelling, TokenType::$keyword),)*];
+
+            fn keyword_spelling(ttype: &TokenType) -> Option<&'static str> {
+                use TokenType::*;
+
+                match ttype {
+                    $($keyword => Some($spelling),)*
+                    $($other)|* => None,
+                }
+            }
+        };
+    }
+
+    keyword_table! {
+        keywords: {
+            "break" => Break,
+            "continue" => Continue,
+            "default" => Default,
+            "else" => Else,
+            "enum" => Enum,
+            "fn" => Fn,
+            "for" => For,
+            "if" => If,
+            "in" => In,
+            "loop" => Loop,
+            "match" => Match,
+            "null" => Null,
+            "return" => Return,
+            "struct" => Struct,
+            "switch" => Switch,
+            "var" => Var,
+            "while" => While,
+            "_" => Underscore,
+        }
+        others: {
+            Add, AddEqual, Minus, MinusEqual, Modulo, ModuloEqual, Slash, SlashEqual, Star,
+            StarEqual, StarStar, StarStarEqual, Bang, BangEqual, Equal, EqualEqual, Greater, GreaterEqual, Less, LessEqual,
+            LessLess, Spaceship,
+            Ampersand, Arrow, At, Dollar, FatArrow, Bar, PipeGreater, Question, QuestionQuestion, QuestionQuestionEqual, Colon, Comma, Dot, DotDot, DotDotEqual, DotDotDot, Hash, LeftBrace,
+            LeftBracket, LeftParen, LogicalAnd, LogicalOr, RightBrace, RightBracket, RightParen,
+            Semicolon, Bool(_), Character(_), Decimal(_), Identifier(_), Integer(_), String(_),
+            InterpolatedString(_), NegativeInteger(_), SuffixedInteger { .. },
+            SuffixedDecimal { .. }, Lifetime(_),
+            // Primitive types are spelled through `PrimitiveType` and checked separately
+            Primitive(_),
+            Comment(_), BlockComment(_), Eof,
+        }
+    }
+
+    #[test]
+    fn keyword_table_agrees_with_lexer() {
+        for (spelling, ttype) in KEYWORDS {
+            assert_eq!(
+                keyword::<String>(spelling).as_ref(),
+                Some(ttype),
+                "{spelling}"
+            );
+            assert_eq!(keyword_spelling(ttype), Some(*spelling));
+            assert_eq!(ttype.to_string(), *spelling);
+            assert!(ttype.is_keyword(), "{ttype:?}");
+        }
+
+        let mut spellings: Vec<&str> = KEYWORDS.iter().map(|(spelling, _)| *spelling).collect();
+        spellings.sort_unstable();
+        spellings.dedup();
+        assert_eq!(
+            spellings.len(),
+            KEYWORDS.len(),
+            "duplicate keyword spelling"
+        );
+    }
+
+    #[test]
+    fn primitive_keywords_agree_with_display() {
+        use PrimitiveType::*;
+
+        for primitive in [Int, UInt, Isize, Usize, Float, Bool, Char, Void] {
+            let spelling = primitive.to_string();
+            assert_eq!(
+                keyword::<String>(&spelling),
+                Some(TokenType::Primitive(primitive)),
+                "{spelling}"
+            );
+        }
+    }
+
+    #[test]
+    fn reserved_words() {
+        for word in ["fn", "var", "while", "true", "null", "int", "_"] {
+            assert!(TokenType::is_reserved_word(word), "{word}");
+        }
+        for word in ["main", "Fn", "fn_", "i32", "", "var x"] {
+            assert!(!TokenType::is_reserved_word(word), "{word}");
+        }
+    }
+
+    #[test]
+    fn keyword_match_agrees_with_keyword_map() {
+        use std::collections::HashMap;
+
+        // The keyword table as it was when keywords were resolved through a `HashMap`
+        let map: HashMap<&str, TokenType> = HashMap::from([
+            ("break", TokenType::Break),
+            ("continue", TokenType::Continue),
+            ("default", TokenType::Default),
+            ("else", TokenType::Else),
+            ("enum", TokenType::Enum),
+            ("false", TokenType::Bool(false)),
+            ("fn", TokenType::Fn),
+            ("for", TokenType::For),
+            ("if", TokenType::If),
+            ("match", TokenType::Match),
+            ("return", TokenType::Return),
+            ("struct", TokenType::Struct),
+            ("switch", TokenType::Switch),
+            ("true", TokenType::Bool(true)),
+            ("var", TokenType::Var),
+            ("while", TokenType::While),
+            ("int", TokenType::Primitive(PrimitiveType::Int)),
+            ("uint", TokenType::Primitive(PrimitiveType::UInt)),
+            ("isize", TokenType::Primitive(PrimitiveType::Isize)),
+            ("usize", TokenType::Primitive(PrimitiveType::Usize)),
+            ("float", TokenType::Primitive(PrimitiveType::Float)),
+            ("bool", TokenType::Primitive(PrimitiveType::Bool)),
+            ("char", TokenType::Primitive(PrimitiveType::Char)),
+            ("void", TokenType::Primitive(PrimitiveType::Void)),
+        ]);
+
+        let mut words: Vec<&str> = map.keys().copied().collect();
+        words.extend(["x", "breaker", "iff", "_var", "Int", "matches"]);
+        let words: Vec<&str> = words.iter().copied().cycle().take(20_000).collect();
+        let source = words.join(" ");
+
+        let tokens = Lexer::new(&source).tokenize();
+
+        let expected: Vec<Result<TokenType>> = words
+            .iter()
+            .map(|word| {
+                Ok(map
+                    .get(word)
+                    .cloned()
+                    .unwrap_or_else(|| TokenType::Identifier(word.to_string())))
+            })
+            .collect();
+        let actual: Vec<Result<TokenType>> = tokens
+            .into_iter()
+            .map(|token| token.map(|token| token.ttype))
+            .collect();
+
+        assert_eq!(actual, expected);
+    }
+
+    #[test]
+    fn string_literal() {
+        let tokens = Lexer::new("var s = \"hello world\";").tokenize();
+
+        assert_eq!(
+            tokens[3],
+            Ok(Token {
+                ttype: TokenType::String("hello world".to_owned()),
+                line: 1,
+                col: 21,
+                span: Span::new(8, 21),
+            })
+        );
+        assert_eq!(
+            tokens[4].as_ref().map(|t| &t.ttype),
+            Ok(&TokenType::Semicolon)
+        );
+    }
+
+    #[test]
+    fn multi_line_string_literal() {
+        let tokens = Lexer::new("\"first\nsecond\"").tokenize();
+
+        assert_eq!(
+            tokens[0].as_ref().map(|t| &t.ttype),
+            Ok(&TokenType::String("first\nsecond".to_owned()))
+        );
+    }
+
+    #[test]
+    fn tokens_after_a_multi_line_string_are_positioned_correctly() {
+        let tokens = Lexer::new("var s = \"a\nbc\nd\";\n  name").tokenize();
+
+        assert_eq!(
+            tokens[3].as_ref().map(|t| (t.line, t.col)),
+            Ok((3, 2)),
+            "the string ends on its closing quote"
+        );
+        assert_eq!(
+            tokens[5],
+            Ok(Token {
+                ttype: TokenType::Identifier("name".to_owned()),
+                line: 4,
+                col: 6,
+                span: Span::new(20, 24),
+            })
+        );
+    }
+
+    /// The line, column and span of every token
+    fn positions(source: &str) -> Vec<(usize, usize, Span)> {
+        Lexer::new(source)
+            .tokenize()
+            .into_iter()
+            .map(|token| {
+                token
+                    .map(|t| (t.line, t.col, t.span))
+                    .expect("source should lex")
+            })
+            .collect()
+    }
+
+    #[test]
+    fn crlf_breaks_the_line_once() {
+        let positions = positions("var a = 1;\r\nvar b =\r\n  2;");
+
+        assert_eq!(positions[6], (2, 5, Span::new(16, 17)));
+        assert_eq!(positions[8], (3, 3, Span::new(23, 24)));
+        assert_eq!(positions.len(), 10);
+    }
+
+    #[test]
+    fn lone_carriage_return_breaks_the_line() {
+        let positions = positions("var a = 1;\rvar b =\r  2; // done\rend");
+
+        assert_eq!(positions[6], (2, 5, Span::new(15, 16)));
+        assert_eq!(positions[8], (3, 3, Span::new(21, 22)));
+        assert_eq!(positions[11], (4, 3, Span::new(32, 35)));
+        assert_eq!(
+            ttypes("x // c\ry"),
+            [
+                TokenType::Identifier("x".to_owned()),
+                TokenType::Comment(" c".to_owned()),
+                TokenType::Identifier("y".to_owned()),
+            ]
+        );
+    }
+
+    #[test]
+    fn strings_normalize_crlf_line_endings() {
+        let crlf = "\"a\r\nb\rc${x}d\r\n\" e";
+        let lf = "\"a\nb\rc${x}d\n\" e";
+        let expected = [
+            TokenType::InterpolatedString(vec![
+                StringPart::Literal("a\nb\rc".to_owned()),
+                StringPart::Expr("x".to_owned()),
+                StringPart::Literal("d\n".to_owned()),
+            ]),
+            TokenType::Identifier("e".to_owned()),
+        ];
+
+        assert_eq!(ttypes(crlf), expected);
+        assert_eq!(ttypes(lf), expected);
+        assert_eq!(positions(crlf)[1], (4, 3, Span::new(16, 17)));
+        assert_eq!(positions(lf)[1], (4, 3, Span::new(14, 15)));
+
+        let keep = LexerOptions {
+            keep_crlf_in_strings: true,
+            ..LexerOptions::default()
+        };
+        let kept: Vec<_> = Lexer::with_options("\"a\r\nb\"", keep)
+            .map(|token| token.unwrap().ttype.into_owned())
+            .collect();
+        assert_eq!(
+            kept,
+            [TokenType::String("a\r\nb".to_owned()), TokenType::Eof]
+        );
+
+        let borrowed = Lexer::new("\"a\nb\"").tokenize_borrowed();
+        assert!(matches!(
+            borrowed[0].as_ref().unwrap().ttype,
+            TokenType::String(Cow::Borrowed("a\nb"))
+        ));
+    }
+
+    #[test]
+    fn unterminated_string_points_at_opening_quote() {
+        let source = "var a = 1;\nvar s = \"never\nclosed\nacross several\nlines";
+        let tokens = Lexer::new(source).tokenize();
+
+        assert_eq!(
+            tokens.last(),
+            Some(&Err(LexerError::UnterminatedString {
+                line: 2,
+                col: 9,
+                offset: 19
+            }))
+        );
+    }
+
+    #[test]
+    fn newlines_advance_line_and_reset_col() {
+        let tokens = Lexer::new("a\n  bc\nd").tokenize();
+
+        assert_eq!(
+            tokens,
+            vec![
+                Ok(Token {
+                    ttype: TokenType::Identifier("a".to_owned()),
+                    line: 1,
+                    col: 1,
+                    span: Span::new(0, 1),
+                }),
+                Ok(Token {
+                    ttype: TokenType::Identifier("bc".to_owned()),
+                    line: 2,
+                    col: 4,
+                    span: Span::new(4, 6),
+                }),
+                Ok(Token {
+                    ttype: TokenType::Identifier("d".to_owned()),
+                    line: 3,
+                    col: 1,
+                    span: Span::new(7, 8),
+                }),
+            ]
+        );
+    }
+
+    #[test]
+    fn borrowed_payloads_are_slices_of_the_source() {
+        let source = "var greeting = \"héllo\"; greeting";
+        let tokens = Lexer::new(source).tokenize_borrowed();
+
+        let mut slices = Vec::new();
+        for token in &tokens {
+            match token.as_ref().map(|t| &t.ttype) {
+                Ok(TokenType::Identifier(Cow::Borrowed(s)))
+                | Ok(TokenType::String(Cow::Borrowed(s))) => slices.push(*s),
+                _ => {}
+            }
+        }
+
+        assert_eq!(slices, vec!["greeting", "héllo", "greeting"]);
+        let range = source.as_bytes().as_ptr_range();
+        for slice in slices {
+            assert!(range.contains(&slice.as_ptr()));
+        }
+    }
+
+    #[test]
+    fn borrowed_tokens_convert_to_owned_tokens() {
+        let source = "fn math() -> int { var a: int = 3; print(a <= 1); }";
+        let borrowed: Vec<_> = Lexer::new(source)
+            .tokenize_borrowed()
+            .into_iter()
+            .map(|token| token.map(BorrowedToken::into_owned))
+            .collect();
+
+        assert_eq!(borrowed, Lexer::new(source).tokenize());
+    }
+
+    #[test]
+    fn hash_token() {
+        let ttypes: Vec<_> = Lexer::new("#[cfg(test)]")
+            .tokenize()
+            .into_iter()
+            .map(|token| token.map(|token| token.ttype))
+            .collect();
+
+        assert_eq!(
+            ttypes,
+            vec![
+                Ok(TokenType::Hash),
+                Ok(TokenType::LeftBracket),
+                Ok(TokenType::Identifier("cfg".to_owned())),
+                Ok(TokenType::LeftParen),
+                Ok(TokenType::Identifier("test".to_owned())),
+                Ok(TokenType::RightParen),
+                Ok(TokenType::RightBracket),
+            ]
+        );
+    }
+
+    #[test]
+    fn at_pattern_binding() {
+        assert_eq!(
+            ttypes("x @ 1..=5"),
+            vec![
+                TokenType::Identifier("x".to_owned()),
+                TokenType::At,
+                TokenType::Integer(1),
+                TokenType::DotDotEqual,
+                TokenType::Integer(5),
+            ]
+        );
+        assert_eq!(
+            Lexer::new("@").tokenize(),
+            vec![Ok(Token {
+                ttype: TokenType::At,
+                line: 1,
+                col: 1,
+                span: Span::new(0, 1),
+            })]
+        );
+    }
+
+    #[test]
+    fn dollar_interpolation() {
+        assert_eq!(
+            ttypes("$x"),
+            vec![TokenType::Dollar, TokenType::Identifier("x".to_owned())]
+        );
+        assert_eq!(ttypes("$"), vec![TokenType::Dollar]);
+    }
+
+    #[test]
+    fn test_attribute() {
+        assert_eq!(
+            ttypes("#[test]"),
+            vec![
+                TokenType::Hash,
+                TokenType::LeftBracket,
+                TokenType::Identifier("test".to_owned()),
+                TokenType::RightBracket,
+            ]
+        );
+    }
+
+    #[test]
+    fn leading_shebang_is_skipped() {
+        let tokens = Lexer::new("#!/usr/bin/env sk\nvar x;").tokenize();
+
+        assert_eq!(
+            tokens[0],
+            Ok(Token {
+                ttype: TokenType::Var,
+                line: 2,
+                col: 3,
+                span: Span::new(18, 21),
+            })
+        );
+        assert_eq!(ttypes("#!/usr/bin/env sk"), Vec::<TokenType>::new());
+    }
+
+    #[test]
+    fn shebang_only_at_start_of_source() {
+        assert_eq!(
+            ttypes("x #!y"),
+            vec![
+                TokenType::Identifier("x".to_owned()),
+                TokenType::Hash,
+                TokenType::Bang,
+                TokenType::Identifier("y".to_owned()),
+            ]
+        );
+        assert_eq!(
+            ttypes("#![test]"),
+            vec![
+                TokenType::Hash,
+                TokenType::Bang,
+                TokenType::LeftBracket,
+                TokenType::Identifier("test".to_owned()),
+                TokenType::RightBracket,
+            ]
+        );
+    }
+
+    /// Tokens of the `main.rs` sample as produced by the char based lexer, before scanning
+    /// moved to bytes
+    #[allow(clippy::approx_constant)]
+    fn main_sample_golden() -> Vec<(TokenType, usize, usize)> {
+        use TokenType::*;
+
+        vec![
+            (Fn, 1, 2),
+            (Identifier("math".to_owned()), 1, 7),
+            (LeftParen, 1, 8),
+            (RightParen, 1, 9),
+            (Arrow, 1, 12),
+            (Primitive(PrimitiveType::Int), 1, 16),
+            (LeftBrace, 1, 18),
+            (Var, 1, 22),
+            (Identifier("a".to_owned()), 1, 24),
+            (Colon, 1, 25),
+            (Primitive(PrimitiveType::Int), 1, 29),
+            (Equal, 1, 31),
+            (Decimal(3.14159), 1, 39),
+            (Semicolon, 1, 40),
+            (Identifier("print".to_owned()), 1, 46),
+            (LeftParen, 1, 47),
+            (Identifier("a".to_owned()), 1, 48),
+            (LessEqual, 1, 51),
+            (Integer(1), 1, 53),
+            (RightParen, 1, 54),
+            (Semicolon, 1, 55),
+            (If, 1, 58),
+            (LeftParen, 1, 60),
+            (Identifier("a".to_owned()), 1, 61),
+            (Ampersand, 1, 63),
+            (Integer(1), 1, 65),
+            (Greater, 1, 67),
+            (Integer(0), 1, 69),
+            (LogicalAnd, 1, 72),
+            (Identifier("a".to_owned()), 1, 74),
+            (BangEqual, 1, 77),
+            (Integer(2), 1, 79),
+            (LogicalOr, 1, 82),
+            (Bool(false), 1, 88),
+            (RightParen, 1, 89),
+            (LeftBrace, 1, 91),
+            (Return, 1, 98),
+            (Identifier("a".to_owned()), 1, 100),
+            (Semicolon, 1, 101),
+            (RightBrace, 1, 103),
+            (Else, 1, 108),
+            (LeftBrace, 1, 110),
+            (Return, 1, 117),
+            (Integer(1117), 1, 122),
+            (Semicolon, 1, 123),
+            (RightBrace, 1, 125),
+            (RightBrace, 1, 127),
+        ]
+    }
+
+    fn positioned_ttypes(source: &str) -> Vec<(TokenType, usize, usize)> {
+        Lexer::new(source)
+            .tokenize()
+            .into_iter()
+            .map(|token| {
+                let token = token.expect("source should lex");
+                (token.ttype, token.line, token.col)
+            })
+            .collect()
+    }
+
+    #[test]
+    fn byte_lexer_matches_char_lexer_on_main_sample() {
+        assert_eq!(positioned_ttypes(MAIN_SAMPLE), main_sample_golden());
+    }
+
+    #[test]
+    fn byte_lexer_matches_char_lexer_on_large_file() {
+        const COPIES: usize = 2_000;
+
+        let source = vec![MAIN_SAMPLE; COPIES].join("\n");
+        let expected: Vec<_> = (0..COPIES)
+            .flat_map(|copy| {
+                main_sample_golden()
+                    .into_iter()
+                    .map(move |(ttype, line, col)| (ttype, line + copy, col))
+            })
+            .collect();
+
+        assert_eq!(positioned_ttypes(&source), expected);
+    }
+
+    #[test]
+    fn spans_are_byte_offsets() {
+        let source = "var é = \"ü\";";
+        let tokens = Lexer::new(source).tokenize();
+
+        let spans: Vec<_> = tokens
+            .iter()
+            .map(|token| {
+                let span = token.as_ref().expect("source should lex").span;
+                &source[span.start..span.end]
+            })
+            .collect();
+
+        assert_eq!(spans, vec!["var", "é", "=", "\"ü\"", ";"]);
+        assert_eq!(
+            tokens[1].as_ref().map(|token| (token.line, token.col)),
+            Ok((1, 5))
+        );
+    }
+
+    #[test]
+    fn non_ascii_whitespace_and_identifiers() {
+        let ttypes: Vec<_> = positioned_ttypes("größe\u{00A0}=\u{2003}1")
+            .into_iter()
+            .map(|(ttype, ..)| ttype)
+            .collect();
+
+        assert_eq!(
+            ttypes,
+            vec![
+                TokenType::Identifier("größe".to_owned()),
+                TokenType::Equal,
+                TokenType::Integer(1),
+            ]
+        );
+    }
+
+    #[test]
+    #[ignore = "benchmark, run with `cargo test --release -- --ignored`"]
+    fn bench_tokenize_large_file() {
+        let source = vec![MAIN_SAMPLE; 50_000].join("\n");
+
+        let start = std::time::Instant::now();
+        let tokens = Lexer::new(&source).tokenize_borrowed();
+        let elapsed = start.elapsed();
+
+        eprintln!(
+            "tokenized {} bytes into {} tokens in {elapsed:?}",
+            source.len(),
+            tokens.len()
+        );
+    }
+
+    #[test]
+    #[ignore = "benchmark, run with `cargo test --release -- --ignored`"]
+    fn bench_tokenize_keyword_heavy_source() {
+        let words = [
+            "while", "breaker", "usize", "x", "return", "matches", "true", "_var",
+        ];
+        let source = vec![words.join(" "); 50_000].join("\n");
+
+        let start = std::time::Instant::now();
+        let tokens = Lexer::new(&source).tokenize_borrowed();
+        let elapsed = start.elapsed();
+
+        eprintln!(
+            "tokenized {} keyword heavy words in {elapsed:?}",
+            tokens.len()
+        );
+    }
+
+    #[test]
+    #[ignore = "benchmark, run with `cargo test --release -- --ignored`"]
+    fn bench_tokenize_into_reused_buffer() {
+        let sources: Vec<_> = (0..10_000)
+            .map(|i| format!("{MAIN_SAMPLE}\nvar n{i} = {i};"))
+            .collect();
+
+        let start = std::time::Instant::now();
+        let fresh: usize = sources
+            .iter()
+            .map(|source| Lexer::new(source).tokenize().len())
+            .sum();
+        let tokenize_elapsed = start.elapsed();
+
+        let start = std::time::Instant::now();
+        let mut buf = Vec::new();
+        let mut reused = 0;
+        for source in &sources {
+            Lexer::new(source).tokenize_into(&mut buf);
+            reused += buf.len();
+        }
+        let tokenize_into_elapsed = start.elapsed();
+
+        assert_eq!(fresh, reused);
+        eprintln!(
+            "tokenized {} sources into {fresh} tokens in {tokenize_elapsed:?} with tokenize, \
+             {tokenize_into_elapsed:?} with tokenize_into and one buffer",
+            sources.len()
+        );
+    }
+
+    fn positions_with(source: &str, column_encoding: ColumnEncoding) -> Vec<(usize, usize)> {
+        let options = LexerOptions {
+            column_encoding,
+            ..LexerOptions::default()
+        };
+        Lexer::with_options(source, options)
+            .tokenize()
+            .into_iter()
+            .map(|token| {
+                let token = token.expect("source should lex");
+                (token.line, token.col)
+            })
+            .collect()
+    }
+
+    #[test]
+    fn line_continuation_joins_lines() {
+        let options = LexerOptions {
+            line_continuations: true,
+            ..LexerOptions::default()
+        };
+        let tokens: Vec<_> = Lexer::with_options("var total = a + \\\n    b;", options)
+            .tokenize()
+            .into_iter()
+            .map(|token| {
+                let token = token.expect("source should lex");
+                (token.ttype, token.line, token.col)
+            })
+            .collect();
+
+        assert_eq!(
+            tokens,
+            vec![
+                (TokenType::Var, 1, 3),
+                (TokenType::Identifier("total".to_owned()), 1, 9),
+                (TokenType::Equal, 1, 11),
+                (TokenType::Identifier("a".to_owned()), 1, 13),
+                (TokenType::Add, 1, 15),
+                (TokenType::Identifier("b".to_owned()), 2, 5),
+                (TokenType::Semicolon, 2, 6),
+            ]
+        );
+    }
+
+    fn folded_ttypes(source: &str) -> Vec<TokenType> {
+        let options = LexerOptions {
+            fold_negative_literals: true,
+            ..LexerOptions::default()
+        };
+
+        Lexer::with_options(source, options)
+            .tokenize()
+            .into_iter()
+            .map(|token| token.expect("source should lex").ttype)
+            .collect()
+    }
+
+    #[test]
+    fn negative_literals_fold_in_prefix_position() {
+        assert_eq!(folded_ttypes("-5"), vec![TokenType::NegativeInteger(5)]);
+        assert_eq!(
+            folded_ttypes("f(-1.5, -2) * -3"),
+            vec![
+                TokenType::Identifier("f".to_owned()),
+                TokenType::LeftParen,
+                TokenType::Decimal(-1.5),
+                TokenType::Comma,
+                TokenType::NegativeInteger(2),
+                TokenType::RightParen,
+                TokenType::Star,
+                TokenType::NegativeInteger(3),
+            ]
+        );
+        assert_eq!(
+            folded_ttypes("x = // note\n-7"),
+            vec![
+                TokenType::Identifier("x".to_owned()),
+                TokenType::Equal,
+                TokenType::Comment(" note".to_owned()),
+                TokenType::NegativeInteger(7),
+            ]
+        );
+        assert_eq!(
+            folded_ttypes("[-5]; return -1; match -2 { _ => -3 } for i in -4..0 { -0x1p4; }"),
+            vec![
+                TokenType::LeftBracket,
+                TokenType::NegativeInteger(5),
+                TokenType::RightBracket,
+                TokenType::Semicolon,
+                TokenType::Return,
+                TokenType::NegativeInteger(1),
+                TokenType::Semicolon,
+                TokenType::Match,
+                TokenType::NegativeInteger(2),
+                TokenType::LeftBrace,
+                TokenType::Underscore,
+                TokenType::FatArrow,
+                TokenType::NegativeInteger(3),
+                TokenType::RightBrace,
+                TokenType::For,
+                TokenType::Identifier("i".to_owned()),
+                TokenType::In,
+                TokenType::NegativeInteger(4),
+                TokenType::DotDot,
+                TokenType::Integer(0),
+                TokenType::LeftBrace,
+                TokenType::Decimal(-16.0),
+                TokenType::Semicolon,
+                TokenType::RightBrace,
+            ]
+        );
+        assert_eq!(
+            folded_ttypes("S { x: -0xFF, y: -0b1_0 }")[4..],
+            [
+                TokenType::NegativeInteger(0xFF),
+                TokenType::Comma,
+                TokenType::Identifier("y".to_owned()),
+                TokenType::Colon,
+                TokenType::NegativeInteger(2),
+                TokenType::RightBrace,
+            ]
+        );
+    }
+
+    #[test]
+    fn subtraction_is_not_folded() {
+        let subtraction = vec![
+            TokenType::Identifier("a".to_owned()),
+            TokenType::Minus,
+            TokenType::Integer(5),
+        ];
+
+        assert_eq!(folded_ttypes("a - 5"), subtraction);
+        assert_eq!(folded_ttypes("a -5"), subtraction);
+        assert_eq!(
+            folded_ttypes("a[0] -1 + f() -2 + { 3 } -4")
+                .iter()
+                .filter(|ttype| **ttype == TokenType::Minus)
+                .count(),
+            3
+        );
+        assert_eq!(
+            folded_ttypes("(1)-2"),
+            vec![
+                TokenType::LeftParen,
+                TokenType::Integer(1),
+                TokenType::RightParen,
+                TokenType::Minus,
+                TokenType::Integer(2),
+            ]
+        );
+        assert_eq!(ttypes("-5"), vec![TokenType::Minus, TokenType::Integer(5)]);
+    }
+
+    #[test]
+    fn backslash_is_unknown_without_line_continuations() {
+        assert_eq!(
+            Lexer::new("a \\\nb").tokenize()[1],
+            Err(LexerError::UnknownCharacter {
+                line: 1,
+                col: 3,
+                offset: 2,
+                character: '\\',
+            })
+        );
+
+        let options = LexerOptions {
+            line_continuations: true,
+            ..LexerOptions::default()
+        };
+        assert!(Lexer::with_options("a \\ b", options).tokenize()[1].is_err());
+    }
+
+    #[test]
+    fn utf16_columns_count_astral_chars_as_two_units() {
+        // U+1D400 MATHEMATICAL BOLD CAPITAL A is outside the BMP
+        let source = "\u{1D400} + x";
+
+        assert_eq!(
+            positions_with(source, ColumnEncoding::Scalar),
+            vec![(1, 1), (1, 3), (1, 5)]
+        );
+        assert_eq!(
+            positions_with(source, ColumnEncoding::Utf16),
+            vec![(1, 2), (1, 4), (1, 6)]
+        );
+        assert_eq!(
+            positions_with(source, ColumnEncoding::Utf8),
+            vec![(1, 4), (1, 6), (1, 8)]
+        );
+    }
+
+    #[test]
+    fn column_encodings_agree_on_ascii() {
+        let source = "var a = 1;\nb";
+        let scalar = positions_with(source, ColumnEncoding::Scalar);
+
+        assert_eq!(positions_with(source, ColumnEncoding::Utf16), scalar);
+        assert_eq!(positions_with(source, ColumnEncoding::Utf8), scalar);
+    }
+
+    fn ttypes(source: &str) -> Vec<TokenType> {
+        tokenize_types(source).expect("source should lex")
+    }
+
+    fn round_trip(source: &str) -> String {
+        let tokens: Vec<Token> = Lexer::new(source)
+            .tokenize()
+            .into_iter()
+            .collect::<Result<_>>()
+            .expect("source should lex");
+
+        tokens_to_source(&tokens)
+    }
+
+    #[test]
+    fn tokens_to_source_renders_spellings() {
+        assert_eq!(
+            round_trip("var   a:int=1.0;\nprint( \"hi\" ,'c' );"),
+            "var a : int = 1.0 ; print ( \"hi\" , 'c' ) ;"
+        );
+    }
+
+    #[test]
+    fn tokens_to_source_relexes_to_same_types() {
+        for source in [
+            MAIN_SAMPLE,
+            "#[cfg(test)]\nfn f(a: float) { loop { a -= 2.5; a %= 3; break; } }",
+            "while (!done) { x *= -1; continue; } // trailing\nvar s = \"a b\";",
+        ] {
+            assert_eq!(ttypes(&round_trip(source)), ttypes(source), "{source}");
+        }
+    }
+
+    #[test]
+    fn tokens_to_source_keeps_decimals_positional() {
+        let source =
+            "100000000000000000000.0 0.0000001 2.0 100000000000000000000.0f64 0.0000001f32 2f64";
+
+        assert_eq!(
+            round_trip(source),
+            "100000000000000000000.0 0.0000001 2.0 100000000000000000000.0f64 0.0000001f32 2.0f64"
+        );
+        assert_eq!(ttypes(&round_trip(source)), ttypes(source));
+    }
+
+    #[test]
+    fn platform_sized_integers() {
+        use TokenType::*;
+
+        assert_eq!(
+            ttypes("var n: usize = 0;"),
+            vec![
+                Var,
+                Identifier("n".to_owned()),
+                Colon,
+                Primitive(PrimitiveType::Usize),
+                Equal,
+                Integer(0),
+                Semicolon,
+            ]
+        );
+        assert_eq!(ttypes("isize"), vec![Primitive(PrimitiveType::Isize)]);
+        assert_eq!(
+            PrimitiveType::Usize.size_bytes(),
+            std::mem::size_of::<usize>()
+        );
+        assert_eq!(
+            PrimitiveType::Isize.size_bytes(),
+            PrimitiveType::Usize.size_bytes()
+        );
+    }
+
+    #[test]
+    fn range_operators() {
+        use TokenType::*;
+
+        assert_eq!(
+            ttypes("for i in 0..10"),
+            vec![
+                For,
+                Identifier("i".to_owned()),
+                In,
+                Integer(0),
+                DotDot,
+                Integer(10)
+            ]
+        );
+        assert_eq!(ttypes("1..=2"), vec![Integer(1), DotDotEqual, Integer(2)]);
+        assert_eq!(
+            ttypes("1.5..2."),
+            vec![Decimal(1.5), DotDot, Integer(2), Dot]
+        );
+    }
+
+    #[test]
+    fn power_operator() {
+        use TokenType::*;
+
+        assert_eq!(ttypes("2 ** 8"), vec![Integer(2), StarStar, Integer(8)]);
+        assert_eq!(
+            ttypes("x **= 2"),
+            vec![Identifier("x".to_owned()), StarStarEqual, Integer(2)]
+        );
+        assert_eq!(
+            ttypes("a * *b"),
+            vec![
+                Identifier("a".to_owned()),
+                Star,
+                Star,
+                Identifier("b".to_owned())
+            ]
+        );
+        assert_eq!(ttypes("***"), vec![StarStar, Star]);
+        assert_eq!(ttypes("*="), vec![StarEqual]);
+    }
+
+    #[test]
+    fn pipeline_operator() {
+        use TokenType::*;
+
+        assert_eq!(
+            ttypes("x |> f"),
+            vec![
+                Identifier("x".to_owned()),
+                PipeGreater,
+                Identifier("f".to_owned())
+            ]
+        );
+        assert_eq!(
+            ttypes("a || b"),
+            vec![
+                Identifier("a".to_owned()),
+                LogicalOr,
+                Identifier("b".to_owned())
+            ]
+        );
+        assert_eq!(
+            ttypes("a | > b"),
+            vec![
+                Identifier("a".to_owned()),
+                Bar,
+                Greater,
+                Identifier("b".to_owned())
+            ]
+        );
+        assert_eq!(ttypes("||>"), vec![LogicalOr, Greater]);
+    }
+
+    #[test]
+    fn null_coalescing_operators() {
+        use TokenType::*;
+
+        assert_eq!(
+            ttypes("x ??= y"),
+            vec![
+                Identifier("x".to_owned()),
+                QuestionQuestionEqual,
+                Identifier("y".to_owned())
+            ]
+        );
+        assert_eq!(
+            ttypes("a ?? b"),
+            vec![
+                Identifier("a".to_owned()),
+                QuestionQuestion,
+                Identifier("b".to_owned())
+            ]
+        );
+        assert_eq!(
+            ttypes("a ? b"),
+            vec![
+                Identifier("a".to_owned()),
+                Question,
+                Identifier("b".to_owned())
+            ]
+        );
+        assert_eq!(ttypes("???"), vec![QuestionQuestion, Question]);
+        assert_eq!(ttypes("? ?="), vec![Question, Question, Equal]);
+    }
+
+    #[test]
+    fn three_way_comparison() {
+        use TokenType::*;
+
+        let a = || Identifier("a".to_owned());
+        let b = || Identifier("b".to_owned());
+
+        assert_eq!(ttypes("a <=> b"), vec![a(), Spaceship, b()]);
+        assert_eq!(ttypes("a <= b"), vec![a(), LessEqual, b()]);
+        assert_eq!(ttypes("a << b"), vec![a(), LessLess, b()]);
+        assert_eq!(ttypes("a <= > b"), vec![a(), LessEqual, Greater, b()]);
+        assert_eq!(ttypes("<<=>"), vec![LessLess, FatArrow]);
+    }
+
+    #[test]
+    fn spread_operator() {
+        use TokenType::*;
+
+        assert_eq!(ttypes("..."), vec![DotDotDot]);
+        assert_eq!(ttypes(".."), vec![DotDot]);
+        assert_eq!(ttypes("..="), vec![DotDotEqual]);
+        assert_eq!(ttypes("...."), vec![DotDotDot, Dot]);
+        assert_eq!(
+            ttypes("f(...args)"),
+            vec![
+                Identifier("f".to_owned()),
+                LeftParen,
+                DotDotDot,
+                Identifier("args".to_owned()),
+                RightParen,
+            ]
+        );
+    }
+
+    #[test]
+    fn null_keyword() {
+        use TokenType::*;
+
+        assert_eq!(
+            ttypes("var x = null;"),
+            vec![Var, Identifier("x".to_owned()), Equal, Null, Semicolon]
+        );
+        assert_eq!(
+            ttypes("nullable null_ptr"),
+            vec![
+                Identifier("nullable".to_owned()),
+                Identifier("null_ptr".to_owned()),
+            ]
+        );
+    }
+
+    #[test]
+    fn void_return_type() {
+        use TokenType::*;
+
+        assert_eq!(
+            ttypes("fn f() -> void {}"),
+            vec![
+                Fn,
+                Identifier("f".to_owned()),
+                LeftParen,
+                RightParen,
+                Arrow,
+                Primitive(PrimitiveType::Void),
+                LeftBrace,
+                RightBrace,
+            ]
+        );
+    }
+
+    #[test]
+    fn iterator_ends_after_eof_when_last_token_errors() {
+        for source in ["var ~", "var s = \"unterminated"] {
+            let mut lexer = Lexer::new(source);
+            let results: Vec<_> = lexer.by_ref().collect();
+
+            let [.., error, eof] = results.as_slice() else {
+                panic!("expected an error and eof, got {results:?}");
+            };
+            assert!(error.is_err(), "{error:?}");
+            assert!(matches!(
+                eof,
+                Ok(Token {
+                    ttype: TokenType::Eof,
+                    ..
+                })
+            ));
+            assert_eq!(lexer.next(), None);
+        }
+    }
+
+    #[test]
+    fn match_arm_tokens() {
+        use TokenType::*;
+
+        assert_eq!(
+            ttypes("1 => a, _ => b, _x"),
+            vec![
+                Integer(1),
+                FatArrow,
+                Identifier("a".to_owned()),
+                Comma,
+                Underscore,
+                FatArrow,
+                Identifier("b".to_owned()),
+                Comma,
+                Identifier("_x".to_owned()),
+            ]
+        );
+    }
+
+    #[test]
+    fn format_tokens_snapshot() {
+        // The leading newline keeps the first line aligned with the rest
+        let expected = r#"
+1:2 0..2 Fn
+1:7 3..7 Identifier("math")
+1:8 7..8 LeftParen
+1:9 8..9 RightParen
+1:12 10..12 Arrow
+1:16 13..16 Primitive(Int)
+1:18 17..18 LeftBrace
+1:22 19..22 Var
+1:24 23..24 Identifier("a")
+1:25 24..25 Colon
+1:29 26..29 Primitive(Int)
+1:31 30..31 Equal
+1:39 32..39 Decimal(3.14159)
+1:40 39..40 Semicolon
+1:46 41..46 Identifier("print")
+1:47 46..47 LeftParen
+1:48 47..48 Identifier("a")
+1:51 49..51 LessEqual
+1:53 52..53 Integer(1)
+1:54 53..54 RightParen
+1:55 54..55 Semicolon
+1:58 56..58 If
+1:60 59..60 LeftParen
+1:61 60..61 Identifier("a")
+1:63 62..63 Ampersand
+1:65 64..65 Integer(1)
+1:67 66..67 Greater
+1:69 68..69 Integer(0)
+1:72 70..72 LogicalAnd
+1:74 73..74 Identifier("a")
+1:77 75..77 BangEqual
+1:79 78..79 Integer(2)
+1:82 80..82 LogicalOr
+1:88 83..88 Bool(false)
+1:89 88..89 RightParen
+1:91 90..91 LeftBrace
+1:98 92..98 Return
+1:100 99..100 Identifier("a")
+1:101 100..101 Semicolon
+1:103 102..103 RightBrace
+1:108 104..108 Else
+1:110 109..110 LeftBrace
+1:117 111..117 Return
+1:122 118..122 Integer(1117)
+1:123 122..123 Semicolon
+1:125 124..125 RightBrace
+1:127 126..127 RightBrace
+"#;
+
+        assert_eq!(
+            format_tokens(&Lexer::new(MAIN_SAMPLE).tokenize()),
+            &expected[1..]
+        );
+    }
+
+    #[test]
+    fn format_tokens_includes_errors() {
+        assert_eq!(
+            format_tokens(&Lexer::new("var ~").tokenize()),
+            "1:3 0..3 Var\nerror: [line 1: 5] Unknown character '~'\n"
+        );
+    }
+
+    #[test]
+    fn lifetimes_and_char_literals() {
+        assert_eq!(ttypes("'a"), vec![TokenType::Lifetime("a".to_owned())]);
+        assert_eq!(ttypes("'a'"), vec![TokenType::Character('a')]);
+        assert_eq!(
+            ttypes("'outer: loop"),
+            vec![
+                TokenType::Lifetime("outer".to_owned()),
+                TokenType::Colon,
+                TokenType::Loop,
+            ]
+        );
+        assert_eq!(
+            ttypes("break 'outer_2;"),
+            vec![
+                TokenType::Break,
+                TokenType::Lifetime("outer_2".to_owned()),
+                TokenType::Semicolon,
+            ]
+        );
+        assert_eq!(
+            ttypes("'1' ' '"),
+            vec![TokenType::Character('1'), TokenType::Character(' ')]
+        );
+        assert_eq!(TokenType::Lifetime("outer").to_string(), "'outer");
+    }
+
+    #[test]
+    fn malformed_literals_do_not_panic() {
+        assert_eq!(
+            Lexer::new("18446744073709551616").tokenize(),
+            vec![Err(LexerError::IntegerOverflow {
+                line: 1,
+                col: 20,
+                offset: 19
+            })]
+        );
+        assert_eq!(
+            ttypes("18446744073709551615"),
+            vec![TokenType::Integer(u64::MAX)]
+        );
+
+        for source in [
+            "1.2.3", "1..2..3", "9.", "'", "'a", "\"", "'\n", "1e5", "0x",
+        ] {
+            let _ = Lexer::new(source).tokenize();
+        }
+    }
+
+    #[test]
+    fn letters_after_a_number_are_an_error() {
+        assert_eq!(
+            Lexer::new("123abc").tokenize(),
+            vec![Err(LexerError::InvalidNumberSuffix {
+                line: 1,
+                col: 6,
+                offset: 5,
+                lexeme: "123abc".to_owned(),
+            })]
+        );
+        assert_eq!(
+            Lexer::new("x = 0x1g;").tokenize()[2],
+            Err(LexerError::InvalidNumberSuffix {
+                line: 1,
+                col: 8,
+                offset: 7,
+                lexeme: "0x1g".to_owned(),
+            })
+        );
+        assert_eq!(
+            Lexer::new("1.5f").tokenize(),
+            vec![Err(LexerError::InvalidNumberSuffix {
+                line: 1,
+                col: 4,
+                offset: 3,
+                lexeme: "1.5f".to_owned(),
+            })]
+        );
+    }
+
+    #[test]
+    fn number_followed_by_separated_identifier() {
+        assert_eq!(
+            ttypes("123 abc"),
+            vec![
+                TokenType::Integer(123),
+                TokenType::Identifier("abc".to_owned())
+            ]
+        );
+        assert_eq!(
+            ttypes("123.method()"),
+            vec![
+                TokenType::Integer(123),
+                TokenType::Dot,
+                TokenType::Identifier("method".to_owned()),
+                TokenType::LeftParen,
+                TokenType::RightParen,
+            ]
+        );
+        assert_eq!(
+            ttypes("0..n"),
+            vec![
+                TokenType::Integer(0),
+                TokenType::DotDot,
+                TokenType::Identifier("n".to_owned()),
+            ]
+        );
+    }
+
+    #[test]
+    fn interpolated_string() {
+        assert_eq!(
+            ttypes("\"hello ${name}!\""),
+            [TokenType::InterpolatedString(vec![
+                StringPart::Literal("hello ".to_owned()),
+                StringPart::Expr("name".to_owned()),
+                StringPart::Literal("!".to_owned()),
+            ])]
+        );
+        assert_eq!(
+            ttypes("\"${a}${b}\""),
+            [TokenType::InterpolatedString(vec![
+                StringPart::Expr("a".to_owned()),
+                StringPart::Expr("b".to_owned()),
+            ])]
+        );
+    }
+
+    #[test]
+    fn escaped_interpolation_is_a_plain_string() {
+        assert_eq!(
+            ttypes("\"cost: \\${price}\""),
+            [TokenType::String("cost: \\${price}".to_owned())]
+        );
+        assert_eq!(
+            ttypes("\"\\${a} ${b}\""),
+            [TokenType::InterpolatedString(vec![
+                StringPart::Literal("\\${a} ".to_owned()),
+                StringPart::Expr("b".to_owned()),
+            ])]
+        );
+    }
+
+    #[test]
+    fn interpolation_with_nested_braces_and_strings() {
+        assert_eq!(
+            ttypes("\"${ { p.x } } and ${ f(\"}\") }\""),
+            [TokenType::InterpolatedString(vec![
+                StringPart::Expr(" { p.x } ".to_owned()),
+                StringPart::Literal(" and ".to_owned()),
+                StringPart::Expr(" f(\"}\") ".to_owned()),
+            ])]
+        );
+    }
+
+    #[test]
+    fn interpolated_string_displays_as_written() {
+        let source = "\"a ${b} \\${c} ${ {d} }\"";
+
+        assert_eq!(ttypes(source)[0].to_string(), source);
+    }
+
+    #[test]
+    fn unterminated_interpolation_points_at_dollar() {
+        let tokens = Lexer::new("var s = \"hi ${name\";").tokenize();
+
+        assert_eq!(
+            tokens.last(),
+            Some(&Err(LexerError::UnterminatedInterpolation {
+                line: 1,
+                col: 13,
+                offset: 12
+            }))
+        );
+    }
+
+    #[test]
+    fn string_longer_than_the_limit_is_an_error() {
+        let options = LexerOptions {
+            max_string_len: Some(8),
+            ..LexerOptions::default()
+        };
+        let long = format!("var s = \"{}\"; var t = \"12345678\";", "x".repeat(1000));
+        let tokens = Lexer::with_options(&long, options).tokenize();
+
+        assert_eq!(
+            tokens[3],
+            Err(LexerError::StringTooLong {
+                line: 1,
+                col: 9,
+                offset: 8,
+                max: 8
+            })
+        );
+        // Lexing carries on after the closing quote, and a string at the limit is allowed
+        assert_eq!(
+            tokens[4].as_ref().map(|t| &t.ttype),
+            Ok(&TokenType::Semicolon)
+        );
+        assert_eq!(
+            tokens[8].as_ref().map(|t| &t.ttype),
+            Ok(&TokenType::String("12345678".to_owned()))
+        );
+    }
+
+    #[test]
+    fn peek_token_does_not_consume() {
+        let mut lexer = Lexer::new("var x = 1");
+
+        let peeked = lexer.peek_token().cloned().map_err(Clone::clone);
+        assert_eq!(peeked.as_ref().map(|t| &t.ttype), Ok(&TokenType::Var));
+        assert_eq!(lexer.peek_token().cloned().map_err(Clone::clone), peeked);
+        assert_eq!(lexer.next_token(), peeked);
+
+        assert_eq!(
+            lexer.peek_token().map(|t| &t.ttype),
+            Ok(&TokenType::Identifier("x".into()))
+        );
+        let rest: Vec<TokenType<Cow<str>>> = lexer.map(|t| t.unwrap().ttype).collect();
+        assert_eq!(
+            rest,
+            [
+                TokenType::Identifier("x".into()),
+                TokenType::Equal,
+                TokenType::Integer(1),
+                TokenType::Eof
+            ]
+        );
+    }
+
+    #[test]
+    fn peek_token_returns_errors_without_consuming_them() {
+        let mut lexer = Lexer::new("~ a");
+
+        assert!(lexer.peek_token().is_err());
+        assert!(lexer.next().unwrap().is_err());
+        assert_eq!(
+            lexer.peek_token().map(|t| &t.ttype),
+            Ok(&TokenType::Identifier("a".into()))
+        );
+    }
+
+    #[test]
+    fn reset_lexes_another_source_from_the_start() {
+        let options = LexerOptions {
+            fold_negative_literals: true,
+            ..LexerOptions::default()
+        };
+        let mut lexer = Lexer::with_options("#!/bin/sk\nvar a = -1;\nvar b", options);
+        let first: Vec<_> = lexer.by_ref().take(3).collect();
+        assert!(lexer.peek_token().is_ok());
+
+        lexer.reset("\"unterminated");
+        assert_eq!(
+            lexer.tokenize(),
+            [Err(LexerError::UnterminatedString {
+                line: 1,
+                col: 1,
+                offset: 0
+            })]
+        );
+
+        lexer.reset("#!/bin/sk\nvar a = -1;\nvar b");
+        assert_eq!(lexer.by_ref().take(3).collect::<Vec<_>>(), first);
+        let rest: Vec<TokenType<Cow<str>>> = lexer.map(|t| t.unwrap().ttype).collect();
+        assert_eq!(
+            rest,
+            [
+                TokenType::NegativeInteger(1),
+                TokenType::Semicolon,
+                TokenType::Var,
+                TokenType::Identifier("b".into()),
+                TokenType::Eof
+            ]
+        );
+    }
+
+    #[test]
+    fn count_tokens_matches_tokenize() {
+        let samples = [
+            "",
+            "// only a comment",
+            MAIN_SAMPLE,
+            "var s = \"hi ${name}\"; var c = 'c'; x ~ y 123abc",
+            "\"unterminated",
+        ];
+
+        for source in samples {
+            let tokens = Lexer::new(source).tokenize();
+            let errors = tokens.iter().filter(|token| token.is_err()).count();
+
+            assert_eq!(
+                count_tokens(source),
+                (tokens.len() - errors, errors),
+                "{source}"
+            );
+        }
+    }
+
+    #[test]
+    fn comments_carry_their_text() {
+        assert_eq!(
+            ttypes("x // a line comment\r\ny //"),
+            [
+                TokenType::Identifier("x".to_owned()),
+                TokenType::Comment(" a line comment".to_owned()),
+                TokenType::Identifier("y".to_owned()),
+                TokenType::Comment(String::new()),
+            ]
+        );
+        assert_eq!(
+            ttypes("/* a block\n * comment // still */ z /**/"),
+            [
+                TokenType::BlockComment(" a block\n * comment // still ".to_owned()),
+                TokenType::Identifier("z".to_owned()),
+                TokenType::BlockComment(String::new()),
+            ]
+        );
+    }
+
+    #[test]
+    fn block_comment_positions_and_errors() {
+        let tokens = Lexer::new("/* one\ntwo */ a /* never closed").tokenize();
+
+        let a = tokens[1].as_ref().expect("a should lex");
+        assert_eq!((a.line, a.col), (2, 8));
+        assert_eq!(
+            tokens[2],
+            Err(LexerError::UnterminatedComment {
+                line: 2,
+                col: 10,
+                offset: 16
+            })
+        );
+    }
+
+    #[test]
+    fn same_type_ignores_position() {
+        let tokens = Lexer::new("a\n  a b").tokenize();
+        let (first, second, third) = (
+            tokens[0].as_ref().unwrap(),
+            tokens[1].as_ref().unwrap(),
+            tokens[2].as_ref().unwrap(),
+        );
+
+        assert_ne!(first, second);
+        assert!(first.same_type(second));
+        assert!(!first.same_type(third));
+    }
+
+    #[test]
+    #[allow(clippy::approx_constant)]
+    fn tokens_match_the_main_sample() {
+        let tokens: Vec<Token> = Lexer::new(MAIN_SAMPLE)
+            .tokenize()
+            .into_iter()
+            .collect::<Result<_>>()
+            .expect("sample should lex");
+        let ident = |name: &str| TokenType::Identifier(name.to_owned());
+
+        #[rustfmt::skip]
+        let expected = [
+            TokenType::Fn, ident("math"), TokenType::LeftParen, TokenType::RightParen,
+            TokenType::Arrow, TokenType::Primitive(PrimitiveType::Int), TokenType::LeftBrace,
+            TokenType::Var, ident("a"), TokenType::Colon, TokenType::Primitive(PrimitiveType::Int),
+            TokenType::Equal, TokenType::Decimal(3.14159), TokenType::Semicolon,
+            ident("print"), TokenType::LeftParen, ident("a"), TokenType::LessEqual,
+            TokenType::Integer(1), TokenType::RightParen, TokenType::Semicolon,
+            TokenType::If, TokenType::LeftParen, ident("a"), TokenType::Ampersand,
+            TokenType::Integer(1), TokenType::Greater, TokenType::Integer(0),
+            TokenType::LogicalAnd, ident("a"), TokenType::BangEqual, TokenType::Integer(2),
+            TokenType::LogicalOr, TokenType::Bool(false), TokenType::RightParen, TokenType::LeftBrace,
+            TokenType::Return, ident("a"), TokenType::Semicolon, TokenType::RightBrace,
+            TokenType::Else, TokenType::LeftBrace, TokenType::Return, TokenType::Integer(1117),
+            TokenType::Semicolon, TokenType::RightBrace, TokenType::RightBrace,
+        ];
+
+        assert!(tokens_match(&tokens, &expected));
+        assert!(!tokens_match(&tokens, &expected[1..]));
+        assert!(!tokens_match(
+            &tokens[..2],
+            &[TokenType::Fn, ident("maths")]
+        ));
+    }
+
+    #[test]
+    fn integer_type_suffixes() {
+        assert_eq!(
+            ttypes("42i32 7u8 9 1int 2uint"),
+            [
+                TokenType::SuffixedInteger {
+                    value: 42,
+                    suffix: IntSuffix::I32
+                },
+                TokenType::SuffixedInteger {
+                    value: 7,
+                    suffix: IntSuffix::U8
+                },
+                TokenType::Integer(9),
+                TokenType::SuffixedInteger {
+                    value: 1,
+                    suffix: IntSuffix::Int
+                },
+                TokenType::SuffixedInteger {
+                    value: 2,
+                    suffix: IntSuffix::UInt
+                },
+            ]
+        );
+        assert_eq!(ttypes("255u8")[0].to_string(), "255u8");
+    }
+
+    #[test]
+    fn integer_too_large_for_its_suffix() {
+        let tokens = Lexer::new("x = 256u8; 1.5u8").tokenize();
+
+        assert_eq!(
+            tokens[2],
+            Err(LexerError::NumberOutOfRange {
+                line: 1,
+                col: 9,
+                offset: 8,
+                lexeme: "256u8".to_owned(),
+            })
+        );
+        assert_eq!(
+            tokens[4],
+            Err(LexerError::InvalidNumberSuffix {
+                line: 1,
+                col: 16,
+                offset: 15,
+                lexeme: "1.5u8".to_owned(),
+            })
+        );
+    }
+
+    #[test]
+    fn suffixed_integers_are_not_folded() {
+        assert_eq!(
+            folded_ttypes("-5i32 * -2"),
+            vec![
+                TokenType::Minus,
+                TokenType::SuffixedInteger {
+                    value: 5,
+                    suffix: IntSuffix::I32
+                },
+                TokenType::Star,
+                TokenType::NegativeInteger(2),
+            ]
+        );
+    }
+
+    #[test]
+    #[allow(clippy::approx_constant)]
+    fn float_type_suffixes() {
+        assert_eq!(
+            ttypes("3.14f32 2f64 2.5"),
+            [
+                TokenType::SuffixedDecimal {
+                    value: 3.14,
+                    suffix: FloatSuffix::F32
+                },
+                TokenType::SuffixedDecimal {
+                    value: 2.0,
+                    suffix: FloatSuffix::F64
+                },
+                TokenType::Decimal(2.5),
+            ]
+        );
+        assert_eq!(ttypes("2f64")[0].to_string(), "2.0f64");
+    }
+
+    #[test]
+    fn float_suffix_on_prefixed_literal_is_rejected() {
+        assert_eq!(
+            Lexer::new("0xFFf32").tokenize()[0],
+            Err(LexerError::InvalidNumberSuffix {
+                line: 1,
+                col: 7,
+                offset: 6,
+                lexeme: "0xFFf32".to_owned(),
+            })
+        );
+        assert!(matches!(
+            Lexer::new("0x1f64").tokenize()[0],
+            Err(LexerError::InvalidNumberSuffix { .. })
+        ));
+        // Only the spelling of a float suffix is ambiguous
+        assert_eq!(ttypes("0xFFF32"), [TokenType::Integer(0xFFF32)]);
+        assert_eq!(
+            Lexer::new("0b1f32").tokenize()[0],
+            Err(LexerError::InvalidNumberSuffix {
+                line: 1,
+                col: 6,
+                offset: 5,
+                lexeme: "0b1f32".to_owned(),
+            })
+        );
+    }
+
+    #[test]
+    fn radix_integers() {
+        assert_eq!(
+            ttypes("0xFF 0XdeadBEEF 0o17 0O0 0b10 0B1 0xFF_FF 0b1_0 0o7_7_7"),
+            [
+                TokenType::Integer(0xFF),
+                TokenType::Integer(0xDEAD_BEEF),
+                TokenType::Integer(0o17),
+                TokenType::Integer(0),
+                TokenType::Integer(0b10),
+                TokenType::Integer(1),
+                TokenType::Integer(0xFF_FF),
+                TokenType::Integer(0b1_0),
+                TokenType::Integer(0o777),
+            ]
+        );
+        assert_eq!(
+            ttypes(&format!("0x{:X}", u64::MAX)),
+            [TokenType::Integer(u64::MAX)]
+        );
+
+        let tokens = Lexer::new("0b; 0o8; 0b102; 0xFFu8; 0x1_0000_0000_0000_0000;").tokenize();
+        for (index, lexeme) in [(0, "0b"), (2, "0o8"), (4, "0b102"), (6, "0xFFu8")] {
+            assert!(
+                matches!(
+                    &tokens[index],
+                    Err(LexerError::InvalidNumberSuffix { lexeme: l, .. }) if l == lexeme
+                ),
+                "{lexeme}: {:?}",
+                tokens[index]
+            );
+        }
+        assert!(matches!(
+            tokens[8],
+            Err(LexerError::IntegerOverflow { offset: 46, .. })
+        ));
+    }
+
+    #[test]
+    fn float_too_large_for_f32() {
+        let source = format!("1{}f32", "0".repeat(40));
+
+        assert!(matches!(
+            Lexer::new(&source).tokenize()[0],
+            Err(LexerError::NumberOutOfRange { .. })
+        ));
+        assert!(matches!(
+            ttypes(&source.replace("f32", "f64"))[0],
+            TokenType::SuffixedDecimal { .. }
+        ));
+    }
+
+    #[test]
+    fn hex_floats() {
+        assert_eq!(
+            ttypes("0x1p4 0x1.8p1 0XAP-2 0x0.1p4"),
+            [
+                TokenType::Decimal(16.0),
+                TokenType::Decimal(3.0),
+                TokenType::Decimal(2.5),
+                TokenType::Decimal(1.0),
+            ]
+        );
+    }
+
+    #[test]
+    fn hex_float_without_exponent() {
+        let tokens = Lexer::new("0x1.8; 0x1p; 0x1p4f32").tokenize();
+
+        assert_eq!(
+            tokens[0],
+            Err(LexerError::MissingHexExponent {
+                line: 1,
+                col: 5,
+                offset: 4,
+                lexeme: "0x1.8".to_owned(),
+            })
+        );
+        assert_eq!(
+            tokens[2],
+            Err(LexerError::MissingHexExponent {
+                line: 1,
+                col: 11,
+                offset: 10,
+                lexeme: "0x1p".to_owned(),
+            })
+        );
+        assert_eq!(
+            tokens[4],
+            Err(LexerError::InvalidNumberSuffix {
+                line: 1,
+                col: 21,
+                offset: 20,
+                lexeme: "0x1p4f32".to_owned(),
+            })
+        );
+
+        let messages: Vec<_> = tokens
+            .iter()
+            .filter_map(|token| token.as_ref().err().map(ToString::to_string))
+            .collect();
+        assert_eq!(
+            messages[..2],
+            [
+                "[line 1: 5] Hex float '0x1.8' is missing its 'p' exponent",
+                "[line 1: 11] Hex float '0x1p' is missing the digits of its 'p' exponent",
+            ]
+        );
+    }
+
+    #[test]
+    fn digit_separators() {
+        assert_eq!(
+            ttypes("1_000 1_000.000_5 2_5u8 0x1_0p0"),
+            [
+                TokenType::Integer(1000),
+                TokenType::Decimal(1000.0005),
+                TokenType::SuffixedInteger {
+                    value: 25,
+                    suffix: IntSuffix::U8
+                },
+                TokenType::Decimal(16.0),
+            ]
+        );
+        assert_eq!(
+            folded_ttypes("-1_000"),
+            vec![TokenType::NegativeInteger(1000)]
+        );
+    }
+
+    #[test]
+    fn misplaced_digit_separators() {
+        for (source, col) in [
+            ("1__0", 2),
+            ("5_", 2),
+            ("1_.5", 2),
+            ("12_3_", 5),
+            ("0x1__0p0", 4),
+            ("0xFF_", 5),
+            ("0x_FF", 3),
+            ("0b1__0", 4),
+            ("0o_7", 3),
+        ] {
+            assert_eq!(
+                Lexer::new(source).tokenize(),
+                vec![Err(LexerError::InvalidNumberSeparator {
+                    line: 1,
+                    col,
+                    offset: col - 1
+                })],
+                "{source}"
+            );
+        }
+
+        // A leading `_` makes an identifier rather than a number
+        assert_eq!(ttypes("_5"), [TokenType::Identifier("_5".to_owned())]);
+    }
+
+    #[test]
+    fn decorator_and_lone_hash() {
+        assert_eq!(
+            ttypes("@inline fn #"),
+            [
+                TokenType::At,
+                TokenType::Identifier("inline".to_owned()),
+                TokenType::Fn,
+                TokenType::Hash,
+            ]
+        );
+    }
+
+    #[test]
+    fn tokenize_types_stops_at_the_first_error() {
+        assert_eq!(
+            tokenize_types("1 + 2"),
+            Ok(vec![
+                TokenType::Integer(1),
+                TokenType::Add,
+                TokenType::Integer(2),
+            ])
+        );
+        assert_eq!(
+            tokenize_types("1 ~ 2 ~"),
+            Err(LexerError::UnknownCharacter {
+                line: 1,
+                col: 3,
+                offset: 2,
+                character: '~',
+            })
+        );
+    }
+
+    #[test]
+    fn error_offset_is_in_bytes() {
+        let source = "var é = 1;\nvar ü = ~;";
+        let error = Lexer::new(source)
+            .tokenize()
+            .into_iter()
+            .find_map(Result::err)
+            .expect("source should fail to lex");
+
+        assert_eq!(
+            error,
+            LexerError::UnknownCharacter {
+                line: 2,
+                col: 9,
+                offset: 21,
+                character: '~',
+            }
+        );
+        assert_eq!(&source[error.offset()..], "~;");
+    }
+
+    #[test]
+    fn tokenize_with_recovery_separates_errors() {
+        // `@` is a token of its own, so use a char the language has no use for
+        let (tokens, errors) = Lexer::new("var a = 1 ~ 2;\nprint(a);").tokenize_with_recovery();
+
+        assert_eq!(
+            tokens.iter().map(|t| &t.ttype).collect::<Vec<_>>(),
+            [
+                &TokenType::Var,
+                &TokenType::Identifier("a".to_owned()),
+                &TokenType::Equal,
+                &TokenType::Integer(1),
+                &TokenType::Integer(2),
+                &TokenType::Semicolon,
+                &TokenType::Identifier("print".to_owned()),
+                &TokenType::LeftParen,
+                &TokenType::Identifier("a".to_owned()),
+                &TokenType::RightParen,
+                &TokenType::Semicolon,
+            ]
+        );
+        assert_eq!(
+            errors,
+            [LexerError::UnknownCharacter {
+                line: 1,
+                col: 11,
+                offset: 10,
+                character: '~',
+            }]
+        );
+    }
+
+    #[test]
+    fn tokenize_into_reuses_the_buffer() {
+        let mut buf = Vec::new();
+
+        Lexer::new(MAIN_SAMPLE).tokenize_into(&mut buf);
+        assert_eq!(buf, Lexer::new(MAIN_SAMPLE).tokenize());
+        let capacity = buf.capacity();
+
+        for source in ["var a = 1;", "", "fn f() { ~ }"] {
+            Lexer::new(source).tokenize_into(&mut buf);
+            assert_eq!(buf, Lexer::new(source).tokenize());
+            assert_eq!(buf.capacity(), capacity);
+        }
+    }
+
+    #[test]
+    fn tokenize_lazy_stops_early() {
+        let mut lexer = Lexer::new("fn f() {\n    return 1;\n}\nfn g() {}");
+
+        let head: Vec<_> = lexer
+            .tokenize_lazy()
+            .take_while(|token| !matches!(token, Ok(t) if t.ttype == TokenType::RightBrace))
+            .collect();
+
+        assert_eq!(head.len(), 8);
+        assert_eq!(lexer.position(), (3, 1));
+        assert_eq!(
+            lexer
+                .tokenize_lazy()
+                .map(|token| token.unwrap().ttype)
+                .collect::<Vec<_>>(),
+            [
+                TokenType::Fn,
+                TokenType::Identifier("g".to_owned()),
+                TokenType::LeftParen,
+                TokenType::RightParen,
+                TokenType::LeftBrace,
+                TokenType::RightBrace,
+            ]
+        );
+        assert_eq!(lexer.tokenize_lazy().count(), 0);
+    }
+
+    #[test]
+    fn leading_trivia_keeps_blank_lines_and_comments() {
+        let source = "var a = 1;\n\n\n// Entry point\n/* note */\nfn main() { ~ }\n";
+        let tokens: Vec<_> = Lexer::new(source).with_leading_trivia().collect();
+
+        assert_eq!(tokens.len(), 13, "comments are trivia rather than tokens");
+        assert_eq!(
+            tokens[5]
+                .as_ref()
+                .map(|(trivia, token)| (*trivia, &token.ttype)),
+            Ok(("\n\n\n// Entry point\n/* note */\n", &TokenType::Fn))
+        );
+        assert_eq!(
+            tokens[0].as_ref().map(|(trivia, _)| *trivia),
+            Ok(""),
+            "nothing precedes the first token"
+        );
+        assert!(tokens[10].is_err());
+        assert_eq!(
+            tokens[11]
+                .as_ref()
+                .map(|(trivia, token)| (*trivia, &token.ttype)),
+            Ok((" ", &TokenType::RightBrace)),
+        );
+        assert_eq!(
+            tokens[12]
+                .as_ref()
+                .map(|(trivia, token)| (*trivia, &token.ttype)),
+            Ok(("\n", &TokenType::Eof)),
+        );
+
+        let rebuilt: String = tokens
+            .iter()
+            .flatten()
+            .map(|(trivia, token)| format!("{trivia}{}", &source[token.span.start..token.span.end]))
+            .collect();
+        assert_eq!(rebuilt, source.replace(" ~", ""));
+    }
+
+    #[test]
+    fn booleans_are_literals() {
+        let ttypes = ttypes("true false");
+
+        assert_eq!(ttypes, [TokenType::Bool(true), TokenType::Bool(false)]);
+        for ttype in &ttypes {
+            assert!(ttype.is_literal(), "{ttype:?}");
+            assert!(!ttype.is_keyword(), "{ttype:?}");
+        }
+        assert_eq!(ttypes[1].to_string(), "false");
+    }
+
+    #[test]
+    fn char_literal_is_a_single_scalar() {
+        assert_eq!(
+            ttypes("'é' '🇫' ' '"),
+            [
+                TokenType::Character('é'),
+                TokenType::Character('🇫'),
+                TokenType::Character(' '),
+            ]
+        );
+
+        // A flag and a letter with a combining accent are each two scalars
+        let source = "x = '🇫🇷'; y = 'e\u{301}';";
+        let tokens = Lexer::new(source).tokenize();
+        assert_eq!(
+            tokens[2],
+            Err(LexerError::MultiScalarChar {
+                line: 1,
+                col: 5,
+                offset: 4,
+            })
+        );
+        // Lexing carries on after the closing quote
+        assert_eq!(
+            tokens[3].as_ref().map(|t| &t.ttype),
+            Ok(&TokenType::Semicolon)
+        );
+        assert!(matches!(
+            tokens[6],
+            Err(LexerError::MultiScalarChar { offset: 20, .. })
+        ));
+    }
+
+    #[test]
+    fn hex_escapes() {
+        assert_eq!(
+            ttypes(r#"'\x41' '\x7f' "\x41\x7F""#),
+            [
+                TokenType::Character('A'),
+                TokenType::Character('\x7f'),
+                TokenType::String("A\x7F".to_owned()),
+            ]
+        );
+
+        // Too few digits
+        let tokens = Lexer::new(r#"s = "a\x4"; c = '\x4';"#).tokenize();
+        assert_eq!(
+            tokens[2],
+            Err(LexerError::InvalidHexEscape {
+                line: 1,
+                col: 7,
+                offset: 6,
+            })
+        );
+        assert_eq!(
+            tokens[3].as_ref().map(|t| &t.ttype),
+            Ok(&TokenType::Semicolon)
+        );
+        assert!(matches!(
+            tokens[6],
+            Err(LexerError::InvalidHexEscape { offset: 17, .. })
+        ));
+        assert_eq!(
+            tokens[7].as_ref().map(|t| &t.ttype),
+            Ok(&TokenType::Semicolon)
+        );
+
+        // Above ASCII
+        let tokens = Lexer::new(r#""\x80" '\xFF'"#).tokenize();
+        assert!(matches!(
+            tokens[0],
+            Err(LexerError::InvalidHexEscape { offset: 1, .. })
+        ));
+        assert!(matches!(
+            tokens[1],
+            Err(LexerError::InvalidHexEscape { offset: 8, .. })
+        ));
+        assert_eq!(tokens.len(), 2);
+    }
 }
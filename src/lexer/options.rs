@@ -0,0 +1,48 @@
+/// Configuration for a [Lexer](super::Lexer)
+#[derive(Debug, PartialEq, Eq, Clone, Default)]
+pub struct LexerOptions {
+    pub column_encoding: ColumnEncoding,
+    /// Whether a `\` directly before a newline joins the two lines, as in shell scripts. The
+    /// lines still count separately for token positions. Off by default, where `\` is an unknown
+    /// character.
+    pub line_continuations: bool,
+    /// Whether a `-` directly followed by a number lexes as a single negative literal when it is
+    /// in prefix position, i.e. where an expression starts: at the start of the source, after an
+    /// operator, an opening bracket, `,`, `;`, `:` or `=>`, or after `return`, `match` or `in`. So
+    /// `-5`, `a * -5` and `[-5]` fold while `a - 5`, `a -5` and `a[0] -5` do not. Negative
+    /// integers lex to [NegativeInteger](super::TokenType::NegativeInteger) and negative decimals
+    /// to a negative [Decimal](super::TokenType::Decimal). Literals with a type suffix such as
+    /// `-5i32` are never folded.
+    pub fold_negative_literals: bool,
+    /// The most bytes allowed between the quotes of a string literal, so that untrusted input
+    /// cannot make the lexer hand out arbitrarily large strings. Longer strings are reported as
+    /// [StringTooLong](super::error::LexerError::StringTooLong). Unlimited when `None`, the
+    /// default.
+    pub max_string_len: Option<usize>,
+    /// Whether a `\r\n` inside a string literal is kept as written. Off by default, where it
+    /// becomes a `\n` so that a string has the same text whichever line endings its file uses.
+    pub keep_crlf_in_strings: bool,
+}
+
+/// The unit `col` is counted in
+#[derive(Debug, PartialEq, Eq, Clone, Copy, Default)]
+pub enum ColumnEncoding {
+    /// UTF-8 bytes
+    Utf8,
+    /// UTF-16 code units, which is how the Language Server Protocol counts columns
+    Utf16,
+    /// Unicode scalar values, i.e. [char]s
+    #[default]
+    Scalar,
+}
+
+impl ColumnEncoding {
+    /// Width of `ch` in this encoding
+    pub fn width(self, ch: char) -> usize {
+        match self {
+            Self::Utf8 => ch.len_utf8(),
+            Self::Utf16 => ch.len_utf16(),
+            Self::Scalar => 1,
+        }
+    }
+}
@@ -0,0 +1,277 @@
+use std::{
+    collections::VecDeque,
+    io::{self, ErrorKind, Read},
+};
+
+use crate::span::Span;
+
+use super::{cursor::Cursor, error::Result, options::LexerOptions, Lexer, Token, TokenType};
+
+/// Bytes read at a time unless set with [StreamLexer::with_chunk_size]
+const DEFAULT_CHUNK_SIZE: usize = 8 * 1024;
+
+/// How far from the end of the buffered text, in bytes, a token must end to be handed out before
+/// the reader is exhausted. The lexer looks at most two chars past a token to decide where it
+/// ends, e.g. a digit after `1.`, so such a token can not change once the next chunk arrives.
+const LOOKAHEAD: usize = 2 * 4;
+
+/// A lexer that pulls its source from a [Read] in chunks instead of holding all of it in memory.
+///
+/// Yields the same tokens and errors as iterating a [Lexer] over the whole source, with the same
+/// lines, columns and byte offsets. Only the text from the token being lexed to the end of the
+/// last chunk is kept, and a token that runs into the end of a chunk is lexed again once the next
+/// one has been read.
+///
+/// The source must be UTF-8. An I/O error or invalid UTF-8 ends the stream early, without an
+/// `Eof` token, and can be retrieved with [StreamLexer::take_io_error].
+pub struct StreamLexer<R> {
+    reader: R,
+    chunk_size: usize,
+    options: LexerOptions,
+    /// Text that has not been lexed yet, after at most one char that has
+    buffer: String,
+    /// The bytes at the end of the last chunk that only make up part of a char
+    partial_char: Vec<u8>,
+    /// Byte offset in `buffer` where lexing carries on
+    pos: usize,
+    /// Byte offset in the whole source of the start of `buffer`
+    base: usize,
+    line: usize,
+    col: usize,
+    prefix_position: bool,
+    reader_done: bool,
+    finished: bool,
+    ready: VecDeque<Result<Token>>,
+    io_error: Option<io::Error>,
+}
+
+impl<R: Read> StreamLexer<R> {
+    pub fn new(reader: R) -> Self {
+        Self::with_options(reader, LexerOptions::default())
+    }
+
+    pub fn with_options(reader: R, options: LexerOptions) -> Self {
+        Self {
+            reader,
+            chunk_size: DEFAULT_CHUNK_SIZE,
+            options,
+            buffer: String::new(),
+            partial_char: Vec::new(),
+            pos: 0,
+            base: 0,
+            line: 1,
+            col: 0,
+            prefix_position: true,
+            reader_done: false,
+            finished: false,
+            ready: VecDeque::new(),
+            io_error: None,
+        }
+    }
+
+    /// Sets how many bytes are read from the reader at a time, 8 KiB by default
+    pub fn with_chunk_size(mut self, chunk_size: usize) -> Self {
+        assert!(chunk_size > 0, "chunk size must not be zero");
+        self.chunk_size = chunk_size;
+        self
+    }
+
+    /// Takes the error that ended the stream early, if reading the source failed
+    pub fn take_io_error(&mut self) -> Option<io::Error> {
+        self.io_error.take()
+    }
+
+    /// Lexes every buffered token that can not be changed by text read later
+    fn lex_buffered(&mut self) {
+        let mut lexer = Lexer {
+            source: Cursor::starting_at(&self.buffer, self.pos),
+            start: self.pos,
+            line: self.line,
+            col: self.col,
+            options: self.options.clone(),
+            finished: false,
+            peeked: None,
+            prefix_position: self.prefix_position,
+        };
+
+        loop {
+            let result = lexer.next_token();
+            let end = lexer.source.offset();
+
+            if !self.reader_done {
+                // A quote looks for the next quote or whitespace to spot a char literal holding
+                // several scalars, which may not have been read yet
+                let after_start = &self.buffer[lexer.start..];
+                let open_quote = after_start.starts_with('\'')
+                    && !after_start[1..].contains(|ch: char| ch == '\'' || ch.is_whitespace());
+
+                if open_quote || end + LOOKAHEAD > self.buffer.len() {
+                    break;
+                }
+            }
+
+            let is_eof = matches!(
+                result,
+                Ok(Token {
+                    ttype: TokenType::Eof,
+                    ..
+                })
+            );
+            self.ready.push_back(match result {
+                Ok(token) => Ok(Token {
+                    span: Span::new(token.span.start + self.base, token.span.end + self.base),
+                    ..token.into_owned()
+                }),
+                Err(mut error) => {
+                    error.shift_offset(self.base);
+                    Err(error)
+                }
+            });
+
+            self.pos = end;
+            self.line = lexer.line;
+            self.col = lexer.col;
+            self.prefix_position = lexer.prefix_position;
+
+            if is_eof {
+                self.finished = true;
+                break;
+            }
+        }
+    }
+
+    /// Drops the lexed text and reads the next chunk
+    fn refill(&mut self) -> io::Result<()> {
+        // Keep the last lexed char so that lexing never restarts at offset 0, where a `#!` would
+        // be taken for a shebang
+        let keep = self.buffer[..self.pos]
+            .char_indices()
+            .next_back()
+            .map_or(0, |(index, _)| index);
+        self.buffer.drain(..keep);
+        self.base += keep;
+        self.pos -= keep;
+
+        let mut chunk = vec![0; self.chunk_size];
+        let read = loop {
+            match self.reader.read(&mut chunk) {
+                Err(error) if error.kind() == ErrorKind::Interrupted => continue,
+                result => break result?,
+            }
+        };
+
+        if read == 0 {
+            self.reader_done = true;
+            if !self.partial_char.is_empty() {
+                return Err(io::Error::new(
+                    ErrorKind::InvalidData,
+                    "stream did not contain valid UTF-8",
+                ));
+            }
+            return Ok(());
+        }
+
+        self.partial_char.extend_from_slice(&chunk[..read]);
+        let valid = match std::str::from_utf8(&self.partial_char) {
+            Ok(text) => text.len(),
+            Err(error) if error.error_len().is_none() => error.valid_up_to(),
+            Err(error) => return Err(io::Error::new(ErrorKind::InvalidData, error)),
+        };
+        let text = std::str::from_utf8(&self.partial_char[..valid]).expect("validated above");
+        self.buffer.push_str(text);
+        self.partial_char.drain(..valid);
+
+        Ok(())
+    }
+}
+
+/// Yields every token followed by exactly one `Eof`, like iterating a [Lexer]
+impl<R: Read> Iterator for StreamLexer<R> {
+    type Item = Result<Token>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        loop {
+            if let Some(token) = self.ready.pop_front() {
+                return Some(token);
+            }
+            if self.finished {
+                return None;
+            }
+
+            if let Err(error) = self.refill() {
+                self.io_error = Some(error);
+                self.finished = true;
+                return None;
+            }
+            self.lex_buffered();
+        }
+    }
+}
+
+impl<R: Read> std::iter::FusedIterator for StreamLexer<R> {}
+
+#[cfg(test)]
+mod tests {
+    use std::io::Cursor;
+
+    use super::*;
+
+    const SOURCE: &str = "#!/usr/bin/env sk\nfn main() {\n    var größe = 1_000.5e3 - 42u8;\n    /* block\n       comment */ var s = \"multi\nline ${größe}\";\n    'outer: while a..=b { print('é', \"🇫🇷\"); } // done\n}\n";
+
+    fn in_memory(source: &str) -> Vec<Result<Token>> {
+        Lexer::new(source)
+            .map(|result| result.map(Token::into_owned))
+            .collect()
+    }
+
+    fn streamed(source: &str, chunk_size: usize) -> Vec<Result<Token>> {
+        StreamLexer::new(Cursor::new(source.as_bytes().to_vec()))
+            .with_chunk_size(chunk_size)
+            .collect()
+    }
+
+    #[test]
+    fn matches_the_in_memory_lexer_at_every_chunk_size() {
+        let expected = in_memory(SOURCE);
+
+        for chunk_size in 1..=SOURCE.len() + 1 {
+            assert_eq!(
+                streamed(SOURCE, chunk_size),
+                expected,
+                "chunk size {chunk_size}"
+            );
+        }
+    }
+
+    #[test]
+    fn tokens_split_across_reads_are_joined() {
+        // The first two reads end inside `ident` and inside `12345`
+        let source = "var ident = 12345;";
+        let tokens = streamed(source, 7);
+
+        assert_eq!(tokens, in_memory(source));
+        assert_eq!(tokens[3].as_ref().unwrap().ttype, TokenType::Integer(12345));
+        assert_eq!(tokens[3].as_ref().unwrap().span, Span::new(12, 17));
+    }
+
+    #[test]
+    fn errors_keep_their_offsets() {
+        let source = "var a = 1;\nvar b = a ~ 'xy';";
+
+        assert_eq!(streamed(source, 3), in_memory(source));
+    }
+
+    #[test]
+    fn invalid_utf8_ends_the_stream() {
+        let mut lexer = StreamLexer::new(Cursor::new(b"var a \xff".to_vec())).with_chunk_size(4);
+        let tokens: Vec<_> = lexer.by_ref().collect();
+
+        assert!(tokens
+            .iter()
+            .all(|token| token.as_ref().unwrap().ttype != TokenType::Eof));
+        assert_eq!(
+            lexer.take_io_error().map(|error| error.kind()),
+            Some(ErrorKind::InvalidData)
+        );
+    }
+}
@@ -1 +1,4 @@
 pub mod lexer;
+pub mod line_index;
+pub mod parser;
+pub mod span;
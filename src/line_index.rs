@@ -0,0 +1,155 @@
+/// Converts between byte offsets into a source and 1-based line and column positions, columns
+/// being counted in chars.
+///
+/// The offset of the start of every line is computed up front, so a lookup is a binary search
+/// followed by a walk over a single line.
+#[derive(Debug, Clone)]
+pub struct LineIndex<'src> {
+    source: &'src str,
+    /// Byte offset of the first char of every line, in order
+    line_starts: Vec<usize>,
+}
+
+impl<'src> LineIndex<'src> {
+    pub fn new(source: &'src str) -> Self {
+        let line_starts = std::iter::once(0).chain(line_breaks(source)).collect();
+
+        Self {
+            source,
+            line_starts,
+        }
+    }
+
+    pub fn line_count(&self) -> usize {
+        self.line_starts.len()
+    }
+
+    /// The text of the 1-based `line` without its line ending, `None` past the last line
+    pub fn line(&self, line: usize) -> Option<&'src str> {
+        let &line_start = self.line_starts.get(line.checked_sub(1)?)?;
+        let line_end = self
+            .line_starts
+            .get(line)
+            .map_or(self.source.len(), |&next| next - 1);
+
+        let text = &self.source[line_start..line_end];
+        Some(text.strip_suffix('\r').unwrap_or(text))
+    }
+
+    /// The line and column of the char at `offset`, clamping offsets past the end of the source
+    /// to its end. An offset inside a multi-byte char is treated as the start of that char.
+    pub fn line_col(&self, offset: usize) -> (usize, usize) {
+        let mut offset = offset.min(self.source.len());
+        while !self.source.is_char_boundary(offset) {
+            offset -= 1;
+        }
+        let line = self.line_starts.partition_point(|&start| start <= offset) - 1;
+        let line_start = self.line_starts[line];
+
+        let col = self.source[line_start..]
+            .char_indices()
+            .take_while(|&(index, _)| line_start + index < offset)
+            .count();
+
+        (line + 1, col + 1)
+    }
+
+    /// The byte offset of the char at `line` and `col`, clamping positions past the end of a line
+    /// to the end of that line and lines past the end of the source to its end
+    pub fn offset(&self, line: usize, col: usize) -> usize {
+        let Some(&line_start) = self.line_starts.get(line.saturating_sub(1)) else {
+            return self.source.len();
+        };
+        let line_end = self
+            .line_starts
+            .get(line)
+            .map_or(self.source.len(), |&next| next - 1);
+
+        self.source[line_start..line_end]
+            .char_indices()
+            .nth(col.saturating_sub(1))
+            .map_or(line_end, |(index, _)| line_start + index)
+    }
+}
+
+/// The byte offset just past every line break in `source`, where a line break is a `\n`, a `\r\n`
+/// or a lone `\r`
+pub(crate) fn line_breaks(source: &str) -> impl Iterator<Item = usize> + '_ {
+    source
+        .match_indices(['\n', '\r'])
+        .filter(|&(index, ch)| ch == "\n" || !source[index + 1..].starts_with('\n'))
+        .map(|(index, _)| index + 1)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SOURCE: &str = "var é = 1;\nvar 名前 = \"😀\";\n\nend";
+
+    #[test]
+    fn line_col_counts_chars() {
+        let index = LineIndex::new(SOURCE);
+
+        assert_eq!(index.line_count(), 4);
+        assert_eq!(index.line_col(0), (1, 1));
+        // `=` after the two byte `é`
+        assert_eq!(index.line_col(SOURCE.find('=').unwrap()), (1, 7));
+        assert_eq!(index.line_col(SOURCE.find('名').unwrap()), (2, 5));
+        // `"` after the four byte emoji
+        assert_eq!(index.line_col(SOURCE.rfind('"').unwrap()), (2, 12));
+        assert_eq!(index.line_col(SOURCE.find("\n\n").unwrap() + 1), (3, 1));
+        assert_eq!(index.line_col(SOURCE.len()), (4, 4));
+    }
+
+    #[test]
+    fn offset_is_the_inverse_of_line_col() {
+        let index = LineIndex::new(SOURCE);
+
+        for (offset, _) in SOURCE.char_indices() {
+            let (line, col) = index.line_col(offset);
+            assert_eq!(index.offset(line, col), offset, "{line}:{col}");
+        }
+    }
+
+    #[test]
+    fn offset_clamps_out_of_range_positions() {
+        let index = LineIndex::new(SOURCE);
+
+        assert_eq!(index.offset(1, 100), SOURCE.find('\n').unwrap());
+        assert_eq!(index.offset(100, 1), SOURCE.len());
+    }
+
+    #[test]
+    fn offsets_inside_a_char_are_at_its_start() {
+        let index = LineIndex::new("é\n😀x");
+
+        assert_eq!(index.line_col(1), (1, 1));
+        assert_eq!(index.line_col(2), (1, 2));
+        assert_eq!(index.line_col(5), (2, 1));
+        assert_eq!(index.line_col(7), (2, 2));
+    }
+
+    #[test]
+    fn line_text_excludes_line_endings() {
+        let index = LineIndex::new("first\r\nsecond\n\nlast");
+
+        assert_eq!(index.line(1), Some("first"));
+        assert_eq!(index.line(2), Some("second"));
+        assert_eq!(index.line(3), Some(""));
+        assert_eq!(index.line(4), Some("last"));
+        assert_eq!(index.line(0), None);
+        assert_eq!(index.line(5), None);
+    }
+
+    #[test]
+    fn lone_carriage_returns_break_lines() {
+        let index = LineIndex::new("first\rsecond\r\nlast");
+
+        assert_eq!(index.line_count(), 3);
+        assert_eq!(index.line(1), Some("first"));
+        assert_eq!(index.line(2), Some("second"));
+        assert_eq!(index.line_col(11), (2, 6));
+        assert_eq!(index.line_col(14), (3, 1));
+    }
+}
@@ -1,7 +1,89 @@
-use sklang::lexer::Lexer;
+use std::process::ExitCode;
 
-fn main() {
-    let source = "fn math() -> int { var a: int = 3.14159; print(a <= 1); if (a & 1 > 0 && a != 2 || false) { return a; } else { return 1117; } }";
-    let mut lexer = Lexer::new(source);
-    lexer.tokenize().iter().for_each(|t| println!("{t:?}"));
+use sklang::{
+    lexer::{Lexer, Token},
+    line_index::LineIndex,
+    parser::{printer::pretty_print, Parser},
+};
+
+/// Lexes and parses a program read from the file given as the first argument, or from stdin when
+/// there is none. Every error is reported to stderr along with the line it was found on, and the
+/// program is pretty printed to stdout if there were none.
+fn main() -> ExitCode {
+    let args: Vec<String> = std::env::args().skip(1).collect();
+
+    let source = match args.iter().find(|arg| !arg.starts_with("--")) {
+        Some(path) => std::fs::read_to_string(path),
+        None => std::io::read_to_string(std::io::stdin()),
+    };
+    let source = match source {
+        Ok(source) => source,
+        Err(error) => {
+            eprintln!("error: could not read the source: {error}");
+            return ExitCode::FAILURE;
+        }
+    };
+
+    // `--json` writes the tokens as JSON instead, for editor integrations
+    #[cfg(feature = "serde")]
+    if args.iter().any(|arg| arg == "--json") {
+        println!("{}", sklang::lexer::json::dump_tokens_json(&source));
+        return ExitCode::SUCCESS;
+    }
+
+    let index = LineIndex::new(&source);
+
+    let mut tokens = Vec::new();
+    let mut failed = false;
+    for token in Lexer::new(&source).tokenize() {
+        match token {
+            Ok(token) => tokens.push(token),
+            Err(error) => {
+                report(&index, "error", error.position(), &error.to_string());
+                failed = true;
+            }
+        }
+    }
+    if failed {
+        return ExitCode::FAILURE;
+    }
+
+    match parse(tokens, &index) {
+        Some(program) => {
+            print!("{}", pretty_print(&program));
+            ExitCode::SUCCESS
+        }
+        None => ExitCode::FAILURE,
+    }
+}
+
+/// Parses every item, reporting warnings and errors as they are found. Returns `None` if there
+/// were any errors.
+fn parse(tokens: Vec<Token>, index: &LineIndex) -> Option<sklang::parser::ast::Program> {
+    let mut parser = Parser::new(tokens);
+    let (program, errors) = parser.parse_program_recovering();
+
+    for warning in parser.warnings() {
+        report(index, "warning", warning.position(), &warning.to_string());
+    }
+    for error in &errors {
+        report(index, "error", error.position(), &error.to_string());
+    }
+
+    errors.is_empty().then_some(program)
+}
+
+/// Writes a message to stderr followed by the source line it points at, with a caret under the
+/// column
+fn report(index: &LineIndex, severity: &str, (line, col): (usize, usize), message: &str) {
+    eprintln!("{severity}: {message}");
+
+    if let Some(text) = index.line(line) {
+        let gutter = " ".repeat(line.to_string().len());
+        let caret = " ".repeat(col.saturating_sub(1));
+
+        eprintln!("{gutter} |");
+        eprintln!("{line} | {text}");
+        eprintln!("{gutter} | {caret}^");
+    }
 }
@@ -0,0 +1,315 @@
+use crate::{lexer::PrimitiveType, span::Span};
+
+use super::token_stream::TokenStream;
+
+/// Every item of a source file, in order
+#[derive(Debug, PartialEq, Clone)]
+pub struct Program {
+    pub items: Vec<Item>,
+}
+
+/// A top level declaration along with the attributes attached to it
+#[derive(Debug, PartialEq, Clone)]
+pub struct Item {
+    pub attrs: Vec<Attribute>,
+    /// The arguments of every `#[cfg(...)]` attribute on the item, so that a later pass can
+    /// strip items that are configured out
+    pub cfg: Vec<TokenStream>,
+    pub kind: ItemKind,
+}
+
+#[derive(Debug, PartialEq, Clone)]
+pub enum ItemKind {
+    Fn(FnDecl),
+    Struct(StructDecl),
+    Enum(EnumDecl),
+    /// A variable declared outside of any function
+    Global(VarDecl),
+}
+
+/// `#[name]` or `#[name(args)]`
+#[derive(Debug, PartialEq, Clone)]
+pub struct Attribute {
+    pub name: String,
+    pub args: TokenStream,
+}
+
+#[derive(Debug, PartialEq, Clone)]
+pub struct FnDecl {
+    pub name: Ident,
+    pub params: Vec<(Ident, Type)>,
+    /// `None` when the declaration has no `-> ReturnType`
+    pub return_type: Option<Type>,
+    pub body: Block,
+}
+
+/// `struct Name { field: Type, ... }`
+#[derive(Debug, PartialEq, Clone)]
+pub struct StructDecl {
+    pub name: Ident,
+    pub fields: Vec<(String, Type)>,
+}
+
+/// `enum Name { Variant, Variant(Type, ...), ... }`
+#[derive(Debug, PartialEq, Clone)]
+pub struct EnumDecl {
+    pub name: Ident,
+    pub variants: Vec<Variant>,
+}
+
+#[derive(Debug, PartialEq, Clone)]
+pub struct Variant {
+    pub name: String,
+    /// The types of the data a tuple-style variant carries, empty for a plain variant
+    pub fields: Vec<Type>,
+}
+
+#[derive(Debug, PartialEq, Clone)]
+pub enum Type {
+    Primitive(PrimitiveType),
+    /// A user defined type, e.g. a struct or enum
+    Named(String),
+    /// `[element]`, or `[element; len]` when the length is fixed
+    Array {
+        element: Box<Type>,
+        len: Option<u64>,
+    },
+    /// A named type applied to type arguments, e.g. `Map<int, [char]>`
+    Generic {
+        name: String,
+        args: Vec<Type>,
+    },
+}
+
+#[derive(Debug, PartialEq, Clone)]
+pub struct Block {
+    pub stmts: Vec<Stmt>,
+}
+
+/// `var name: ty = init;` where both the type annotation and initializer are optional
+#[derive(Debug, PartialEq, Clone)]
+pub struct VarDecl {
+    pub name: Ident,
+    pub ty: Option<Type>,
+    pub init: Option<Expr>,
+}
+
+#[derive(Debug, PartialEq, Clone)]
+pub enum Stmt {
+    Var(VarDecl),
+    If(If),
+    While(While),
+    Loop(Loop),
+    For(For),
+    /// `break;`, or `break 'label;` to exit an outer loop
+    Break(Option<Ident>),
+    /// `continue;`, or `continue 'label;` to continue an outer loop
+    Continue(Option<Ident>),
+    Return(Option<Expr>),
+    Block(Block),
+    Expr(Expr),
+}
+
+/// `target = value`, or `target op= value` for a compound assignment. Assignment is an expression
+/// that binds looser than any other and groups to the right, so `a = b = 1` is `a = (b = 1)`.
+#[derive(Debug, PartialEq, Clone)]
+pub struct Assign {
+    /// A variable, an index into one or a field of one, e.g. `a`, `arr[i]` or `p.x`
+    pub target: Expr,
+    /// The operator of a compound assignment such as `+=`, `None` for a plain `=`
+    pub op: Option<BinaryOp>,
+    pub value: Expr,
+}
+
+/// `if (cond) { ... }` with an optional `else` branch
+#[derive(Debug, PartialEq, Clone)]
+pub struct If {
+    pub cond: Expr,
+    pub then_block: Block,
+    pub else_block: Option<Else>,
+}
+
+#[derive(Debug, PartialEq, Clone)]
+pub enum Else {
+    Block(Block),
+    /// An `else if`, chaining onto another conditional
+    If(Box<If>),
+}
+
+/// `while (cond) { ... }`
+#[derive(Debug, PartialEq, Clone)]
+pub struct While {
+    pub label: Option<Ident>,
+    pub cond: Expr,
+    pub body: Block,
+}
+
+/// `loop { ... }`, which only exits through a `break` or `return`
+#[derive(Debug, PartialEq, Clone)]
+pub struct Loop {
+    pub label: Option<Ident>,
+    pub body: Block,
+}
+
+/// `for binding in iterable { ... }`, e.g. `for i in 0..10 { ... }`.
+///
+/// There is no C-style `for (init; cond; step)`, a `while` loop covers those cases.
+///
+/// Any loop may be labelled, e.g. `'outer: for i in 0..10 { ... }`, so that a `break` or
+/// `continue` in a nested loop can refer to it. A label's name leaves out the `'` while its span
+/// covers it.
+#[derive(Debug, PartialEq, Clone)]
+pub struct For {
+    pub label: Option<Ident>,
+    pub binding: Ident,
+    pub iterable: Expr,
+    pub body: Block,
+}
+
+#[derive(Debug, PartialEq, Clone)]
+pub enum Expr {
+    Literal(Literal),
+    Identifier(Ident),
+    Array(Vec<Expr>),
+    Unary {
+        op: UnaryOp,
+        operand: Box<Expr>,
+    },
+    Binary {
+        left: Box<Expr>,
+        op: BinaryOp,
+        right: Box<Expr>,
+    },
+    Call {
+        callee: Box<Expr>,
+        args: Vec<Expr>,
+    },
+    /// `target[index]`
+    Index {
+        target: Box<Expr>,
+        index: Box<Expr>,
+    },
+    /// `base.name`
+    Field {
+        base: Box<Expr>,
+        name: String,
+    },
+    /// A block in expression position, currently only the body of a match arm
+    Block(Block),
+    /// `match scrutinee { pattern => expr, ... }`, the arms being tried in order
+    Match {
+        scrutinee: Box<Expr>,
+        arms: Vec<(Pattern, Expr)>,
+    },
+    /// `Name { field: value, ... }`
+    StructLit {
+        name: String,
+        fields: Vec<(String, Expr)>,
+    },
+    /// `start..end`, or `start..=end` when `inclusive`
+    Range {
+        start: Box<Expr>,
+        end: Box<Expr>,
+        inclusive: bool,
+    },
+    Assign(Box<Assign>),
+}
+
+/// A name, along with where it appears in the source, either where it is declared or used
+#[derive(Debug, PartialEq, Clone)]
+pub struct Ident {
+    pub name: String,
+    pub span: Span,
+}
+
+#[derive(Debug, PartialEq, Clone)]
+pub enum Literal {
+    Bool(bool),
+    Character(char),
+    Decimal(f64),
+    Integer(u64),
+    String(String),
+    /// `null`, the absence of a value
+    Null,
+}
+
+impl Literal {
+    /// The value of a numeric literal as an `i64`, provided it converts without losing anything,
+    /// so `2.0` gives `Some(2)` while `2.5` and values out of range give `None`
+    pub fn as_int_lossless(&self) -> Option<i64> {
+        match *self {
+            Self::Integer(n) => i64::try_from(n).ok(),
+            // 2^63 is exactly representable as an f64 while i64::MAX is not
+            Self::Decimal(n)
+                if n.fract() == 0.0 && (-(2f64.powi(63))..2f64.powi(63)).contains(&n) =>
+            {
+                Some(n as i64)
+            }
+            _ => None,
+        }
+    }
+}
+
+#[derive(Debug, PartialEq, Clone)]
+pub enum Pattern {
+    Literal(Literal),
+    /// `_`, which matches anything
+    Wildcard,
+}
+
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub enum UnaryOp {
+    /// -
+    Negate,
+    /// !
+    Not,
+}
+
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub enum BinaryOp {
+    Add,
+    Subtract,
+    Multiply,
+    Divide,
+    Modulo,
+    /// **
+    Power,
+
+    Equal,
+    NotEqual,
+    Greater,
+    GreaterEqual,
+    Less,
+    LessEqual,
+
+    /// &
+    BitAnd,
+    /// |
+    BitOr,
+    /// &&
+    And,
+    /// ||
+    Or,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn whole_decimal_converts_to_int() {
+        assert_eq!(Literal::Decimal(2.0).as_int_lossless(), Some(2));
+        assert_eq!(Literal::Decimal(-2.0).as_int_lossless(), Some(-2));
+        assert_eq!(Literal::Integer(7).as_int_lossless(), Some(7));
+    }
+
+    #[test]
+    fn lossy_conversions_are_rejected() {
+        assert_eq!(Literal::Decimal(2.5).as_int_lossless(), None);
+        assert_eq!(Literal::Decimal(1e300).as_int_lossless(), None);
+        assert_eq!(Literal::Decimal(2f64.powi(63)).as_int_lossless(), None);
+        assert_eq!(Literal::Decimal(f64::NAN).as_int_lossless(), None);
+        assert_eq!(Literal::Integer(u64::MAX).as_int_lossless(), None);
+        assert_eq!(Literal::Bool(true).as_int_lossless(), None);
+    }
+}
@@ -0,0 +1,159 @@
+use crate::lexer::TokenType;
+
+pub type Result<T> = core::result::Result<T, ParserError>;
+
+#[derive(Debug, PartialEq, Clone)]
+pub enum ParserError {
+    UnexpectedEof {
+        line: usize,
+        col: usize,
+        expected: String,
+    },
+    UnexpectedToken {
+        line: usize,
+        col: usize,
+        expected: String,
+        got: TokenType,
+    },
+    /// The left hand side of an assignment is not something that can be assigned to
+    InvalidAssignmentTarget { line: usize, col: usize },
+    /// A `match` without any arms, positioned at the `match` keyword
+    EmptyMatch { line: usize, col: usize },
+    /// A string literal directly following another, positioned at the second one. Only reported
+    /// with [AdjacentStrings::Error](super::options::AdjacentStrings::Error)
+    AdjacentStrings { line: usize, col: usize },
+    /// A keyword where a name is being bound, e.g. `var fn = 1;`, positioned at the keyword
+    ReservedWord {
+        line: usize,
+        col: usize,
+        word: String,
+    },
+}
+
+impl ParserError {
+    /// The line and column the error was reported at
+    pub fn position(&self) -> (usize, usize) {
+        match self {
+            Self::UnexpectedEof { line, col, .. }
+            | Self::UnexpectedToken { line, col, .. }
+            | Self::InvalidAssignmentTarget { line, col }
+            | Self::EmptyMatch { line, col }
+            | Self::AdjacentStrings { line, col }
+            | Self::ReservedWord { line, col, .. } => (*line, *col),
+        }
+    }
+}
+
+impl std::fmt::Display for ParserError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::UnexpectedEof {
+                line,
+                col,
+                expected,
+            } => write!(
+                f,
+                "[line {line}: {col}] Unexpected end of file, expected {expected}."
+            ),
+            Self::UnexpectedToken {
+                line,
+                col,
+                expected,
+                got,
+            } => write!(
+                f,
+                "[line {line}: {col}] Unexpected token '{got}', expected {expected}."
+            ),
+            Self::InvalidAssignmentTarget { line, col } => {
+                write!(f, "[line {line}: {col}] Invalid assignment target.")
+            }
+            Self::EmptyMatch { line, col } => {
+                write!(
+                    f,
+                    "[line {line}: {col}] A match must have at least one arm."
+                )
+            }
+            Self::AdjacentStrings { line, col } => {
+                write!(
+                    f,
+                    "[line {line}: {col}] Adjacent string literals, expected an operator between them."
+                )
+            }
+            Self::ReservedWord { line, col, word } => {
+                write!(
+                    f,
+                    "[line {line}: {col}] `{word}` is a reserved keyword and cannot be used as an identifier."
+                )
+            }
+        }
+    }
+}
+
+impl std::error::Error for ParserError {}
+
+#[cfg(test)]
+mod tests {
+    use crate::parser::{test_tokens, Parser};
+
+    fn statement_error(source: &str) -> String {
+        Parser::new(test_tokens(source))
+            .parse_statement()
+            .expect_err("statement should fail to parse")
+            .to_string()
+    }
+
+    fn program_error(source: &str) -> String {
+        Parser::new(test_tokens(source))
+            .parse_program()
+            .expect_err("program should fail to parse")
+            .to_string()
+    }
+
+    #[test]
+    fn unexpected_token_message() {
+        assert_eq!(
+            statement_error("var = 1;"),
+            "[line 1: 5] Unexpected token '=', expected a variable name."
+        );
+        assert_eq!(
+            statement_error("while x {}"),
+            "[line 1: 7] Unexpected token 'x', expected '(' before the while condition."
+        );
+    }
+
+    #[test]
+    fn reserved_word_message() {
+        assert_eq!(
+            statement_error("var fn = 1;"),
+            "[line 1: 6] `fn` is a reserved keyword and cannot be used as an identifier."
+        );
+        assert_eq!(
+            statement_error("for while in xs {}"),
+            "[line 1: 9] `while` is a reserved keyword and cannot be used as an identifier."
+        );
+    }
+
+    #[test]
+    fn reserved_word_as_item_name() {
+        assert_eq!(
+            program_error("fn while() {}"),
+            "[line 1: 8] `while` is a reserved keyword and cannot be used as an identifier."
+        );
+        assert_eq!(
+            program_error("struct if { x: int }"),
+            "[line 1: 9] `if` is a reserved keyword and cannot be used as an identifier."
+        );
+        assert_eq!(
+            program_error("enum match { A }"),
+            "[line 1: 10] `match` is a reserved keyword and cannot be used as an identifier."
+        );
+    }
+
+    #[test]
+    fn unexpected_eof_message() {
+        assert_eq!(
+            statement_error("return 1"),
+            "[line 1: 8] Unexpected end of file, expected ';'."
+        );
+    }
+}
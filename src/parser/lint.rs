@@ -0,0 +1,93 @@
+use crate::lexer::PrimitiveType;
+
+/// A likely mistake that still parses, reported through [Parser::warnings](super::Parser::warnings)
+#[derive(Debug, PartialEq, Clone)]
+pub enum ParserWarning {
+    /// A variable, parameter or loop variable named like a primitive type apart from case, e.g.
+    /// `var Int = 5;`
+    ShadowsPrimitive {
+        line: usize,
+        col: usize,
+        name: String,
+        primitive: PrimitiveType,
+    },
+}
+
+impl ParserWarning {
+    /// The line and column the warning was reported at
+    pub fn position(&self) -> (usize, usize) {
+        match self {
+            Self::ShadowsPrimitive { line, col, .. } => (*line, *col),
+        }
+    }
+}
+
+impl std::fmt::Display for ParserWarning {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::ShadowsPrimitive {
+                line,
+                col,
+                name,
+                primitive,
+            } => write!(
+                f,
+                "[line {line}: {col}] '{name}' is easily confused with the primitive type '{primitive}'."
+            ),
+        }
+    }
+}
+
+/// The primitive type `name` is spelled like when ignoring case, if any
+pub(super) fn shadowed_primitive(name: &str) -> Option<PrimitiveType> {
+    PrimitiveType::from_spelling(&name.to_lowercase())
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::parser::{test_tokens, Parser};
+
+    use super::*;
+
+    fn warnings(source: &str) -> Vec<ParserWarning> {
+        let mut parser = Parser::new(test_tokens(source));
+        parser.parse_program().expect("source should parse");
+
+        parser.warnings().to_vec()
+    }
+
+    #[test]
+    fn variable_named_like_a_primitive() {
+        let warnings = warnings("fn f() { var Int = 5; }");
+
+        assert_eq!(
+            warnings,
+            vec![ParserWarning::ShadowsPrimitive {
+                line: 1,
+                col: 16,
+                name: "Int".to_owned(),
+                primitive: PrimitiveType::Int,
+            }]
+        );
+        assert_eq!(
+            warnings[0].to_string(),
+            "[line 1: 16] 'Int' is easily confused with the primitive type 'int'."
+        );
+    }
+
+    #[test]
+    fn parameters_and_loop_variables_are_checked() {
+        let warnings = warnings("fn f(Bool: bool) { for CHAR in 0..1 {} }");
+
+        let names: Vec<_> = warnings
+            .iter()
+            .map(|ParserWarning::ShadowsPrimitive { name, .. }| name.as_str())
+            .collect();
+        assert_eq!(names, ["Bool", "CHAR"]);
+    }
+
+    #[test]
+    fn other_names_do_not_warn() {
+        assert!(warnings("fn Int() { var integer = 1; var p = Float { x: 1 }; }").is_empty());
+    }
+}
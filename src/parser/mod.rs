@@ -0,0 +1,2268 @@
+use crate::lexer::{Token, TokenCategory, TokenType};
+
+use self::{
+    ast::{
+        Assign, Attribute, Block, Else, EnumDecl, Expr, FnDecl, For, Ident, If, Item, ItemKind,
+        Literal, Loop, Pattern, Program, Stmt, StructDecl, Type, UnaryOp, VarDecl, Variant, While,
+    },
+    error::{ParserError, Result},
+    lint::{shadowed_primitive, ParserWarning},
+    options::{AdjacentStrings, ParserOptions},
+    precedence::{compound_assignment_operator, infix_operator, Associativity},
+    token_stream::{Mark, TokenStream},
+};
+
+pub mod ast;
+pub mod error;
+pub mod lint;
+pub mod options;
+pub mod precedence;
+pub mod printer;
+pub mod references;
+pub mod resolve;
+pub mod spans;
+pub mod symbols;
+pub mod token_stream;
+pub mod typeck;
+pub mod visit;
+
+/// A recursive descent parser that decides between alternatives by looking at the next token.
+/// Errors are reported at the furthest token it reached, even after backtracking.
+pub struct Parser {
+    tokens: TokenStream,
+    /// Errors that were recovered from, `None` unless parsing with
+    /// [Parser::parse_program_recovering]
+    recovered: Option<Vec<ParserError>>,
+    /// Whether a name followed by `{` starts a struct literal. This is turned off where a `{`
+    /// already starts a block, i.e. after a `for` iterable or a `match` scrutinee, so that
+    /// `for p in points { ... }` does not parse as `points { ... }`. Struct literals are allowed
+    /// again inside any delimiters, e.g. `for p in (Points { n: 3 }) { ... }`.
+    struct_literals: bool,
+    warnings: Vec<ParserWarning>,
+    options: ParserOptions,
+}
+
+impl Parser {
+    pub fn new(tokens: Vec<Token>) -> Self {
+        Self::with_options(tokens, ParserOptions::default())
+    }
+
+    pub fn with_options(tokens: Vec<Token>, options: ParserOptions) -> Self {
+        let tokens = tokens
+            .into_iter()
+            .filter(|token| token.ttype.category() != TokenCategory::Comment)
+            .collect();
+
+        Self {
+            tokens: TokenStream::new(tokens),
+            recovered: None,
+            struct_literals: true,
+            warnings: Vec::new(),
+            options,
+        }
+    }
+
+    /// Parses every item up to the end of the tokens
+    pub fn parse_program(&mut self) -> Result<Program> {
+        let mut items = Vec::new();
+        while self.peek().is_some() {
+            items.push(self.parse_item()?);
+        }
+
+        Ok(Program { items })
+    }
+
+    /// Parses every item like [Parser::parse_program], but carries on after an error instead of
+    /// stopping at the first one.
+    ///
+    /// A broken statement is skipped up to and including the next `;`, or up to the `}` closing
+    /// its block, and a broken item up to the start of the next item. Every error is returned
+    /// along with whatever could still be parsed.
+    pub fn parse_program_recovering(&mut self) -> (Program, Vec<ParserError>) {
+        self.recovered = Some(Vec::new());
+
+        let mut items = Vec::new();
+        while self.peek().is_some() {
+            let start = self.tokens.mark();
+            match self.parse_item() {
+                Ok(item) => items.push(item),
+                Err(error) => {
+                    self.recover(error);
+                    self.synchronize_item(start);
+                }
+            }
+        }
+
+        let errors = self.recovered.take().unwrap_or_default();
+        (Program { items }, errors)
+    }
+
+    /// Likely mistakes found in what has been parsed so far, which do not stop parsing
+    pub fn warnings(&self) -> &[ParserWarning] {
+        &self.warnings
+    }
+
+    /// Records an error when recovering from errors, otherwise hands it back
+    fn recover(&mut self, error: ParserError) -> Option<ParserError> {
+        match &mut self.recovered {
+            Some(errors) => {
+                errors.push(error);
+                None
+            }
+            None => Some(error),
+        }
+    }
+
+    /// Skips past the rest of a broken statement, which started at `start`
+    fn synchronize_statement(&mut self, start: Mark) {
+        while let Some(token) = self.peek() {
+            match token.ttype {
+                TokenType::Semicolon => {
+                    self.advance();
+                    break;
+                }
+                TokenType::RightBrace => break,
+                _ => {
+                    self.advance();
+                }
+            }
+        }
+
+        // Always make progress, so that the same token cannot fail forever
+        if self.tokens.mark() == start {
+            self.advance();
+        }
+    }
+
+    /// Skips to the start of the next item after a broken one, which started at `start`
+    fn synchronize_item(&mut self, start: Mark) {
+        if self.tokens.mark() == start {
+            self.advance();
+        }
+
+        while let Some(token) = self.peek() {
+            if matches!(
+                token.ttype,
+                TokenType::Fn
+                    | TokenType::Struct
+                    | TokenType::Enum
+                    | TokenType::Var
+                    | TokenType::Hash
+            ) {
+                break;
+            }
+            self.advance();
+        }
+    }
+
+    /// Parses a top level declaration along with any attributes preceding it
+    pub fn parse_item(&mut self) -> Result<Item> {
+        let mut attrs = Vec::new();
+        let mut cfg = Vec::new();
+
+        while self.check(&TokenType::Hash) {
+            let attr = self.parse_attribute()?;
+            if attr.name == "cfg" {
+                cfg.push(attr.args);
+            } else {
+                attrs.push(attr);
+            }
+        }
+
+        let expected = "an item";
+        let kind = match self.peek() {
+            Some(token) if token.ttype == TokenType::Fn => ItemKind::Fn(self.parse_fn()?),
+            Some(token) if token.ttype == TokenType::Struct => {
+                ItemKind::Struct(self.parse_struct()?)
+            }
+            Some(token) if token.ttype == TokenType::Enum => ItemKind::Enum(self.parse_enum()?),
+            Some(token) if token.ttype == TokenType::Var => {
+                self.advance();
+                ItemKind::Global(self.parse_var()?)
+            }
+            Some(token) => return Err(self.tokens.unexpected(token, expected)),
+            None => return Err(self.eof_error(expected)),
+        };
+
+        Ok(Item { attrs, cfg, kind })
+    }
+
+    /// Parses an attribute such as `#[inline]` or `#[cfg(test)]`
+    fn parse_attribute(&mut self) -> Result<Attribute> {
+        self.consume(&TokenType::Hash, "'#'")?;
+        self.consume(&TokenType::LeftBracket, "'['")?;
+        let name = self.consume_identifier("an attribute name")?;
+
+        let args = if self.matches(&TokenType::LeftParen) {
+            self.parse_delimited_tokens()?
+        } else {
+            TokenStream::default()
+        };
+        self.consume(&TokenType::RightBracket, "']'")?;
+
+        Ok(Attribute { name, args })
+    }
+
+    /// Collects the raw tokens up to the `)` closing an already consumed `(`, keeping any nested
+    /// parentheses balanced
+    fn parse_delimited_tokens(&mut self) -> Result<TokenStream> {
+        let mut tokens = Vec::new();
+        let mut depth = 0;
+
+        loop {
+            let Some(token) = self.advance().cloned() else {
+                return Err(self.eof_error("')'"));
+            };
+
+            match token.ttype {
+                TokenType::LeftParen => depth += 1,
+                TokenType::RightParen if depth == 0 => break,
+                TokenType::RightParen => depth -= 1,
+                _ => {}
+            }
+            tokens.push(token);
+        }
+
+        Ok(TokenStream::new(tokens))
+    }
+
+    /// Parses a function declaration such as `fn add(a: int, b: int) -> int { ... }`
+    pub fn parse_fn(&mut self) -> Result<FnDecl> {
+        self.consume(&TokenType::Fn, "'fn'")?;
+        let name = self.consume_ident("a function name")?;
+
+        self.consume(&TokenType::LeftParen, "'('")?;
+        let mut params = Vec::new();
+        while !self.check(&TokenType::RightParen) {
+            let param = self.consume_binding("a parameter name")?;
+            self.consume(&TokenType::Colon, "':'")?;
+            params.push((param, self.parse_type()?));
+
+            if !self.matches(&TokenType::Comma) {
+                break;
+            }
+        }
+        self.consume(&TokenType::RightParen, "')'")?;
+
+        let return_type = if self.matches(&TokenType::Arrow) {
+            Some(self.parse_type()?)
+        } else {
+            None
+        };
+
+        let body = self.parse_block()?;
+
+        Ok(FnDecl {
+            name,
+            params,
+            return_type,
+            body,
+        })
+    }
+
+    /// Parses a struct declaration such as `struct Point { x: int, y: int }`
+    pub fn parse_struct(&mut self) -> Result<StructDecl> {
+        self.consume(&TokenType::Struct, "'struct'")?;
+        let name = self.consume_ident("a struct name")?;
+
+        self.consume(&TokenType::LeftBrace, "'{'")?;
+        let mut fields = Vec::new();
+        while !self.check(&TokenType::RightBrace) {
+            let field = self.consume_identifier("a field name")?;
+            self.consume(&TokenType::Colon, "':' before the field type")?;
+            fields.push((field, self.parse_type()?));
+
+            if !self.matches(&TokenType::Comma) {
+                break;
+            }
+        }
+        self.consume(&TokenType::RightBrace, "'}'")?;
+
+        Ok(StructDecl { name, fields })
+    }
+
+    /// Parses an enum declaration such as `enum Option { Some(int), None }`
+    pub fn parse_enum(&mut self) -> Result<EnumDecl> {
+        self.consume(&TokenType::Enum, "'enum'")?;
+        let name = self.consume_ident("an enum name")?;
+
+        self.consume(&TokenType::LeftBrace, "'{'")?;
+        let mut variants = Vec::new();
+        while !self.check(&TokenType::RightBrace) {
+            let name = self.consume_identifier("a variant name")?;
+
+            let mut fields = Vec::new();
+            if self.matches(&TokenType::LeftParen) {
+                while !self.check(&TokenType::RightParen) {
+                    fields.push(self.parse_type()?);
+
+                    if !self.matches(&TokenType::Comma) {
+                        break;
+                    }
+                }
+                self.consume(&TokenType::RightParen, "')'")?;
+            }
+            variants.push(Variant { name, fields });
+
+            if !self.matches(&TokenType::Comma) {
+                break;
+            }
+        }
+        self.consume(&TokenType::RightBrace, "'}'")?;
+
+        Ok(EnumDecl { name, variants })
+    }
+
+    fn parse_type(&mut self) -> Result<Type> {
+        let expected = "a type";
+        let Some(token) = self.advance().cloned() else {
+            return Err(self.eof_error(expected));
+        };
+
+        let ty = match token.ttype {
+            TokenType::Primitive(primitive) => Type::Primitive(primitive),
+            TokenType::Identifier(name) if self.matches(&TokenType::Less) => Type::Generic {
+                name,
+                args: self.parse_type_args()?,
+            },
+            TokenType::Identifier(name) => Type::Named(name),
+            TokenType::LeftBracket => self.parse_array_type()?,
+            _ => return Err(self.tokens.unexpected(&token, expected)),
+        };
+
+        Ok(ty)
+    }
+
+    /// Parses the arguments of a generic type after the opening `<` has been consumed. There must
+    /// be at least one.
+    fn parse_type_args(&mut self) -> Result<Vec<Type>> {
+        let mut args = vec![self.parse_type()?];
+        while self.matches(&TokenType::Comma) {
+            args.push(self.parse_type()?);
+        }
+        self.consume(&TokenType::Greater, "',' or '>'")?;
+
+        Ok(args)
+    }
+
+    /// Parses an array type after the opening `[` has been consumed
+    fn parse_array_type(&mut self) -> Result<Type> {
+        let element = Box::new(self.parse_type()?);
+
+        let len = if self.matches(&TokenType::Semicolon) {
+            let expected = "an array length";
+            match self.advance().cloned() {
+                Some(Token {
+                    ttype: TokenType::Integer(len),
+                    ..
+                }) => Some(len),
+                Some(token) => return Err(self.tokens.unexpected(&token, expected)),
+                None => return Err(self.eof_error(expected)),
+            }
+        } else {
+            None
+        };
+        self.consume(&TokenType::RightBracket, "']'")?;
+
+        Ok(Type::Array { element, len })
+    }
+
+    /// Parses a brace delimited list of statements
+    pub fn parse_block(&mut self) -> Result<Block> {
+        self.consume(&TokenType::LeftBrace, "'{'")?;
+
+        let mut stmts = Vec::new();
+        while self.peek().is_some() && !self.check(&TokenType::RightBrace) {
+            let start = self.tokens.mark();
+            match self.parse_statement() {
+                Ok(stmt) => stmts.push(stmt),
+                Err(error) => {
+                    if let Some(error) = self.recover(error) {
+                        return Err(error);
+                    }
+                    self.synchronize_statement(start);
+                }
+            }
+        }
+        self.consume(&TokenType::RightBrace, "'}'")?;
+
+        Ok(Block { stmts })
+    }
+
+    pub fn parse_statement(&mut self) -> Result<Stmt> {
+        if self.matches(&TokenType::Var) {
+            return Ok(Stmt::Var(self.parse_var()?));
+        }
+
+        if self.matches(&TokenType::If) {
+            return Ok(Stmt::If(self.parse_if()?));
+        }
+
+        let label = self.consume_label();
+        if label.is_some() {
+            self.consume(&TokenType::Colon, "':' after the loop label")?;
+        }
+
+        if self.matches(&TokenType::While) {
+            self.consume(&TokenType::LeftParen, "'(' before the while condition")?;
+            let cond = self.parse_expression()?;
+            self.consume(&TokenType::RightParen, "')' after the while condition")?;
+            let body = self.parse_block()?;
+
+            return Ok(Stmt::While(While { label, cond, body }));
+        }
+
+        if self.matches(&TokenType::Loop) {
+            let body = self.parse_block()?;
+
+            return Ok(Stmt::Loop(Loop { label, body }));
+        }
+
+        if self.matches(&TokenType::For) {
+            return Ok(Stmt::For(self.parse_for(label)?));
+        }
+
+        if label.is_some() {
+            let expected = "a loop after its label";
+            return Err(match self.peek() {
+                Some(token) => self.tokens.unexpected(token, expected),
+                None => self.eof_error(expected),
+            });
+        }
+
+        if self.matches(&TokenType::Break) {
+            let label = self.consume_label();
+            self.consume(&TokenType::Semicolon, "';'")?;
+            return Ok(Stmt::Break(label));
+        }
+
+        if self.matches(&TokenType::Continue) {
+            let label = self.consume_label();
+            self.consume(&TokenType::Semicolon, "';'")?;
+            return Ok(Stmt::Continue(label));
+        }
+
+        if self.matches(&TokenType::Return) {
+            let value = if self.check(&TokenType::Semicolon) {
+                None
+            } else {
+                Some(self.parse_expression()?)
+            };
+            self.consume(&TokenType::Semicolon, "';'")?;
+
+            return Ok(Stmt::Return(value));
+        }
+
+        if self.check(&TokenType::LeftBrace) {
+            return Ok(Stmt::Block(self.parse_block()?));
+        }
+
+        let expr = self.parse_expression()?;
+
+        // Like a block, a match used as a statement needs no semicolon
+        if matches!(expr, Expr::Match { .. }) {
+            self.matches(&TokenType::Semicolon);
+        } else {
+            self.consume(&TokenType::Semicolon, "';'")?;
+        }
+
+        Ok(Stmt::Expr(expr))
+    }
+
+    /// Parses an `if` statement after the `if` keyword has been consumed. The condition must be
+    /// parenthesized and both branches must be blocks, except for an `else if`.
+    fn parse_if(&mut self) -> Result<If> {
+        self.consume(&TokenType::LeftParen, "'(' before the if condition")?;
+        let cond = self.parse_expression()?;
+        self.consume(&TokenType::RightParen, "')' after the if condition")?;
+
+        let then_block = self.parse_block()?;
+
+        let else_block = if self.matches(&TokenType::Else) {
+            if self.matches(&TokenType::If) {
+                Some(Else::If(Box::new(self.parse_if()?)))
+            } else {
+                Some(Else::Block(self.parse_block()?))
+            }
+        } else {
+            None
+        };
+
+        Ok(If {
+            cond,
+            then_block,
+            else_block,
+        })
+    }
+
+    /// Parses a variable declaration after the `var` keyword has been consumed
+    fn parse_var(&mut self) -> Result<VarDecl> {
+        let name = self.consume_binding("a variable name")?;
+
+        let ty = if self.matches(&TokenType::Colon) {
+            Some(self.parse_type()?)
+        } else {
+            None
+        };
+
+        let init = if self.matches(&TokenType::Equal) {
+            Some(self.parse_expression()?)
+        } else {
+            None
+        };
+        self.consume(&TokenType::Semicolon, "';'")?;
+
+        Ok(VarDecl { name, ty, init })
+    }
+
+    /// Parses a `for` loop after the `for` keyword has been consumed. Unlike `while`, the
+    /// iterable is not parenthesized.
+    fn parse_for(&mut self, label: Option<Ident>) -> Result<For> {
+        let binding = self.consume_binding("a loop variable")?;
+        self.consume(&TokenType::In, "'in' after the loop variable")?;
+        let iterable = self.with_struct_literals(false, Self::parse_expression)?;
+        let body = self.parse_block()?;
+
+        Ok(For {
+            label,
+            binding,
+            iterable,
+            body,
+        })
+    }
+
+    /// Parses an expression. Assignment binds the loosest and groups to the right, and its target
+    /// must be a variable, an index or a field.
+    pub fn parse_expression(&mut self) -> Result<Expr> {
+        let start = self.peek().cloned();
+        let target = self.parse_range()?;
+
+        // `Some(None)` for a plain `=` and `Some(Some(op))` for a compound assignment
+        let Some(op) = self.peek().and_then(|token| match &token.ttype {
+            TokenType::Equal => Some(None),
+            ttype => compound_assignment_operator(ttype).map(Some),
+        }) else {
+            return Ok(target);
+        };
+        self.advance();
+
+        if !matches!(
+            target,
+            Expr::Identifier(_) | Expr::Index { .. } | Expr::Field { .. }
+        ) {
+            let start = start.expect("an expression was parsed so it has a first token");
+            return Err(ParserError::InvalidAssignmentTarget {
+                line: start.line,
+                col: start.col,
+            });
+        }
+        let value = self.parse_expression()?;
+
+        Ok(Expr::Assign(Box::new(Assign { target, op, value })))
+    }
+
+    /// Parses either a binary expression or a range between two of them. Ranges bind looser than
+    /// every binary operator and do not chain.
+    fn parse_range(&mut self) -> Result<Expr> {
+        let start = self.parse_binary(0)?;
+
+        let inclusive = if self.matches(&TokenType::DotDot) {
+            false
+        } else if self.matches(&TokenType::DotDotEqual) {
+            true
+        } else {
+            return Ok(start);
+        };
+        let end = self.parse_binary(0)?;
+
+        Ok(Expr::Range {
+            start: Box::new(start),
+            end: Box::new(end),
+            inclusive,
+        })
+    }
+
+    /// Parses a chain of binary operators that bind tighter than `min_precedence`, using the
+    /// [precedence table](precedence::infix_operator) to decide how operands group
+    fn parse_binary(&mut self, min_precedence: u8) -> Result<Expr> {
+        let mut left = self.parse_unary()?;
+
+        while let Some(operator) = self
+            .peek()
+            .and_then(|token| infix_operator(&token.ttype))
+            .filter(|operator| operator.precedence > min_precedence)
+        {
+            self.advance();
+
+            let next_min_precedence = match operator.associativity {
+                Associativity::Left => operator.precedence,
+                Associativity::Right => operator.precedence - 1,
+            };
+            let right = self.parse_binary(next_min_precedence)?;
+
+            left = Expr::Binary {
+                left: Box::new(left),
+                op: operator.op,
+                right: Box::new(right),
+            };
+        }
+
+        Ok(left)
+    }
+
+    fn parse_unary(&mut self) -> Result<Expr> {
+        let op = match self.peek().map(|token| &token.ttype) {
+            // Unary plus is a no-op, so `+5` parses exactly like `5`
+            Some(TokenType::Add) => {
+                self.advance();
+                return self.parse_unary();
+            }
+            Some(TokenType::Minus) => UnaryOp::Negate,
+            Some(TokenType::Bang) => UnaryOp::Not,
+            _ => return self.parse_postfix(),
+        };
+        self.advance();
+
+        let operand = self.parse_unary()?;
+
+        Ok(Expr::Unary {
+            op,
+            operand: Box::new(operand),
+        })
+    }
+
+    fn parse_postfix(&mut self) -> Result<Expr> {
+        let mut expr = self.parse_primary()?;
+
+        loop {
+            if self.matches(&TokenType::LeftBracket) {
+                let index = self.with_struct_literals(true, Self::parse_expression)?;
+                self.consume(&TokenType::RightBracket, "']'")?;
+
+                expr = Expr::Index {
+                    target: Box::new(expr),
+                    index: Box::new(index),
+                };
+                continue;
+            }
+
+            if self.matches(&TokenType::Dot) {
+                let name = self.consume_identifier("a field name after '.'")?;
+
+                expr = Expr::Field {
+                    base: Box::new(expr),
+                    name,
+                };
+                continue;
+            }
+
+            if !self.matches(&TokenType::LeftParen) {
+                break;
+            }
+
+            let mut args = Vec::new();
+            while !self.check(&TokenType::RightParen) {
+                args.push(self.with_struct_literals(true, Self::parse_expression)?);
+
+                if !self.matches(&TokenType::Comma) {
+                    break;
+                }
+            }
+            self.consume(&TokenType::RightParen, "')'")?;
+
+            expr = Expr::Call {
+                callee: Box::new(expr),
+                args,
+            };
+        }
+
+        Ok(expr)
+    }
+
+    fn parse_primary(&mut self) -> Result<Expr> {
+        let Some(token) = self.advance().cloned() else {
+            return Err(self.eof_error("an expression"));
+        };
+
+        let expr = match token.ttype {
+            // The AST does not keep type suffixes, nothing checks them against types yet
+            TokenType::Integer(n) | TokenType::SuffixedInteger { value: n, .. } => {
+                Expr::Literal(Literal::Integer(n))
+            }
+            // The AST has no negative integer literals, so a folded one is a negation as usual
+            TokenType::NegativeInteger(n) => Expr::Unary {
+                op: UnaryOp::Negate,
+                operand: Box::new(Expr::Literal(Literal::Integer(n))),
+            },
+            TokenType::Decimal(n) | TokenType::SuffixedDecimal { value: n, .. } => {
+                Expr::Literal(Literal::Decimal(n))
+            }
+            TokenType::String(s) => self.parse_adjacent_strings(s)?,
+            TokenType::Character(c) => Expr::Literal(Literal::Character(c)),
+            TokenType::Bool(b) => Expr::Literal(Literal::Bool(b)),
+            TokenType::Null => Expr::Literal(Literal::Null),
+            TokenType::Identifier(name)
+                if self.struct_literals && self.matches(&TokenType::LeftBrace) =>
+            {
+                self.parse_struct_literal(name)?
+            }
+            TokenType::Identifier(name) => Expr::Identifier(Ident {
+                name,
+                span: token.span,
+            }),
+            TokenType::LeftParen => {
+                let expr = self.with_struct_literals(true, Self::parse_expression)?;
+                self.consume(&TokenType::RightParen, "')'")?;
+                expr
+            }
+            TokenType::LeftBracket => self.parse_array()?,
+            TokenType::Match => self.parse_match(&token)?,
+            ttype => {
+                let token = Token { ttype, ..token };
+                return Err(self.tokens.unexpected(&token, "an expression"));
+            }
+        };
+
+        Ok(expr)
+    }
+
+    /// Handles any string literals directly following `first` according to
+    /// [ParserOptions::adjacent_strings]
+    fn parse_adjacent_strings(&mut self, first: String) -> Result<Expr> {
+        let mut value = first;
+
+        while let Some(token) = self.peek() {
+            let TokenType::String(next) = &token.ttype else {
+                break;
+            };
+
+            match self.options.adjacent_strings {
+                AdjacentStrings::Concatenate => value.push_str(next),
+                AdjacentStrings::Error => {
+                    return Err(ParserError::AdjacentStrings {
+                        line: token.line,
+                        col: token.col,
+                    })
+                }
+            }
+            self.advance();
+        }
+
+        Ok(Expr::Literal(Literal::String(value)))
+    }
+
+    /// Parses the fields of a struct literal after the opening `{` has been consumed
+    fn parse_struct_literal(&mut self, name: String) -> Result<Expr> {
+        let mut fields = Vec::new();
+
+        while !self.check(&TokenType::RightBrace) {
+            let field = self.consume_identifier("a field name")?;
+            self.consume(&TokenType::Colon, "':' after the field name")?;
+            fields.push((
+                field,
+                self.with_struct_literals(true, Self::parse_expression)?,
+            ));
+
+            if !self.matches(&TokenType::Comma) {
+                break;
+            }
+        }
+        self.consume(&TokenType::RightBrace, "'}'")?;
+
+        Ok(Expr::StructLit { name, fields })
+    }
+
+    /// Runs `parse` with struct literals allowed or not, see [Parser::struct_literals]
+    fn with_struct_literals<T>(
+        &mut self,
+        allowed: bool,
+        parse: impl FnOnce(&mut Self) -> Result<T>,
+    ) -> Result<T> {
+        let outer = std::mem::replace(&mut self.struct_literals, allowed);
+        let result = parse(self);
+        self.struct_literals = outer;
+
+        result
+    }
+
+    /// Parses the elements of an array literal, after the opening `[` has been consumed
+    fn parse_array(&mut self) -> Result<Expr> {
+        let mut elements = Vec::new();
+
+        while !self.check(&TokenType::RightBracket) {
+            elements.push(self.with_struct_literals(true, Self::parse_expression)?);
+
+            if !self.matches(&TokenType::Comma) {
+                break;
+            }
+        }
+        self.consume(&TokenType::RightBracket, "']'")?;
+
+        Ok(Expr::Array(elements))
+    }
+
+    /// Parses the rest of a match expression after its `match` keyword, which is used to
+    /// position the error for a match without arms
+    fn parse_match(&mut self, keyword: &Token) -> Result<Expr> {
+        let scrutinee = self.with_struct_literals(false, Self::parse_expression)?;
+
+        self.consume(&TokenType::LeftBrace, "'{' after the match scrutinee")?;
+        self.with_struct_literals(true, |parser| parser.parse_match_arms(keyword, scrutinee))
+    }
+
+    /// Parses the arms of a match after its opening `{` has been consumed
+    fn parse_match_arms(&mut self, keyword: &Token, scrutinee: Expr) -> Result<Expr> {
+        let mut arms = Vec::new();
+        while !self.check(&TokenType::RightBrace) {
+            let pattern = self.parse_pattern()?;
+            self.consume(&TokenType::FatArrow, "'=>' after the match pattern")?;
+
+            // The closing brace of a block arm already ends the arm, so its comma is optional
+            if self.check(&TokenType::LeftBrace) {
+                arms.push((pattern, Expr::Block(self.parse_block()?)));
+                self.matches(&TokenType::Comma);
+                continue;
+            }
+            arms.push((pattern, self.parse_expression()?));
+
+            if !self.matches(&TokenType::Comma) {
+                break;
+            }
+        }
+        self.consume(&TokenType::RightBrace, "'}'")?;
+
+        if arms.is_empty() {
+            return Err(ParserError::EmptyMatch {
+                line: keyword.line,
+                col: keyword.col,
+            });
+        }
+
+        Ok(Expr::Match {
+            scrutinee: Box::new(scrutinee),
+            arms,
+        })
+    }
+
+    fn parse_pattern(&mut self) -> Result<Pattern> {
+        let expected = "a pattern";
+        let Some(token) = self.peek() else {
+            return Err(self.eof_error(expected));
+        };
+
+        let pattern = match &token.ttype {
+            TokenType::Underscore => Pattern::Wildcard,
+            TokenType::Integer(n) | TokenType::SuffixedInteger { value: n, .. } => {
+                Pattern::Literal(Literal::Integer(*n))
+            }
+            TokenType::Decimal(n) | TokenType::SuffixedDecimal { value: n, .. } => {
+                Pattern::Literal(Literal::Decimal(*n))
+            }
+            TokenType::String(s) => Pattern::Literal(Literal::String(s.clone())),
+            TokenType::Character(c) => Pattern::Literal(Literal::Character(*c)),
+            TokenType::Bool(b) => Pattern::Literal(Literal::Bool(*b)),
+            TokenType::Null => Pattern::Literal(Literal::Null),
+            _ => return Err(self.tokens.unexpected(token, expected)),
+        };
+        self.advance();
+
+        Ok(pattern)
+    }
+
+    fn consume_identifier(&mut self, expected: &str) -> Result<String> {
+        match self.peek() {
+            Some(Token {
+                ttype: TokenType::Identifier(name),
+                ..
+            }) => {
+                let name = name.clone();
+                self.advance();
+                Ok(name)
+            }
+            Some(token) => Err(self.tokens.unexpected(token, expected)),
+            None => Err(self.eof_error(expected)),
+        }
+    }
+
+    /// Consumes an identifier along with its span, for a name that is being declared. A keyword in
+    /// its place is reported as a [ParserError::ReservedWord] rather than a missing name.
+    fn consume_ident(&mut self, expected: &str) -> Result<Ident> {
+        if let Some(token) = self.peek() {
+            let word = token.ttype.to_string();
+            if TokenType::is_reserved_word(&word) {
+                return Err(ParserError::ReservedWord {
+                    line: token.line,
+                    col: token.col,
+                    word,
+                });
+            }
+        }
+
+        let span = self.peek().map(|token| token.span).unwrap_or_default();
+        let name = self.consume_identifier(expected)?;
+
+        Ok(Ident { name, span })
+    }
+
+    /// Consumes a loop label such as `'outer` if one is next
+    fn consume_label(&mut self) -> Option<Ident> {
+        let Some(Token {
+            ttype: TokenType::Lifetime(name),
+            span,
+            ..
+        }) = self.peek()
+        else {
+            return None;
+        };
+        let label = Ident {
+            name: name.clone(),
+            span: *span,
+        };
+        self.advance();
+
+        Some(label)
+    }
+
+    /// Consumes the name a value is bound to, warning if it could be mistaken for a primitive type.
+    /// Like any declared name it can not be a keyword, see [Parser::consume_ident].
+    fn consume_binding(&mut self, expected: &str) -> Result<Ident> {
+        let position = self.peek().map(|token| (token.line, token.col));
+        let ident = self.consume_ident(expected)?;
+
+        if let (Some(primitive), Some((line, col))) = (shadowed_primitive(&ident.name), position) {
+            self.warnings.push(ParserWarning::ShadowsPrimitive {
+                line,
+                col,
+                name: ident.name.clone(),
+                primitive,
+            });
+        }
+
+        Ok(ident)
+    }
+
+    fn consume(&mut self, ttype: &TokenType, expected: &str) -> Result<&Token> {
+        self.tokens.consume(ttype, expected)
+    }
+
+    /// Advances past the next token if it is of the given type
+    fn matches(&mut self, ttype: &TokenType) -> bool {
+        let matched = self.check(ttype);
+        if matched {
+            self.advance();
+        }
+
+        matched
+    }
+
+    fn check(&self, ttype: &TokenType) -> bool {
+        self.tokens.check(ttype)
+    }
+
+    fn peek(&self) -> Option<&Token> {
+        self.tokens.peek()
+    }
+
+    fn advance(&mut self) -> Option<&Token> {
+        self.tokens.advance()
+    }
+
+    fn eof_error(&self, expected: &str) -> ParserError {
+        self.tokens.eof_error(expected)
+    }
+}
+
+/// Parses a whole program up to the end of `tokens`, reporting every error that was found instead
+/// of only the first
+pub fn parse_program(tokens: Vec<Token>) -> std::result::Result<Program, Vec<ParserError>> {
+    let (program, errors) = Parser::new(tokens).parse_program_recovering();
+    if errors.is_empty() {
+        Ok(program)
+    } else {
+        Err(errors)
+    }
+}
+
+fn unexpected_token(token: &Token, expected: &str) -> ParserError {
+    ParserError::UnexpectedToken {
+        line: token.line,
+        col: token.col,
+        expected: expected.to_owned(),
+        got: token.ttype.clone(),
+    }
+}
+
+/// Lexes `source` for a test, panicking if it does not lex
+#[cfg(test)]
+pub(crate) fn test_tokens(source: &str) -> Vec<Token> {
+    crate::lexer::Lexer::new(source)
+        .tokenize()
+        .into_iter()
+        .collect::<std::result::Result<_, _>>()
+        .expect("source should lex")
+}
+
+/// Lexes and parses `source` for a test, panicking if either fails
+#[cfg(test)]
+pub(crate) fn test_program(source: &str) -> Program {
+    Parser::new(test_tokens(source))
+        .parse_program()
+        .expect("source should parse")
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{
+        lexer::{options::LexerOptions, Lexer, PrimitiveType},
+        span::Span,
+    };
+
+    use super::{ast::BinaryOp, precedence::binary_operator, *};
+
+    fn parser(source: &str) -> Parser {
+        parser_with(source, ParserOptions::default())
+    }
+
+    fn parser_with(source: &str, options: ParserOptions) -> Parser {
+        Parser::with_options(test_tokens(source), options)
+    }
+
+    fn integer(n: u64) -> Expr {
+        Expr::Literal(Literal::Integer(n))
+    }
+
+    /// A name starting at byte offset `start`
+    fn ident(name: &str, start: usize) -> Ident {
+        Ident {
+            name: name.to_owned(),
+            span: Span::new(start, start + name.len()),
+        }
+    }
+
+    /// An identifier starting at byte offset `start`
+    fn identifier(name: &str, start: usize) -> Expr {
+        Expr::Identifier(ident(name, start))
+    }
+
+    /// Renders an expression as an s-expression so tests can assert on its shape
+    fn sexpr(expr: &Expr) -> String {
+        match expr {
+            Expr::Literal(Literal::Integer(n)) => n.to_string(),
+            Expr::Literal(literal) => format!("{literal:?}"),
+            Expr::Identifier(ident) => ident.name.clone(),
+            Expr::Array(elements) => {
+                let elements: Vec<_> = elements.iter().map(sexpr).collect();
+                format!("[{}]", elements.join(" "))
+            }
+            Expr::Unary { op, operand } => format!("({op:?} {})", sexpr(operand)),
+            Expr::Binary { left, op, right } => {
+                format!("({op:?} {} {})", sexpr(left), sexpr(right))
+            }
+            Expr::Call { callee, args } => {
+                let args: Vec<_> = args.iter().map(sexpr).collect();
+                format!("(call {} {})", sexpr(callee), args.join(" "))
+            }
+            Expr::Index { target, index } => format!("(index {} {})", sexpr(target), sexpr(index)),
+            Expr::Field { base, name } => format!("(. {} {name})", sexpr(base)),
+            Expr::StructLit { name, fields } => {
+                let fields: Vec<_> = fields
+                    .iter()
+                    .map(|(field, value)| format!("({field} {})", sexpr(value)))
+                    .collect();
+                format!("({name} {})", fields.join(" "))
+            }
+            Expr::Block(block) => format!("(block {})", block.stmts.len()),
+            Expr::Match { scrutinee, arms } => {
+                let arms: Vec<_> = arms
+                    .iter()
+                    .map(|(pattern, expr)| format!("({pattern:?} {})", sexpr(expr)))
+                    .collect();
+                format!("(match {} {})", sexpr(scrutinee), arms.join(" "))
+            }
+            Expr::Range {
+                start,
+                end,
+                inclusive,
+            } => {
+                let op = if *inclusive { "..=" } else { ".." };
+                format!("({op} {} {})", sexpr(start), sexpr(end))
+            }
+            Expr::Assign(assign) => {
+                let op = assign
+                    .op
+                    .map(|op| binary_operator(op).0.to_string())
+                    .unwrap_or_default();
+                format!("({op}= {} {})", sexpr(&assign.target), sexpr(&assign.value))
+            }
+        }
+    }
+
+    fn parse_sexpr(source: &str) -> String {
+        let expr = parser(source)
+            .parse_expression()
+            .expect("expression should parse");
+
+        sexpr(&expr)
+    }
+
+    #[test]
+    fn unary_plus_is_a_no_op() {
+        assert_eq!(parser("+5").parse_expression(), Ok(integer(5)));
+    }
+
+    #[test]
+    fn folded_negative_integer_is_a_negation() {
+        let options = LexerOptions {
+            fold_negative_literals: true,
+            ..LexerOptions::default()
+        };
+        let tokens = Lexer::with_options("a * -5", options)
+            .tokenize()
+            .into_iter()
+            .collect::<core::result::Result<Vec<_>, _>>()
+            .expect("source should lex");
+
+        let expr = Parser::new(tokens).parse_expression();
+        assert_eq!(
+            expr.map(|expr| sexpr(&expr)),
+            Ok("(Multiply a (Negate 5))".to_owned())
+        );
+    }
+
+    #[test]
+    fn unary_plus_in_var_initializer() {
+        let stmt = parser("var x = +5;").parse_statement();
+
+        assert_eq!(
+            stmt,
+            Ok(Stmt::Var(VarDecl {
+                name: ident("x", 4),
+                ty: None,
+                init: Some(integer(5)),
+            }))
+        );
+    }
+
+    #[test]
+    fn unary_plus_and_minus_in_array() {
+        let expr = parser("[+1, -2]").parse_expression();
+
+        assert_eq!(
+            expr,
+            Ok(Expr::Array(vec![
+                integer(1),
+                Expr::Unary {
+                    op: UnaryOp::Negate,
+                    operand: Box::new(integer(2)),
+                },
+            ]))
+        );
+    }
+
+    #[test]
+    fn missing_operand_after_unary_plus() {
+        let result = parser("+").parse_expression();
+
+        assert!(matches!(result, Err(ParserError::UnexpectedEof { .. })));
+    }
+
+    #[test]
+    fn multiplication_binds_tighter_than_addition() {
+        assert_eq!(parse_sexpr("1 + 2 * 3"), "(Add 1 (Multiply 2 3))");
+        assert_eq!(parse_sexpr("1 * 2 + 3"), "(Add (Multiply 1 2) 3)");
+    }
+
+    #[test]
+    fn logical_and_binds_tighter_than_logical_or() {
+        assert_eq!(parse_sexpr("a || b && c"), "(Or a (And b c))");
+        assert_eq!(parse_sexpr("a && b || c"), "(Or (And a b) c)");
+    }
+
+    #[test]
+    fn comparison_binds_looser_than_arithmetic() {
+        assert_eq!(
+            parse_sexpr("a + 1 > b * 2"),
+            "(Greater (Add a 1) (Multiply b 2))"
+        );
+        assert_eq!(
+            parse_sexpr("a & 1 > 0 && a != 2"),
+            "(And (Greater (BitAnd a 1) 0) (NotEqual a 2))"
+        );
+    }
+
+    #[test]
+    fn binary_operators_are_left_associative() {
+        assert_eq!(parse_sexpr("1 - 2 - 3"), "(Subtract (Subtract 1 2) 3)");
+    }
+
+    #[test]
+    fn power_is_right_associative_and_binds_tightest() {
+        assert_eq!(parse_sexpr("2 ** 3 ** 2"), "(Power 2 (Power 3 2))");
+        assert_eq!(parse_sexpr("2 * 3 ** 2"), "(Multiply 2 (Power 3 2))");
+    }
+
+    #[test]
+    fn parentheses_override_precedence() {
+        assert_eq!(parse_sexpr("(1 + 2) * 3"), "(Multiply (Add 1 2) 3)");
+    }
+
+    #[test]
+    fn fn_decl_sample() {
+        let source = "fn math() -> int { var a = 1.5; print(a <= 1); return a; }";
+        let decl = parser(source).parse_fn().expect("function should parse");
+
+        assert_eq!(decl.name.name, "math");
+        assert!(decl.params.is_empty());
+        assert_eq!(decl.return_type, Some(Type::Primitive(PrimitiveType::Int)));
+        assert_eq!(
+            decl.body.stmts,
+            vec![
+                Stmt::Var(VarDecl {
+                    name: ident("a", 23),
+                    ty: None,
+                    init: Some(Expr::Literal(Literal::Decimal(1.5))),
+                }),
+                Stmt::Expr(Expr::Call {
+                    callee: Box::new(identifier("print", 32)),
+                    args: vec![Expr::Binary {
+                        left: Box::new(identifier("a", 38)),
+                        op: BinaryOp::LessEqual,
+                        right: Box::new(integer(1)),
+                    }],
+                }),
+                Stmt::Return(Some(identifier("a", 54))),
+            ]
+        );
+    }
+
+    #[test]
+    fn fn_decl_with_params() {
+        let source = "fn add(a: int, b: int) -> int { return a + b; }";
+        let decl = parser(source).parse_fn().expect("function should parse");
+
+        assert_eq!(decl.name.name, "add");
+        assert_eq!(
+            decl.params,
+            vec![
+                (ident("a", 7), Type::Primitive(PrimitiveType::Int)),
+                (ident("b", 15), Type::Primitive(PrimitiveType::Int)),
+            ]
+        );
+        assert_eq!(decl.return_type, Some(Type::Primitive(PrimitiveType::Int)));
+        assert_eq!(decl.body.stmts.len(), 1);
+    }
+
+    #[test]
+    fn fn_decl_without_return_type() {
+        let decl = parser("fn noop(p: Point) {}")
+            .parse_fn()
+            .expect("function should parse");
+
+        assert_eq!(
+            decl.params,
+            vec![(ident("p", 8), Type::Named("Point".to_owned()))]
+        );
+        assert_eq!(decl.return_type, None);
+        assert!(decl.body.stmts.is_empty());
+    }
+
+    #[test]
+    fn fn_decl_with_void_return_type() {
+        let decl = parser("fn f() -> void {}")
+            .parse_fn()
+            .expect("function should parse");
+
+        assert_eq!(decl.return_type, Some(Type::Primitive(PrimitiveType::Void)));
+    }
+
+    #[test]
+    fn fn_decl_missing_name() {
+        let result = parser("fn () {}").parse_fn();
+
+        assert!(matches!(
+            result,
+            Err(ParserError::UnexpectedToken {
+                got: TokenType::LeftParen,
+                ..
+            })
+        ));
+    }
+
+    #[test]
+    fn fn_decl_unbalanced_braces() {
+        let result = parser("fn f() { { return 1; }").parse_fn();
+
+        assert!(matches!(result, Err(ParserError::UnexpectedEof { .. })));
+    }
+
+    #[test]
+    fn var_decl_annotated() {
+        let stmt = parser("var a: int = 3;").parse_statement();
+
+        assert_eq!(
+            stmt,
+            Ok(Stmt::Var(VarDecl {
+                name: ident("a", 4),
+                ty: Some(Type::Primitive(PrimitiveType::Int)),
+                init: Some(integer(3)),
+            }))
+        );
+    }
+
+    #[test]
+    fn var_decl_unannotated() {
+        let stmt = parser("var a = 3;").parse_statement();
+
+        assert_eq!(
+            stmt,
+            Ok(Stmt::Var(VarDecl {
+                name: ident("a", 4),
+                ty: None,
+                init: Some(integer(3)),
+            }))
+        );
+    }
+
+    #[test]
+    fn var_decl_null() {
+        let stmt = parser("var x = null;").parse_statement();
+
+        assert_eq!(
+            stmt,
+            Ok(Stmt::Var(VarDecl {
+                name: ident("x", 4),
+                ty: None,
+                init: Some(Expr::Literal(Literal::Null)),
+            }))
+        );
+    }
+
+    #[test]
+    fn var_decl_uninitialized() {
+        let stmt = parser("var a: int;").parse_statement();
+
+        assert_eq!(
+            stmt,
+            Ok(Stmt::Var(VarDecl {
+                name: ident("a", 4),
+                ty: Some(Type::Primitive(PrimitiveType::Int)),
+                init: None,
+            }))
+        );
+    }
+
+    #[test]
+    fn var_decl_missing_semicolon_points_at_next_token() {
+        let result = parser("var a = 3\nvar b = 4;").parse_statement();
+
+        assert_eq!(
+            result,
+            Err(ParserError::UnexpectedToken {
+                line: 2,
+                col: 3,
+                expected: "';'".to_owned(),
+                got: TokenType::Var,
+            })
+        );
+    }
+
+    fn ttypes(stream: &TokenStream) -> Vec<TokenType> {
+        stream
+            .tokens()
+            .iter()
+            .map(|token| token.ttype.clone())
+            .collect()
+    }
+
+    #[test]
+    fn item_records_cfg_attribute() {
+        let item = parser("#[cfg(test)] fn t() {}")
+            .parse_item()
+            .expect("item should parse");
+
+        assert_eq!(item.cfg.len(), 1);
+        assert_eq!(
+            ttypes(&item.cfg[0]),
+            vec![TokenType::Identifier("test".to_owned())]
+        );
+        assert!(item.attrs.is_empty());
+        assert!(matches!(item.kind, ItemKind::Fn(FnDecl { ref name, .. }) if name.name == "t"));
+    }
+
+    #[test]
+    fn item_keeps_nested_cfg_arguments_and_other_attributes() {
+        let item = parser("#[inline] #[cfg(all(test, unix))] fn t() {}")
+            .parse_item()
+            .expect("item should parse");
+
+        assert_eq!(
+            ttypes(&item.cfg[0]),
+            vec![
+                TokenType::Identifier("all".to_owned()),
+                TokenType::LeftParen,
+                TokenType::Identifier("test".to_owned()),
+                TokenType::Comma,
+                TokenType::Identifier("unix".to_owned()),
+                TokenType::RightParen,
+            ]
+        );
+        assert_eq!(item.attrs.len(), 1);
+        assert_eq!(item.attrs[0].name, "inline");
+        assert!(item.attrs[0].args.is_empty());
+    }
+
+    #[test]
+    fn item_without_attributes() {
+        let item = parser("fn t() {}").parse_item().expect("item should parse");
+
+        assert!(item.cfg.is_empty());
+        assert!(item.attrs.is_empty());
+    }
+
+    #[test]
+    fn item_with_unterminated_attribute() {
+        let result = parser("#[cfg(test fn t() {}").parse_item();
+
+        assert!(matches!(result, Err(ParserError::UnexpectedEof { .. })));
+    }
+
+    fn parse_if(source: &str) -> If {
+        match parser(source).parse_statement() {
+            Ok(Stmt::If(stmt)) => stmt,
+            other => panic!("expected an if statement, got {other:?}"),
+        }
+    }
+
+    #[test]
+    fn if_without_else() {
+        let stmt = parse_if("if (a > 1) { return a; }");
+
+        assert_eq!(sexpr(&stmt.cond), "(Greater a 1)");
+        assert_eq!(stmt.then_block.stmts.len(), 1);
+        assert_eq!(stmt.else_block, None);
+    }
+
+    #[test]
+    fn if_else() {
+        let stmt = parse_if("if (a) { return 1; } else { return 2; }");
+
+        assert_eq!(
+            stmt.else_block,
+            Some(Else::Block(Block {
+                stmts: vec![Stmt::Return(Some(integer(2)))],
+            }))
+        );
+    }
+
+    #[test]
+    fn if_else_if_else_chain() {
+        let stmt = parse_if("if (a) { 1; } else if (b) { 2; } else if (c) { 3; } else { 4; }");
+
+        let Some(Else::If(second)) = stmt.else_block else {
+            panic!("expected an else if");
+        };
+        assert_eq!(sexpr(&second.cond), "b");
+
+        let Some(Else::If(third)) = second.else_block else {
+            panic!("expected an else if");
+        };
+        assert_eq!(sexpr(&third.cond), "c");
+        assert_eq!(
+            third.else_block,
+            Some(Else::Block(Block {
+                stmts: vec![Stmt::Expr(integer(4))],
+            }))
+        );
+    }
+
+    #[test]
+    fn if_condition_must_be_parenthesized() {
+        let result = parser("if a { return a; }").parse_statement();
+
+        assert_eq!(
+            result,
+            Err(ParserError::UnexpectedToken {
+                line: 1,
+                col: 4,
+                expected: "'(' before the if condition".to_owned(),
+                got: TokenType::Identifier("a".to_owned()),
+            })
+        );
+    }
+
+    #[test]
+    fn if_missing_block() {
+        let result = parser("if (a) return a;").parse_statement();
+
+        assert!(matches!(
+            result,
+            Err(ParserError::UnexpectedToken {
+                got: TokenType::Return,
+                ..
+            })
+        ));
+    }
+
+    #[test]
+    fn fn_decl_main_sample() {
+        let source = "fn math() -> int { var a: int = 3.14159; print(a <= 1); if (a & 1 > 0 && a != 2 || false) { return a; } else { return 1117; } }";
+        let decl = parser(source).parse_fn().expect("sample should parse");
+
+        assert_eq!(decl.name.name, "math");
+        assert_eq!(decl.body.stmts.len(), 3);
+
+        let Stmt::If(ref stmt) = decl.body.stmts[2] else {
+            panic!("expected an if statement");
+        };
+        assert_eq!(
+            sexpr(&stmt.cond),
+            "(Or (And (Greater (BitAnd a 1) 0) (NotEqual a 2)) Bool(false))"
+        );
+        assert_eq!(
+            stmt.else_block,
+            Some(Else::Block(Block {
+                stmts: vec![Stmt::Return(Some(integer(1117)))],
+            }))
+        );
+    }
+
+    #[test]
+    fn counting_while_loop() {
+        let stmt = parser("while (i < 10) { print(i); continue; }").parse_statement();
+
+        let Ok(Stmt::While(stmt)) = stmt else {
+            panic!("expected a while loop, got {stmt:?}");
+        };
+        assert_eq!(sexpr(&stmt.cond), "(Less i 10)");
+        assert_eq!(stmt.body.stmts.len(), 2);
+        assert_eq!(stmt.body.stmts[1], Stmt::Continue(None));
+    }
+
+    #[test]
+    fn bare_loop_with_break() {
+        let stmt = parser("loop { break; }").parse_statement();
+
+        assert_eq!(
+            stmt,
+            Ok(Stmt::Loop(Loop {
+                label: None,
+                body: Block {
+                    stmts: vec![Stmt::Break(None)],
+                },
+            }))
+        );
+    }
+
+    #[test]
+    fn while_condition_must_be_parenthesized() {
+        let result = parser("while true { }").parse_statement();
+
+        assert!(matches!(
+            result,
+            Err(ParserError::UnexpectedToken {
+                got: TokenType::Bool(true),
+                ..
+            })
+        ));
+    }
+
+    #[test]
+    fn break_requires_semicolon() {
+        let result = parser("loop { break }").parse_statement();
+
+        assert!(matches!(
+            result,
+            Err(ParserError::UnexpectedToken {
+                got: TokenType::RightBrace,
+                ..
+            })
+        ));
+    }
+
+    #[test]
+    fn for_over_range() {
+        let stmt = parser("for i in 0..10 { print(i); }").parse_statement();
+
+        let Ok(Stmt::For(stmt)) = stmt else {
+            panic!("expected a for loop, got {stmt:?}");
+        };
+        assert_eq!(stmt.binding.name, "i");
+        assert_eq!(sexpr(&stmt.iterable), "(.. 0 10)");
+        assert_eq!(stmt.body.stmts.len(), 1);
+    }
+
+    #[test]
+    fn range_binds_looser_than_arithmetic() {
+        assert_eq!(
+            parse_sexpr("1 + 1..=n * 2"),
+            "(..= (Add 1 1) (Multiply n 2))"
+        );
+    }
+
+    #[test]
+    fn c_style_for_is_rejected() {
+        let result = parser("for (var i = 0; i < 10; i += 1) { }").parse_statement();
+
+        assert!(matches!(
+            result,
+            Err(ParserError::UnexpectedToken {
+                got: TokenType::LeftParen,
+                ..
+            })
+        ));
+    }
+
+    #[test]
+    fn struct_with_two_fields() {
+        let decl = parser("struct Point { x: int, y: float, }")
+            .parse_struct()
+            .expect("struct should parse");
+
+        assert_eq!(
+            decl,
+            StructDecl {
+                name: ident("Point", 7),
+                fields: vec![
+                    ("x".to_owned(), Type::Primitive(PrimitiveType::Int)),
+                    ("y".to_owned(), Type::Primitive(PrimitiveType::Float)),
+                ],
+            }
+        );
+    }
+
+    fn parse_type(source: &str) -> Result<Type> {
+        parser(source).parse_type()
+    }
+
+    fn named(name: &str) -> Type {
+        Type::Named(name.to_owned())
+    }
+
+    #[test]
+    fn primitive_and_named_types() {
+        assert_eq!(parse_type("int"), Ok(Type::Primitive(PrimitiveType::Int)));
+        assert_eq!(parse_type("Point"), Ok(named("Point")));
+    }
+
+    #[test]
+    fn array_types() {
+        assert_eq!(
+            parse_type("[int]"),
+            Ok(Type::Array {
+                element: Box::new(Type::Primitive(PrimitiveType::Int)),
+                len: None,
+            })
+        );
+        assert_eq!(
+            parse_type("[Point; 4]"),
+            Ok(Type::Array {
+                element: Box::new(named("Point")),
+                len: Some(4),
+            })
+        );
+    }
+
+    #[test]
+    fn generic_types() {
+        assert_eq!(
+            parse_type("Vec<int>"),
+            Ok(Type::Generic {
+                name: "Vec".to_owned(),
+                args: vec![Type::Primitive(PrimitiveType::Int)],
+            })
+        );
+        assert_eq!(
+            parse_type("Map<int, [char]>"),
+            Ok(Type::Generic {
+                name: "Map".to_owned(),
+                args: vec![
+                    Type::Primitive(PrimitiveType::Int),
+                    Type::Array {
+                        element: Box::new(Type::Primitive(PrimitiveType::Char)),
+                        len: None,
+                    },
+                ],
+            })
+        );
+        assert_eq!(
+            parse_type("Vec<Vec<Point> >"),
+            Ok(Type::Generic {
+                name: "Vec".to_owned(),
+                args: vec![Type::Generic {
+                    name: "Vec".to_owned(),
+                    args: vec![named("Point")],
+                }],
+            })
+        );
+    }
+
+    #[test]
+    fn unterminated_generic_arguments() {
+        assert_eq!(
+            parse_type("Map<int, char = 1"),
+            Err(ParserError::UnexpectedToken {
+                line: 1,
+                col: 15,
+                expected: "',' or '>'".to_owned(),
+                got: TokenType::Equal,
+            })
+        );
+        assert_eq!(
+            parse_type("Vec<int"),
+            Err(ParserError::UnexpectedEof {
+                line: 1,
+                col: 7,
+                expected: "',' or '>'".to_owned(),
+            })
+        );
+    }
+
+    #[test]
+    fn array_length_must_be_an_integer() {
+        assert!(parse_type("[int; n]").is_err());
+        assert!(parse_type("[int; 4").is_err());
+    }
+
+    #[test]
+    fn empty_struct_item() {
+        let item = parser("struct Unit {}").parse_item();
+
+        assert_eq!(
+            item.map(|item| item.kind),
+            Ok(ItemKind::Struct(StructDecl {
+                name: ident("Unit", 7),
+                fields: Vec::new(),
+            }))
+        );
+    }
+
+    #[test]
+    fn struct_field_requires_type() {
+        let result = parser("struct Point { x, y: int }").parse_struct();
+
+        assert!(matches!(
+            result,
+            Err(ParserError::UnexpectedToken {
+                got: TokenType::Comma,
+                ..
+            })
+        ));
+    }
+
+    fn variant(name: &str, fields: Vec<Type>) -> Variant {
+        Variant {
+            name: name.to_owned(),
+            fields,
+        }
+    }
+
+    #[test]
+    fn c_like_enum() {
+        let item = parser("enum Color { Red, Green, Blue, }").parse_item();
+
+        assert_eq!(
+            item.map(|item| item.kind),
+            Ok(ItemKind::Enum(EnumDecl {
+                name: ident("Color", 5),
+                variants: vec![
+                    variant("Red", vec![]),
+                    variant("Green", vec![]),
+                    variant("Blue", vec![]),
+                ],
+            }))
+        );
+    }
+
+    #[test]
+    fn enum_with_tuple_variant() {
+        let decl = parser("enum Shape { Circle(float), Rect(float, float), Empty }")
+            .parse_enum()
+            .expect("enum should parse");
+
+        assert_eq!(
+            decl.variants,
+            vec![
+                variant("Circle", vec![Type::Primitive(PrimitiveType::Float)]),
+                variant(
+                    "Rect",
+                    vec![
+                        Type::Primitive(PrimitiveType::Float),
+                        Type::Primitive(PrimitiveType::Float),
+                    ],
+                ),
+                variant("Empty", vec![]),
+            ]
+        );
+    }
+
+    fn parse_assign(source: &str) -> Assign {
+        match parser(source).parse_statement() {
+            Ok(Stmt::Expr(Expr::Assign(assign))) => *assign,
+            stmt => panic!("expected an assignment, got {stmt:?}"),
+        }
+    }
+
+    #[test]
+    fn index_assignment() {
+        let assign = parse_assign("arr[0] = 5;");
+
+        assert_eq!(sexpr(&assign.target), "(index arr 0)");
+        assert_eq!(assign.op, None);
+        assert_eq!(assign.value, integer(5));
+    }
+
+    #[test]
+    fn error_points_at_furthest_token_reached() {
+        // Whether this is an assignment or an expression statement is only known after `x[1]`,
+        // the error is still at the missing value rather than back at the start of the statement
+        assert_eq!(
+            parser("x[1] = ;").parse_statement(),
+            Err(ParserError::UnexpectedToken {
+                line: 1,
+                col: 8,
+                expected: "an expression".to_owned(),
+                got: TokenType::Semicolon,
+            })
+        );
+        assert_eq!(
+            parser("x[1 + ] = 2;").parse_statement(),
+            Err(ParserError::UnexpectedToken {
+                line: 1,
+                col: 7,
+                expected: "an expression".to_owned(),
+                got: TokenType::RightBracket,
+            })
+        );
+    }
+
+    #[test]
+    fn compound_index_assignment() {
+        let assign = parse_assign("arr[i] += 2;");
+
+        assert_eq!(sexpr(&assign.target), "(index arr i)");
+        assert_eq!(assign.op, Some(BinaryOp::Add));
+        assert_eq!(assign.value, integer(2));
+    }
+
+    #[test]
+    fn nested_index_and_call() {
+        assert_eq!(
+            parse_sexpr("grid[i][j + 1]"),
+            "(index (index grid i) (Add j 1))"
+        );
+        assert_eq!(parse_sexpr("rows()[0]"), "(index (call rows ) 0)");
+    }
+
+    #[test]
+    fn assignment_is_right_associative() {
+        assert_eq!(parse_sexpr("a = b = 1"), "(= a (= b 1))");
+        assert_eq!(parse_sexpr("a = b += 1 + 2"), "(= a (+= b (Add 1 2)))");
+        assert_eq!(parse_sexpr("r = 0..n"), "(= r (.. 0 n))");
+    }
+
+    #[test]
+    fn compound_assignment_carries_its_operator() {
+        let assign = parse_assign("x += 3;");
+
+        assert_eq!(sexpr(&assign.target), "x");
+        assert_eq!(assign.op, Some(BinaryOp::Add));
+        assert_eq!(assign.value, integer(3));
+    }
+
+    #[test]
+    fn literal_is_not_an_assignment_target() {
+        assert_eq!(
+            parser("1 = 2").parse_expression(),
+            Err(ParserError::InvalidAssignmentTarget { line: 1, col: 1 })
+        );
+        assert_eq!(
+            parser("a = 1 = 2").parse_expression(),
+            Err(ParserError::InvalidAssignmentTarget { line: 1, col: 5 })
+        );
+    }
+
+    #[test]
+    fn field_access_chains_to_the_left() {
+        assert_eq!(parse_sexpr("a.b.c"), "(. (. a b) c)");
+    }
+
+    #[test]
+    fn field_access_with_calls_and_indexing() {
+        assert_eq!(parse_sexpr("a.b()"), "(call (. a b) )");
+        assert_eq!(parse_sexpr("a.b[0].c"), "(. (index (. a b) 0) c)");
+        assert_eq!(parse_sexpr("-a.b * 2"), "(Multiply (Negate (. a b)) 2)");
+    }
+
+    #[test]
+    fn field_access_beside_numbers_and_ranges() {
+        assert_eq!(parse_sexpr("p.x..1.5"), "(.. (. p x) Decimal(1.5))");
+        assert_eq!(parse_sexpr("0..p.len"), "(.. 0 (. p len))");
+        assert_eq!(parse_sexpr("1.max"), "(. 1 max)");
+    }
+
+    #[test]
+    fn field_is_an_assignment_target() {
+        let assign = parse_assign("p.x += 1;");
+
+        assert_eq!(sexpr(&assign.target), "(. p x)");
+        assert_eq!(assign.op, Some(BinaryOp::Add));
+    }
+
+    #[test]
+    fn field_name_must_be_an_identifier() {
+        assert!(matches!(
+            parser("a.1").parse_expression(),
+            Err(ParserError::UnexpectedToken { .. })
+        ));
+    }
+
+    #[test]
+    fn call_is_not_an_assignment_target() {
+        let result = parser("f() = 1;").parse_statement();
+
+        assert_eq!(
+            result,
+            Err(ParserError::InvalidAssignmentTarget { line: 1, col: 1 })
+        );
+    }
+
+    #[test]
+    fn match_with_literal_and_wildcard() {
+        let expr = parser("match x { 1 => a, 'c' => b, _ => c, }").parse_expression();
+
+        let Ok(Expr::Match { scrutinee, arms }) = expr else {
+            panic!("expected a match, got {expr:?}");
+        };
+        assert_eq!(*scrutinee, identifier("x", 6));
+        assert_eq!(
+            arms,
+            vec![
+                (Pattern::Literal(Literal::Integer(1)), identifier("a", 15)),
+                (
+                    Pattern::Literal(Literal::Character('c')),
+                    identifier("b", 25)
+                ),
+                (Pattern::Wildcard, identifier("c", 33)),
+            ]
+        );
+    }
+
+    #[test]
+    fn match_statement_needs_no_semicolon() {
+        let block = parser("{ match x { _ => f() } return; }").parse_block();
+
+        assert!(matches!(
+            block.as_ref().map(|block| block.stmts.as_slice()),
+            Ok([Stmt::Expr(Expr::Match { .. }), Stmt::Return(None)])
+        ));
+    }
+
+    #[test]
+    fn struct_literal_in_var_initializer() {
+        let Ok(Stmt::Var(decl)) = parser("var p = Point { x: 1, y: a + 2, };").parse_statement()
+        else {
+            panic!("expected a var declaration");
+        };
+
+        let init = decl.init.expect("var should be initialized");
+        assert_eq!(sexpr(&init), "(Point (x 1) (y (Add a 2)))");
+    }
+
+    #[test]
+    fn empty_and_nested_struct_literals() {
+        assert_eq!(parse_sexpr("Unit {}"), "(Unit )");
+        assert_eq!(
+            parse_sexpr("Line { from: Point { x: 0 }, to: p }"),
+            "(Line (from (Point (x 0))) (to p))"
+        );
+    }
+
+    #[test]
+    fn brace_after_loop_or_match_head_is_not_a_struct_literal() {
+        let Ok(Stmt::For(stmt)) = parser("for p in points {}").parse_statement() else {
+            panic!("expected a for loop");
+        };
+        assert_eq!(sexpr(&stmt.iterable), "points");
+
+        assert_eq!(
+            parse_sexpr("match p { 1 => Point { x: 1 }, }"),
+            "(match p (Literal(Integer(1)) (Point (x 1))))"
+        );
+
+        // The condition must be parenthesized, so the brace can never continue it
+        let Ok(Stmt::If(stmt)) = parser("if (x) {}").parse_statement() else {
+            panic!("expected an if statement");
+        };
+        assert_eq!(sexpr(&stmt.cond), "x");
+        assert!(stmt.then_block.stmts.is_empty());
+        assert!(parser("if x {}").parse_statement().is_err());
+    }
+
+    #[test]
+    fn parenthesized_struct_literal_in_loop_head() {
+        let Ok(Stmt::For(stmt)) = parser("for p in (Points { n: 3 }) {}").parse_statement() else {
+            panic!("expected a for loop");
+        };
+        assert_eq!(sexpr(&stmt.iterable), "(Points (n 3))");
+
+        let Ok(Stmt::For(stmt)) = parser("for p in f(Points { n: 3 }) {}").parse_statement() else {
+            panic!("expected a for loop");
+        };
+        assert_eq!(sexpr(&stmt.iterable), "(call f (Points (n 3)))");
+    }
+
+    #[test]
+    fn match_arm_requires_fat_arrow() {
+        let result = parser("match x { 1 a }").parse_expression();
+
+        assert!(matches!(
+            result,
+            Err(ParserError::UnexpectedToken {
+                got: TokenType::Identifier(_),
+                ..
+            })
+        ));
+    }
+
+    #[test]
+    fn match_without_arms() {
+        let result = parser("match x {}").parse_expression();
+
+        assert_eq!(result, Err(ParserError::EmptyMatch { line: 1, col: 5 }));
+    }
+
+    #[test]
+    fn negation_binds_tighter_than_binary_operators() {
+        assert_eq!(parse_sexpr("-a + b"), "(Add (Negate a) b)");
+        assert_eq!(parse_sexpr("-a * b"), "(Multiply (Negate a) b)");
+        assert_eq!(parse_sexpr("a - -b"), "(Subtract a (Negate b))");
+    }
+
+    #[test]
+    fn logical_not_binds_tighter_than_binary_operators() {
+        assert_eq!(parse_sexpr("!a && b"), "(And (Not a) b)");
+        assert_eq!(parse_sexpr("!f(x) == y"), "(Equal (Not (call f x)) y)");
+    }
+
+    #[test]
+    fn double_prefix() {
+        assert_eq!(parse_sexpr("!!x"), "(Not (Not x))");
+        assert_eq!(parse_sexpr("-!x"), "(Negate (Not x))");
+    }
+
+    #[test]
+    fn match_arm_block_needs_no_comma() {
+        assert_eq!(
+            parse_sexpr("match x { 1 => { f(); g(); } 2 => { }, _ => a }"),
+            "(match x (Literal(Integer(1)) (block 2)) (Literal(Integer(2)) (block 0)) (Wildcard a))"
+        );
+    }
+
+    #[test]
+    fn match_arm_expression_needs_comma() {
+        let result = parser("match x { 1 => a 2 => b }").parse_expression();
+
+        assert!(matches!(
+            result,
+            Err(ParserError::UnexpectedToken {
+                got: TokenType::Integer(2),
+                ..
+            })
+        ));
+    }
+
+    #[test]
+    fn recovers_from_broken_statements() {
+        let source =
+            "fn main() {\n    var = 1;\n    print(1 +);\n    var ok = 2;\n}\nfn other() {}";
+        let (program, errors) = parser(source).parse_program_recovering();
+
+        assert_eq!(errors.len(), 2, "{errors:?}");
+        assert!(matches!(
+            errors[0],
+            ParserError::UnexpectedToken {
+                line: 2,
+                got: TokenType::Equal,
+                ..
+            }
+        ));
+        assert!(matches!(
+            errors[1],
+            ParserError::UnexpectedToken {
+                line: 3,
+                got: TokenType::RightParen,
+                ..
+            }
+        ));
+
+        assert_eq!(program.items.len(), 2);
+        let ItemKind::Fn(main) = &program.items[0].kind else {
+            panic!("expected a function");
+        };
+        assert_eq!(
+            main.body.stmts,
+            vec![Stmt::Var(VarDecl {
+                name: ident("ok", source.find("ok").unwrap()),
+                ty: None,
+                init: Some(integer(2)),
+            })]
+        );
+    }
+
+    #[test]
+    fn recovery_always_makes_progress() {
+        for source in [
+            "}}}",
+            "fn f() { ) ) }",
+            "fn f() { var",
+            "fn (",
+            "struct { fn g() {}",
+        ] {
+            let (_, errors) = parser(source).parse_program_recovering();
+
+            assert!(!errors.is_empty(), "{source}");
+        }
+    }
+
+    #[test]
+    fn errors_are_not_recovered_by_default() {
+        let result = parser("fn main() { var = 1; var ok = 2; }").parse_program();
+
+        assert!(matches!(
+            result,
+            Err(ParserError::UnexpectedToken {
+                got: TokenType::Equal,
+                ..
+            })
+        ));
+    }
+
+    #[test]
+    fn adjacent_strings_are_an_error_by_default() {
+        assert_eq!(
+            parser(r#"x = "a" "b";"#).parse_statement(),
+            Err(ParserError::AdjacentStrings { line: 1, col: 11 })
+        );
+    }
+
+    #[test]
+    fn adjacent_strings_can_be_concatenated() {
+        let options = ParserOptions {
+            adjacent_strings: AdjacentStrings::Concatenate,
+        };
+
+        assert_eq!(
+            parser_with(r#""a" "b""#, options.clone()).parse_expression(),
+            Ok(Expr::Literal(Literal::String("ab".to_owned())))
+        );
+        assert_eq!(
+            parser_with("\"a\"\n    \"b\" \"c\"", options).parse_expression(),
+            Ok(Expr::Literal(Literal::String("abc".to_owned())))
+        );
+    }
+
+    #[test]
+    fn separated_strings_are_not_adjacent() {
+        assert_eq!(
+            parser(r#"["a", "b"]"#).parse_expression(),
+            Ok(Expr::Array(vec![
+                Expr::Literal(Literal::String("a".to_owned())),
+                Expr::Literal(Literal::String("b".to_owned())),
+            ]))
+        );
+    }
+
+    #[test]
+    fn parse_program_collects_globals_and_declarations() {
+        let program = super::parse_program(test_tokens(
+            "var limit: int = 10; fn f() {} struct S { x: int } enum E { A } var g;",
+        ))
+        .expect("program should parse");
+
+        let kinds: Vec<&ItemKind> = program.items.iter().map(|item| &item.kind).collect();
+        assert!(matches!(
+            kinds[..],
+            [
+                ItemKind::Global(VarDecl {
+                    init: Some(Expr::Literal(Literal::Integer(10))),
+                    ..
+                }),
+                ItemKind::Fn(_),
+                ItemKind::Struct(_),
+                ItemKind::Enum(_),
+                ItemKind::Global(VarDecl { init: None, .. }),
+            ]
+        ));
+    }
+
+    #[test]
+    fn parse_program_reports_every_stray_top_level_token() {
+        let errors = super::parse_program(test_tokens("fn f() {} 1; fn g() {} return;"))
+            .expect_err("stray tokens should not parse");
+
+        assert_eq!(errors.len(), 2);
+        assert!(matches!(
+            &errors[0],
+            ParserError::UnexpectedToken {
+                line: 1,
+                col: 11,
+                ..
+            }
+        ));
+        assert!(matches!(
+            &errors[1],
+            ParserError::UnexpectedToken {
+                line: 1,
+                col: 29,
+                ..
+            }
+        ));
+    }
+
+    #[test]
+    fn labelled_loops() {
+        let stmt = parser("'outer: for i in 0..3 { 'inner: loop { continue 'outer; } break; }")
+            .parse_statement();
+
+        let Ok(Stmt::For(stmt)) = stmt else {
+            panic!("expected a for loop, got {stmt:?}");
+        };
+        assert_eq!(
+            stmt.label,
+            Some(Ident {
+                name: "outer".to_owned(),
+                span: Span::new(0, 6),
+            })
+        );
+        let Stmt::Loop(inner) = &stmt.body.stmts[0] else {
+            panic!("expected a loop, got {:?}", stmt.body.stmts[0]);
+        };
+        assert_eq!(inner.label.as_ref().map(|l| l.name.as_str()), Some("inner"));
+        assert_eq!(
+            inner.body.stmts,
+            [Stmt::Continue(Some(Ident {
+                name: "outer".to_owned(),
+                span: Span::new(48, 54),
+            }))]
+        );
+        assert_eq!(stmt.body.stmts[1], Stmt::Break(None));
+    }
+
+    #[test]
+    fn label_must_be_followed_by_a_loop() {
+        assert!(matches!(
+            parser("'a: if (x) {}").parse_statement(),
+            Err(ParserError::UnexpectedToken {
+                got: TokenType::If,
+                ..
+            })
+        ));
+        assert!(matches!(
+            parser("'a while (x) {}").parse_statement(),
+            Err(ParserError::UnexpectedToken {
+                got: TokenType::While,
+                ..
+            })
+        ));
+    }
+}
@@ -0,0 +1,16 @@
+/// Configuration for a [Parser](super::Parser)
+#[derive(Debug, PartialEq, Eq, Clone, Default)]
+pub struct ParserOptions {
+    pub adjacent_strings: AdjacentStrings,
+}
+
+/// What to do with string literals that directly follow each other, e.g. `"a" "b"`
+#[derive(Debug, PartialEq, Eq, Clone, Copy, Default)]
+pub enum AdjacentStrings {
+    /// Reject them with [ParserError::AdjacentStrings](super::error::ParserError::AdjacentStrings),
+    /// as they are most likely missing an operator between them
+    #[default]
+    Error,
+    /// Join them into a single literal, so `"a" "b"` is the same as `"ab"`
+    Concatenate,
+}
@@ -0,0 +1,133 @@
+use crate::lexer::TokenType;
+
+use super::ast::BinaryOp;
+
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub enum Associativity {
+    Left,
+    Right,
+}
+
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub struct InfixOperator {
+    pub op: BinaryOp,
+    /// Binding power of the operator, a higher value binds tighter
+    pub precedence: u8,
+    pub associativity: Associativity,
+}
+
+/// Every binary operator the parser understands, from loosest to tightest binding.
+///
+/// This is the single source of truth for operator precedence, adding an operator to the
+/// expression parser only needs a new row here.
+#[rustfmt::skip]
+const INFIX_OPERATORS: &[(TokenType, BinaryOp, u8, Associativity)] = &[
+    (TokenType::LogicalOr, BinaryOp::Or, 1, Associativity::Left),
+    (TokenType::LogicalAnd, BinaryOp::And, 2, Associativity::Left),
+    (TokenType::EqualEqual, BinaryOp::Equal, 3, Associativity::Left),
+    (TokenType::BangEqual, BinaryOp::NotEqual, 3, Associativity::Left),
+    (TokenType::Greater, BinaryOp::Greater, 3, Associativity::Left),
+    (TokenType::GreaterEqual, BinaryOp::GreaterEqual, 3, Associativity::Left),
+    (TokenType::Less, BinaryOp::Less, 3, Associativity::Left),
+    (TokenType::LessEqual, BinaryOp::LessEqual, 3, Associativity::Left),
+    (TokenType::Bar, BinaryOp::BitOr, 4, Associativity::Left),
+    (TokenType::Ampersand, BinaryOp::BitAnd, 5, Associativity::Left),
+    (TokenType::Add, BinaryOp::Add, 6, Associativity::Left),
+    (TokenType::Minus, BinaryOp::Subtract, 6, Associativity::Left),
+    (TokenType::Star, BinaryOp::Multiply, 7, Associativity::Left),
+    (TokenType::Slash, BinaryOp::Divide, 7, Associativity::Left),
+    (TokenType::Modulo, BinaryOp::Modulo, 7, Associativity::Left),
+    (TokenType::StarStar, BinaryOp::Power, 8, Associativity::Right),
+];
+
+/// The precedence of a binary operator and the token it is spelled with, the reverse of
+/// [infix_operator]
+pub fn binary_operator(op: BinaryOp) -> (&'static TokenType, u8) {
+    INFIX_OPERATORS
+        .iter()
+        .find(|(_, row_op, ..)| *row_op == op)
+        .map(|(token, _, precedence, _)| (token, *precedence))
+        .expect("every binary operator has a row in the table")
+}
+
+/// Compound assignment operators and the binary operator each applies before assigning
+#[rustfmt::skip]
+const COMPOUND_ASSIGNMENT_OPERATORS: &[(TokenType, BinaryOp)] = &[
+    (TokenType::AddEqual, BinaryOp::Add),
+    (TokenType::MinusEqual, BinaryOp::Subtract),
+    (TokenType::StarEqual, BinaryOp::Multiply),
+    (TokenType::SlashEqual, BinaryOp::Divide),
+    (TokenType::ModuloEqual, BinaryOp::Modulo),
+    (TokenType::StarStarEqual, BinaryOp::Power),
+];
+
+/// Looks up the binary operator a compound assignment token such as `+=` applies
+pub fn compound_assignment_operator(ttype: &TokenType) -> Option<BinaryOp> {
+    COMPOUND_ASSIGNMENT_OPERATORS
+        .iter()
+        .find(|(token, _)| token == ttype)
+        .map(|&(_, op)| op)
+}
+
+/// Looks up the binary operator a token represents along with its precedence and associativity
+pub fn infix_operator(ttype: &TokenType) -> Option<InfixOperator> {
+    INFIX_OPERATORS
+        .iter()
+        .find(|(token, ..)| token == ttype)
+        .map(|&(_, op, precedence, associativity)| InfixOperator {
+            op,
+            precedence,
+            associativity,
+        })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn precedence(ttype: TokenType) -> u8 {
+        infix_operator(&ttype)
+            .expect("token should be an infix operator")
+            .precedence
+    }
+
+    #[test]
+    fn multiplicative_binds_tighter_than_additive() {
+        assert!(precedence(TokenType::Star) > precedence(TokenType::Add));
+        assert!(precedence(TokenType::Slash) > precedence(TokenType::Minus));
+    }
+
+    #[test]
+    fn comparison_binds_looser_than_arithmetic() {
+        assert!(precedence(TokenType::Less) < precedence(TokenType::Add));
+        assert!(precedence(TokenType::EqualEqual) < precedence(TokenType::Ampersand));
+    }
+
+    #[test]
+    fn logical_and_binds_tighter_than_logical_or() {
+        assert!(precedence(TokenType::LogicalAnd) > precedence(TokenType::LogicalOr));
+        assert!(precedence(TokenType::LogicalAnd) < precedence(TokenType::EqualEqual));
+    }
+
+    #[test]
+    fn compound_assignment_applies_its_operator() {
+        assert_eq!(
+            compound_assignment_operator(&TokenType::AddEqual),
+            Some(BinaryOp::Add)
+        );
+        assert_eq!(compound_assignment_operator(&TokenType::Equal), None);
+    }
+
+    #[test]
+    fn binary_operator_is_the_reverse_of_infix_operator() {
+        for (token, op, precedence, _) in INFIX_OPERATORS {
+            assert_eq!(binary_operator(*op), (token, *precedence));
+        }
+    }
+
+    #[test]
+    fn non_operator_has_no_entry() {
+        assert_eq!(infix_operator(&TokenType::Semicolon), None);
+        assert_eq!(infix_operator(&TokenType::Bang), None);
+    }
+}
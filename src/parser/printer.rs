@@ -0,0 +1,580 @@
+use crate::lexer::{decimal_spelling, encode_escapes, tokens_to_source};
+
+use super::{
+    ast::{
+        Attribute, Block, Else, Expr, FnDecl, Ident, If, Item, ItemKind, Literal, Pattern, Program,
+        Stmt, Type, UnaryOp, VarDecl,
+    },
+    precedence::{binary_operator, infix_operator, Associativity},
+};
+
+const INDENT: &str = "    ";
+
+/// Renders a program as canonically formatted source: one statement per line, four space
+/// indentation and single spaces around binary operators.
+///
+/// Parentheses are only added where precedence requires them, and comments are not part of
+/// the AST so they are lost. Parsing the output gives back the same program, apart from spans
+/// (see [Program::strip_spans]).
+pub fn pretty_print(program: &Program) -> String {
+    let mut printer = Printer::default();
+
+    for (i, item) in program.items.iter().enumerate() {
+        if i > 0 {
+            printer.out.push('\n');
+        }
+        printer.item(item);
+    }
+
+    printer.out
+}
+
+#[derive(Default)]
+struct Printer {
+    out: String,
+    depth: usize,
+}
+
+impl Printer {
+    fn line(&mut self, text: &str) {
+        for _ in 0..self.depth {
+            self.out.push_str(INDENT);
+        }
+        self.out.push_str(text);
+        self.out.push('\n');
+    }
+
+    fn item(&mut self, item: &Item) {
+        for args in &item.cfg {
+            self.line(&format!("#[cfg({})]", tokens_to_source(args.tokens())));
+        }
+        for attr in &item.attrs {
+            self.line(&attribute(attr));
+        }
+
+        match &item.kind {
+            ItemKind::Fn(decl) => self.function(decl),
+            ItemKind::Struct(decl) => {
+                let fields: Vec<_> = decl
+                    .fields
+                    .iter()
+                    .map(|(name, ty)| format!("{name}: {}", ty_source(ty)))
+                    .collect();
+                self.braced_list(&format!("struct {}", decl.name.name), &fields);
+            }
+            ItemKind::Enum(decl) => {
+                let variants: Vec<_> = decl
+                    .variants
+                    .iter()
+                    .map(|variant| {
+                        if variant.fields.is_empty() {
+                            return variant.name.clone();
+                        }
+                        let fields: Vec<_> = variant.fields.iter().map(ty_source).collect();
+                        format!("{}({})", variant.name, fields.join(", "))
+                    })
+                    .collect();
+                self.braced_list(&format!("enum {}", decl.name.name), &variants);
+            }
+            ItemKind::Global(decl) => self.var(decl),
+        }
+    }
+
+    fn var(&mut self, decl: &VarDecl) {
+        let mut head = format!("var {}", decl.name.name);
+        if let Some(ty) = &decl.ty {
+            head.push_str(&format!(": {}", ty_source(ty)));
+        }
+        match &decl.init {
+            Some(init) => self.multi_line_expr(&format!("{head} = "), init, ";"),
+            None => self.line(&format!("{head};")),
+        }
+    }
+
+    /// `head { entry, ... }` with one entry per line and trailing commas
+    fn braced_list(&mut self, head: &str, entries: &[String]) {
+        if entries.is_empty() {
+            return self.line(&format!("{head} {{}}"));
+        }
+
+        self.line(&format!("{head} {{"));
+        self.depth += 1;
+        for entry in entries {
+            self.line(&format!("{entry},"));
+        }
+        self.depth -= 1;
+        self.line("}");
+    }
+
+    fn function(&mut self, decl: &FnDecl) {
+        let params: Vec<_> = decl
+            .params
+            .iter()
+            .map(|(name, ty)| format!("{}: {}", name.name, ty_source(ty)))
+            .collect();
+        let mut head = format!("fn {}({})", decl.name.name, params.join(", "));
+        if let Some(ty) = &decl.return_type {
+            head.push_str(&format!(" -> {}", ty_source(ty)));
+        }
+
+        self.block(&head, &decl.body, "");
+    }
+
+    /// `head { stmts }tail`, where the head is already indented on the current line if it is
+    /// empty
+    fn block(&mut self, head: &str, block: &Block, tail: &str) {
+        let open = if head.is_empty() {
+            "{".to_owned()
+        } else {
+            format!("{head} {{")
+        };
+
+        if block.stmts.is_empty() {
+            return self.line(&format!("{open}}}{tail}"));
+        }
+
+        self.block_open(&open, block);
+        self.line(&format!("}}{tail}"));
+    }
+
+    fn stmt(&mut self, stmt: &Stmt) {
+        match stmt {
+            Stmt::Var(decl) => self.var(decl),
+            Stmt::If(stmt) => self.if_chain(stmt),
+            Stmt::While(stmt) => {
+                let head = format!(
+                    "{}while ({})",
+                    label_prefix(&stmt.label),
+                    self.expr(&stmt.cond)
+                );
+                self.block(&head, &stmt.body, "");
+            }
+            Stmt::Loop(stmt) => {
+                let head = format!("{}loop", label_prefix(&stmt.label));
+                self.block(&head, &stmt.body, "");
+            }
+            Stmt::For(stmt) => {
+                let head = format!(
+                    "{}for {} in {}",
+                    label_prefix(&stmt.label),
+                    stmt.binding.name,
+                    self.head_expr(&stmt.iterable)
+                );
+                self.block(&head, &stmt.body, "");
+            }
+            Stmt::Break(None) => self.line("break;"),
+            Stmt::Break(Some(label)) => self.line(&format!("break '{};", label.name)),
+            Stmt::Continue(None) => self.line("continue;"),
+            Stmt::Continue(Some(label)) => self.line(&format!("continue '{};", label.name)),
+            Stmt::Return(None) => self.line("return;"),
+            Stmt::Return(Some(value)) => self.multi_line_expr("return ", value, ";"),
+            Stmt::Block(block) => self.block("", block, ""),
+            // Even a match statement, which may go without a semicolon, gets one, or a following
+            // statement that starts with an operator, `(` or `[` would parse as part of the match
+            // expression
+            Stmt::Expr(expr) => self.multi_line_expr("", expr, ";"),
+        }
+    }
+
+    fn if_chain(&mut self, stmt: &If) {
+        let mut head = format!("if ({})", self.expr(&stmt.cond));
+        let mut stmt = stmt;
+
+        loop {
+            match &stmt.else_block {
+                None => return self.block(&head, &stmt.then_block, ""),
+                Some(else_block) => {
+                    self.block_open(&format!("{head} {{"), &stmt.then_block);
+                    match else_block {
+                        Else::Block(block) => return self.block("} else", block, ""),
+                        Else::If(next) => {
+                            head = format!("}} else if ({})", self.expr(&next.cond));
+                            stmt = next;
+                        }
+                    }
+                }
+            }
+        }
+    }
+
+    /// `open` followed by the statements of `block`, leaving the closing brace to the caller
+    fn block_open(&mut self, open: &str, block: &Block) {
+        self.line(open);
+        self.depth += 1;
+        for stmt in &block.stmts {
+            self.stmt(stmt);
+        }
+        self.depth -= 1;
+    }
+
+    /// Renders an expression, multi-line ones being indented relative to the current line
+    fn expr(&self, expr: &Expr) -> String {
+        match expr {
+            Expr::Literal(literal) => literal_source(literal),
+            Expr::Identifier(ident) => ident.name.clone(),
+            Expr::Array(elements) => {
+                let elements: Vec<_> = elements.iter().map(|element| self.expr(element)).collect();
+                format!("[{}]", elements.join(", "))
+            }
+            Expr::Unary { op, operand } => {
+                let op = match op {
+                    UnaryOp::Negate => "-",
+                    UnaryOp::Not => "!",
+                };
+                format!("{op}{}", self.operand(operand, u8::MAX))
+            }
+            Expr::Binary { left, op, right } => {
+                let (token, precedence) = binary_operator(*op);
+                // An equally binding operand on the side the operator does not group towards
+                // needs parentheses to keep its grouping
+                let (left_precedence, right_precedence) = match infix_operator(token)
+                    .expect("a binary operator's token is an infix operator")
+                    .associativity
+                {
+                    Associativity::Left => (precedence, precedence + 1),
+                    Associativity::Right => (precedence + 1, precedence),
+                };
+                format!(
+                    "{} {token} {}",
+                    self.operand(left, left_precedence),
+                    self.operand(right, right_precedence)
+                )
+            }
+            Expr::Call { callee, args } => {
+                let args: Vec<_> = args.iter().map(|arg| self.expr(arg)).collect();
+                format!("{}({})", self.operand(callee, u8::MAX), args.join(", "))
+            }
+            Expr::Index { target, index } => {
+                format!("{}[{}]", self.operand(target, u8::MAX), self.expr(index))
+            }
+            Expr::Field { base, name } => format!("{}.{name}", self.operand(base, u8::MAX)),
+            Expr::Range {
+                start,
+                end,
+                inclusive,
+            } => {
+                let op = if *inclusive { "..=" } else { ".." };
+                format!("{}{op}{}", self.operand(start, 1), self.operand(end, 1))
+            }
+            Expr::StructLit { name, fields } if fields.is_empty() => format!("{name} {{}}"),
+            Expr::StructLit { name, fields } => {
+                let fields: Vec<_> = fields
+                    .iter()
+                    .map(|(field, value)| format!("{field}: {}", self.expr(value)))
+                    .collect();
+                format!("{name} {{ {} }}", fields.join(", "))
+            }
+            Expr::Assign(assign) => {
+                let op = assign
+                    .op
+                    .map(|op| binary_operator(op).0.to_string())
+                    .unwrap_or_default();
+                format!(
+                    "{} {op}= {}",
+                    self.expr(&assign.target),
+                    self.expr(&assign.value)
+                )
+            }
+            Expr::Block(block) => {
+                let mut printer = Printer::default();
+                printer.block("", block, "");
+                printer.out.trim_end().to_owned()
+            }
+            Expr::Match { scrutinee, arms } => {
+                let mut printer = Printer::default();
+                printer.line(&format!("match {} {{", self.head_expr(scrutinee)));
+                printer.depth += 1;
+                for (pattern, arm) in arms {
+                    let pattern = match pattern {
+                        Pattern::Literal(literal) => literal_source(literal),
+                        Pattern::Wildcard => "_".to_owned(),
+                    };
+                    let comma = if matches!(arm, Expr::Block(_)) {
+                        ""
+                    } else {
+                        ","
+                    };
+                    printer.multi_line_expr(&format!("{pattern} => "), arm, comma);
+                }
+                printer.depth -= 1;
+                printer.line("}");
+                printer.out.trim_end().to_owned()
+            }
+        }
+    }
+
+    /// Prints an expression that is directly followed by a block, parenthesizing it when it has
+    /// a struct literal outside of any delimiters as that would not parse back
+    fn head_expr(&self, expr: &Expr) -> String {
+        if has_bare_struct_literal(expr) {
+            format!("({})", self.expr(expr))
+        } else {
+            self.expr(expr)
+        }
+    }
+
+    /// Prints an expression that may span several lines, e.g. a match, between `head` and
+    /// `tail`
+    fn multi_line_expr(&mut self, head: &str, expr: &Expr, tail: &str) {
+        let text = format!("{head}{}", self.expr(expr));
+        let mut lines = text.lines().peekable();
+
+        while let Some(line) = lines.next() {
+            if lines.peek().is_none() {
+                self.line(&format!("{line}{tail}"));
+            } else {
+                self.line(line);
+            }
+        }
+    }
+
+    /// Renders an operand of an operator binding with `precedence`, parenthesizing it when it
+    /// binds looser than that
+    fn operand(&self, expr: &Expr, precedence: u8) -> String {
+        let binds = match expr {
+            Expr::Binary { op, .. } => binary_operator(*op).1,
+            Expr::Range { .. } | Expr::Assign(_) => 0,
+            _ => u8::MAX,
+        };
+
+        if binds < precedence {
+            format!("({})", self.expr(expr))
+        } else {
+            self.expr(expr)
+        }
+    }
+}
+
+fn attribute(attr: &Attribute) -> String {
+    if attr.args.is_empty() {
+        format!("#[{}]", attr.name)
+    } else {
+        format!("#[{}({})]", attr.name, tokens_to_source(attr.args.tokens()))
+    }
+}
+
+/// `'label: ` for a labelled loop, nothing otherwise
+fn label_prefix(label: &Option<Ident>) -> String {
+    label
+        .as_ref()
+        .map_or_else(String::new, |label| format!("'{}: ", label.name))
+}
+
+/// Whether `expr` has a struct literal that is not enclosed in brackets or parentheses
+fn has_bare_struct_literal(expr: &Expr) -> bool {
+    match expr {
+        Expr::StructLit { .. } => true,
+        Expr::Unary { operand, .. } => has_bare_struct_literal(operand),
+        Expr::Binary { left, right, .. }
+        | Expr::Range {
+            start: left,
+            end: right,
+            ..
+        } => has_bare_struct_literal(left) || has_bare_struct_literal(right),
+        Expr::Assign(assign) => {
+            has_bare_struct_literal(&assign.target) || has_bare_struct_literal(&assign.value)
+        }
+        Expr::Call { callee: target, .. }
+        | Expr::Index { target, .. }
+        | Expr::Field { base: target, .. } => has_bare_struct_literal(target),
+        Expr::Literal(_)
+        | Expr::Identifier(_)
+        | Expr::Array(_)
+        | Expr::Block(_)
+        | Expr::Match { .. } => false,
+    }
+}
+
+fn ty_source(ty: &Type) -> String {
+    match ty {
+        Type::Primitive(primitive) => primitive.to_string(),
+        Type::Named(name) => name.clone(),
+        Type::Array { element, len: None } => format!("[{}]", ty_source(element)),
+        Type::Array {
+            element,
+            len: Some(len),
+        } => format!("[{}; {len}]", ty_source(element)),
+        Type::Generic { name, args } => {
+            let args: Vec<String> = args.iter().map(ty_source).collect();
+            format!("{name}<{}>", args.join(", "))
+        }
+    }
+}
+
+fn literal_source(literal: &Literal) -> String {
+    match literal {
+        Literal::Bool(b) => b.to_string(),
+        Literal::Null => "null".to_owned(),
+        // As `\x` escapes, which the lexer decodes back to the same chars
+        Literal::Character(c) if *c == '\'' || *c == '\\' || c.is_ascii_control() => {
+            format!("'\\x{:02X}'", u32::from(*c))
+        }
+        Literal::Character(c) => format!("'{c}'"),
+        Literal::Decimal(n) => decimal_spelling(*n),
+        Literal::Integer(n) => n.to_string(),
+        // Line breaks are escaped so that indenting the lines of a statement never changes them
+        Literal::String(s) => format!(
+            "\"{}\"",
+            encode_escapes(s)
+                .replace('\n', "\\x0A")
+                .replace('\r', "\\x0D")
+        ),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::parser::{test_program, test_tokens};
+
+    use super::*;
+
+    const MAIN_SAMPLE: &str = "fn math() -> int { var a: int = 3.14159; print(a <= 1); if (a & 1 > 0 && a != 2 || false) { return a; } else { return 1117; } }";
+
+    fn ttypes(source: &str) -> Vec<String> {
+        test_tokens(source)
+            .into_iter()
+            .map(|token| format!("{:?}", token.ttype))
+            .collect()
+    }
+
+    fn assert_structurally_equal(mut left: Program, mut right: Program) {
+        left.strip_spans();
+        right.strip_spans();
+
+        assert_eq!(left, right);
+    }
+
+    #[test]
+    fn main_sample_round_trips() {
+        let printed = pretty_print(&test_program(MAIN_SAMPLE));
+
+        assert_eq!(
+            printed,
+            "\
+fn math() -> int {
+    var a: int = 3.14159;
+    print(a <= 1);
+    if (a & 1 > 0 && a != 2 || false) {
+        return a;
+    } else {
+        return 1117;
+    }
+}
+"
+        );
+        assert_eq!(ttypes(&printed), ttypes(MAIN_SAMPLE));
+        assert_structurally_equal(test_program(&printed), test_program(MAIN_SAMPLE));
+    }
+
+    #[test]
+    fn parenthesizes_only_where_needed() {
+        let source = "fn f() { x = (a + b) * c - (d - e) + -(f + g)[0] + (1..2)[i]; }";
+        let printed = pretty_print(&test_program(source));
+
+        assert_eq!(
+            printed,
+            "fn f() {\n    x = (a + b) * c - (d - e) + -(f + g)[0] + (1..2)[i];\n}\n"
+        );
+    }
+
+    #[test]
+    fn parenthesizes_power_on_its_left() {
+        let source = "fn f() { x = (a ** b) ** c + a ** (b ** c); }";
+        let printed = pretty_print(&test_program(source));
+
+        assert_eq!(
+            printed,
+            "fn f() {\n    x = (a ** b) ** c + a ** b ** c;\n}\n"
+        );
+        assert_structurally_equal(test_program(&printed), test_program(source));
+    }
+
+    #[test]
+    fn literals_round_trip() {
+        let source = r#"fn f() {
+            g(10000000000000000.0, 0.0000001, 2.0, '\x27', '\x0A', '\x5C', 'a');
+            h("\x41", "\x22q\x22", "a\x5Cx41", "\${a}", "\x24{a}");
+        }"#;
+        let printed = pretty_print(&test_program(source));
+
+        assert!(
+            printed
+                .contains(r"g(10000000000000000.0, 0.0000001, 2.0, '\x27', '\x0A', '\x5C', 'a');"),
+            "{printed}"
+        );
+        assert!(
+            printed.contains(r#"h("A", "\x22q\x22", "a\x5Cx41", "\${a}", "\x24{a}");"#),
+            "{printed}"
+        );
+        assert_structurally_equal(test_program(&printed), test_program(source));
+    }
+
+    #[test]
+    fn multi_line_values_are_indented_with_their_statement() {
+        let source = "fn f() { if (a) { var y = match a { 1 => { g(); } _ => 1 }; return match b { _ => [1] }; z = match c { _ => 2 }; var s = \"x\ny\"; } }";
+        let printed = pretty_print(&test_program(source));
+
+        assert_eq!(
+            printed,
+            r#"fn f() {
+    if (a) {
+        var y = match a {
+            1 => {
+                g();
+            }
+            _ => 1,
+        };
+        return match b {
+            _ => [1],
+        };
+        z = match c {
+            _ => 2,
+        };
+        var s = "x\x0Ay";
+    }
+}
+"#
+        );
+        assert_structurally_equal(test_program(&printed), test_program(source));
+    }
+
+    #[test]
+    fn match_statement_keeps_its_semicolon() {
+        let source = "fn f() { match x { _ => 1 }; -1; match x { _ => 2 }; (a); [b]; }";
+        let printed = pretty_print(&test_program(source));
+
+        assert_eq!(
+            printed,
+            "fn f() {\n    match x {\n        _ => 1,\n    };\n    -1;\n    match x {\n        _ => 2,\n    };\n    a;\n    [b];\n}\n"
+        );
+        assert_structurally_equal(test_program(&printed), test_program(source));
+    }
+
+    #[test]
+    fn parenthesizes_struct_literals_before_a_block() {
+        let source = "fn f() { for p in (P { n: 1 })[0] {} match (P { n: 1 } == p) { _ => 0, } }";
+        let printed = pretty_print(&test_program(source));
+
+        assert_eq!(
+            printed,
+            "fn f() {\n    for p in (P { n: 1 }[0]) {}\n    match (P { n: 1 } == p) {\n        _ => 0,\n    };\n}\n"
+        );
+        assert_structurally_equal(test_program(&printed), test_program(source));
+    }
+
+    #[test]
+    fn every_construct_round_trips() {
+        let source = "#[cfg(test)] #[inline] fn f(n: int, p: Point) -> void { \
+            for i in 0..=n { if (i % 2 == 0) { continue; } else if (i > 9) { break; } else { } } \
+            'a: while (true) { 'b: loop { break 'a; continue 'b; } } arr[i] += 'c'; a = b.c[0].d = 1; { return; } \
+            var p = Point { x: 1, y: f(Unit {}) }; \
+            match n { 1 => { g(); } null => null, _ => [1.5, \"s\"], }; \
+        } struct Unit {} enum E { A, B(int, float), } var g: int = 1; \
+        struct C { a: [int], b: [[char; 4]; 2], c: Map<int, [Vec<float>]>, }";
+
+        let printed = pretty_print(&test_program(source));
+
+        assert_eq!(ttypes(&printed), ttypes(source));
+        assert_structurally_equal(test_program(&printed), test_program(source));
+    }
+}
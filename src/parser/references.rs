@@ -0,0 +1,50 @@
+use crate::span::Span;
+
+use super::{
+    ast::{Expr, Program},
+    visit::{walk_expr, walk_program, Visitor},
+};
+
+impl Program {
+    /// Every use of a name in the program along with its span, in source order. Names that are
+    /// declared, such as function, parameter and variable names, are not included.
+    pub fn identifier_refs(&self) -> Vec<(&str, Span)> {
+        let mut collector = RefCollector::default();
+        walk_program(&mut collector, self);
+
+        collector.refs
+    }
+}
+
+#[derive(Default)]
+struct RefCollector<'ast> {
+    refs: Vec<(&'ast str, Span)>,
+}
+
+impl<'ast> Visitor<'ast> for RefCollector<'ast> {
+    fn visit_expr(&mut self, expr: &'ast Expr) {
+        if let Expr::Identifier(ident) = expr {
+            self.refs.push((&ident.name, ident.span));
+        }
+        walk_expr(self, expr);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::parser::test_program;
+
+    #[test]
+    fn collects_uses_but_not_declarations() {
+        let source = "fn area(w: int, h: int) -> int { var a = w * h; if (a > max) { a = max; } return a; }\nstruct Point { x: int }";
+        let program = test_program(source);
+
+        let refs = program.identifier_refs();
+
+        let names: Vec<&str> = refs.iter().map(|(name, _)| *name).collect();
+        assert_eq!(names, ["w", "h", "a", "max", "a", "max", "a"]);
+        for (name, span) in refs {
+            assert_eq!(&source[span.start..span.end], name);
+        }
+    }
+}
@@ -0,0 +1,94 @@
+use crate::span::Span;
+
+use super::{
+    ast::Program,
+    symbols::{Builder, SymbolTable},
+};
+
+#[derive(Debug, PartialEq, Clone)]
+pub enum ResolveError {
+    /// A use of a name that is not declared in any enclosing scope before it
+    Undefined { name: String, span: Span },
+}
+
+impl std::fmt::Display for ResolveError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Undefined { name, .. } => write!(f, "Cannot find '{name}' in this scope."),
+        }
+    }
+}
+
+impl std::error::Error for ResolveError {}
+
+impl Program {
+    /// Checks that every name used in the program is declared, returning the program's
+    /// [SymbolTable] along with every name that is not.
+    ///
+    /// A local variable is only in scope after its declaration, so `var a = a;` does not resolve
+    /// unless an outer `a` exists, while items are in scope everywhere, so a function can call
+    /// another declared after it.
+    pub fn resolve(&self) -> (SymbolTable, Vec<ResolveError>) {
+        let builder = Builder::build(self);
+
+        let errors = builder
+            .undefined
+            .into_iter()
+            .map(|ident| ResolveError::Undefined {
+                name: ident.name.clone(),
+                span: ident.span,
+            })
+            .collect();
+
+        (builder.table, errors)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::parser::test_program;
+
+    use super::*;
+
+    fn resolve_errors(source: &str) -> Vec<ResolveError> {
+        let program = test_program(source);
+
+        program.resolve().1
+    }
+
+    #[test]
+    fn defined_names_resolve() {
+        let source = "var limit = 10;
+            fn main() { var a = 1; for i in 0..limit { a += helper(i); } }
+            fn helper(n: int) -> int { return n * 2; }";
+
+        assert_eq!(resolve_errors(source), []);
+    }
+
+    #[test]
+    fn undefined_variable_is_reported() {
+        let source = "fn f() { var a = 1; { var b = a; } return b; }";
+
+        assert_eq!(
+            resolve_errors(source),
+            [ResolveError::Undefined {
+                name: "b".to_owned(),
+                span: Span::new(42, 43),
+            }]
+        );
+    }
+
+    #[test]
+    fn variable_is_not_in_scope_in_its_own_initializer() {
+        let errors = resolve_errors("fn f() { var a = a + 1; }");
+
+        assert_eq!(
+            errors,
+            [ResolveError::Undefined {
+                name: "a".to_owned(),
+                span: Span::new(17, 18),
+            }]
+        );
+        assert_eq!(errors[0].to_string(), "Cannot find 'a' in this scope.");
+    }
+}
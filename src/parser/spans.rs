@@ -0,0 +1,171 @@
+use crate::{lexer::Token, span::Span};
+
+use super::{
+    ast::{Block, Else, Expr, Ident, ItemKind, Program, Stmt, VarDecl},
+    token_stream::TokenStream,
+};
+
+impl Program {
+    /// Resets every span and token position in the program, so that programs parsed from
+    /// differently formatted source compare equal when they have the same structure
+    pub fn strip_spans(&mut self) {
+        for item in &mut self.items {
+            for args in item.cfg.iter_mut() {
+                strip_tokens(args);
+            }
+            for attr in &mut item.attrs {
+                strip_tokens(&mut attr.args);
+            }
+
+            match &mut item.kind {
+                ItemKind::Fn(decl) => {
+                    decl.name.span = Span::default();
+                    for (param, _) in &mut decl.params {
+                        param.span = Span::default();
+                    }
+                    strip_block(&mut decl.body);
+                }
+                ItemKind::Struct(decl) => decl.name.span = Span::default(),
+                ItemKind::Enum(decl) => decl.name.span = Span::default(),
+                ItemKind::Global(decl) => strip_var(decl),
+            }
+        }
+    }
+}
+
+fn strip_tokens(stream: &mut TokenStream) {
+    let tokens = stream
+        .tokens()
+        .iter()
+        .map(|token| Token {
+            line: 0,
+            col: 0,
+            span: Span::default(),
+            ..token.clone()
+        })
+        .collect();
+
+    *stream = TokenStream::new(tokens);
+}
+
+fn strip_block(block: &mut Block) {
+    block.stmts.iter_mut().for_each(strip_stmt);
+}
+
+fn strip_var(decl: &mut VarDecl) {
+    decl.name.span = Span::default();
+    if let Some(init) = &mut decl.init {
+        strip_expr(init);
+    }
+}
+
+fn strip_label(label: &mut Option<Ident>) {
+    if let Some(label) = label {
+        label.span = Span::default();
+    }
+}
+
+fn strip_stmt(stmt: &mut Stmt) {
+    match stmt {
+        Stmt::Var(decl) => strip_var(decl),
+        Stmt::If(stmt) => {
+            let mut stmt = stmt;
+            loop {
+                strip_expr(&mut stmt.cond);
+                strip_block(&mut stmt.then_block);
+
+                match &mut stmt.else_block {
+                    Some(Else::If(next)) => stmt = next,
+                    Some(Else::Block(block)) => break strip_block(block),
+                    None => break,
+                }
+            }
+        }
+        Stmt::While(stmt) => {
+            strip_label(&mut stmt.label);
+            strip_expr(&mut stmt.cond);
+            strip_block(&mut stmt.body);
+        }
+        Stmt::Loop(stmt) => {
+            strip_label(&mut stmt.label);
+            strip_block(&mut stmt.body);
+        }
+        Stmt::For(stmt) => {
+            strip_label(&mut stmt.label);
+            stmt.binding.span = Span::default();
+            strip_expr(&mut stmt.iterable);
+            strip_block(&mut stmt.body);
+        }
+        Stmt::Break(label) | Stmt::Continue(label) => strip_label(label),
+        Stmt::Return(None) => {}
+        Stmt::Return(Some(expr)) | Stmt::Expr(expr) => strip_expr(expr),
+        Stmt::Block(block) => strip_block(block),
+    }
+}
+
+fn strip_expr(expr: &mut Expr) {
+    match expr {
+        Expr::Literal(_) => {}
+        Expr::Identifier(ident) => ident.span = Span::default(),
+        Expr::Array(elements) => elements.iter_mut().for_each(strip_expr),
+        Expr::Unary { operand, .. } | Expr::Field { base: operand, .. } => strip_expr(operand),
+        Expr::Binary { left, right, .. } => {
+            strip_expr(left);
+            strip_expr(right);
+        }
+        Expr::Call { callee, args } => {
+            strip_expr(callee);
+            args.iter_mut().for_each(strip_expr);
+        }
+        Expr::Index { target, index } => {
+            strip_expr(target);
+            strip_expr(index);
+        }
+        Expr::Range { start, end, .. } => {
+            strip_expr(start);
+            strip_expr(end);
+        }
+        Expr::StructLit { fields, .. } => {
+            fields.iter_mut().for_each(|(_, value)| strip_expr(value))
+        }
+        Expr::Assign(assign) => {
+            strip_expr(&mut assign.target);
+            strip_expr(&mut assign.value);
+        }
+        Expr::Block(block) => strip_block(block),
+        Expr::Match { scrutinee, arms } => {
+            strip_expr(scrutinee);
+            arms.iter_mut().for_each(|(_, arm)| strip_expr(arm));
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::parser::test_program;
+
+    #[test]
+    fn differently_formatted_sources_are_structurally_equal() {
+        let mut compact = test_program("#[cfg(unix)] fn f(a: int) { var b = a; g(b[0] + 1); }");
+        let mut spread = test_program(
+            "// A comment\n#[cfg( unix )]\nfn  f(\n    a: int\n) {\n    var b = a;\n    g(\n        b[0] + 1\n    );\n}\n",
+        );
+        assert_ne!(compact, spread);
+
+        compact.strip_spans();
+        spread.strip_spans();
+
+        assert_eq!(compact, spread);
+    }
+
+    #[test]
+    fn different_structure_is_still_unequal() {
+        let mut left = test_program("fn f() { g(a); }");
+        let mut right = test_program("fn f() { g(b); }");
+
+        left.strip_spans();
+        right.strip_spans();
+
+        assert_ne!(left, right);
+    }
+}
@@ -0,0 +1,315 @@
+//! The names a program declares, grouped into nested scopes.
+//!
+//! Every item is declared in the global scope before anything else, so functions can refer to
+//! items declared after them. Each function gets a scope for its parameters, each block a scope
+//! of its own and each `for` loop a scope for its loop variable.
+
+use crate::span::Span;
+
+use super::{
+    ast::{Block, Expr, Ident, Item, ItemKind, Program, Stmt},
+    visit::{walk_block, walk_expr, walk_item, walk_program, walk_stmt, Visitor},
+};
+
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub enum SymbolKind {
+    Fn,
+    Struct,
+    Enum,
+    Global,
+    Param,
+    Var,
+    /// The variable of a `for` loop
+    LoopVar,
+}
+
+/// A declared name, along with the span of the name where it is declared
+#[derive(Debug, PartialEq, Clone)]
+pub struct Symbol {
+    pub name: String,
+    pub kind: SymbolKind,
+    pub span: Span,
+}
+
+/// Identifies a scope within its [SymbolTable]
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub struct ScopeId(usize);
+
+#[derive(Debug, PartialEq, Clone)]
+pub struct Scope {
+    /// The scope this one is nested in, `None` for the global scope
+    pub parent: Option<ScopeId>,
+    /// Every symbol declared directly in this scope, in source order
+    pub symbols: Vec<Symbol>,
+}
+
+#[derive(Debug, PartialEq, Clone)]
+pub struct SymbolTable {
+    scopes: Vec<Scope>,
+}
+
+impl SymbolTable {
+    pub const GLOBAL: ScopeId = ScopeId(0);
+
+    pub fn scope(&self, id: ScopeId) -> &Scope {
+        &self.scopes[id.0]
+    }
+
+    /// Every scope, the global one first and the rest in the order they open in the source
+    pub fn scopes(&self) -> impl Iterator<Item = (ScopeId, &Scope)> {
+        self.scopes
+            .iter()
+            .enumerate()
+            .map(|(index, scope)| (ScopeId(index), scope))
+    }
+
+    /// Finds the symbol `name` refers to from within `scope`, looking outwards through the
+    /// enclosing scopes. Within a scope the last declaration of a name shadows earlier ones.
+    pub fn lookup(&self, scope: ScopeId, name: &str) -> Option<&Symbol> {
+        let mut scope = Some(scope);
+        while let Some(id) = scope {
+            let current = self.scope(id);
+            if let Some(symbol) = current.symbols.iter().rev().find(|s| s.name == name) {
+                return Some(symbol);
+            }
+            scope = current.parent;
+        }
+
+        None
+    }
+}
+
+/// An item declared with the same name as an earlier item
+#[derive(Debug, PartialEq, Clone)]
+pub struct DuplicateDefinition {
+    pub name: String,
+    pub span: Span,
+    /// Where the name was first declared
+    pub previous: Span,
+}
+
+impl std::fmt::Display for DuplicateDefinition {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "'{}' is defined more than once.", self.name)
+    }
+}
+
+impl std::error::Error for DuplicateDefinition {}
+
+impl Program {
+    /// Collects every name declared in the program into a [SymbolTable]. Items that share a name
+    /// with an earlier item are reported, but are still added to the table.
+    pub fn symbol_table(&self) -> (SymbolTable, Vec<DuplicateDefinition>) {
+        let builder = Builder::build(self);
+        (builder.table, builder.duplicates)
+    }
+}
+
+/// Builds a [SymbolTable] in a single walk over a program, declaring names as they are reached
+pub(super) struct Builder<'ast> {
+    pub(super) table: SymbolTable,
+    current: ScopeId,
+    pub(super) duplicates: Vec<DuplicateDefinition>,
+    /// Uses of names that were not declared in any enclosing scope at the point of use
+    pub(super) undefined: Vec<&'ast Ident>,
+}
+
+impl<'ast> Builder<'ast> {
+    pub(super) fn build(program: &'ast Program) -> Self {
+        let mut builder = Self {
+            table: SymbolTable {
+                scopes: vec![Scope {
+                    parent: None,
+                    symbols: Vec::new(),
+                }],
+            },
+            current: SymbolTable::GLOBAL,
+            duplicates: Vec::new(),
+            undefined: Vec::new(),
+        };
+
+        for item in &program.items {
+            builder.declare_item(item);
+        }
+        walk_program(&mut builder, program);
+
+        builder
+    }
+
+    fn declare_item(&mut self, item: &Item) {
+        let (ident, kind) = match &item.kind {
+            ItemKind::Fn(decl) => (&decl.name, SymbolKind::Fn),
+            ItemKind::Struct(decl) => (&decl.name, SymbolKind::Struct),
+            ItemKind::Enum(decl) => (&decl.name, SymbolKind::Enum),
+            ItemKind::Global(decl) => (&decl.name, SymbolKind::Global),
+        };
+
+        let globals = &self.table.scope(SymbolTable::GLOBAL).symbols;
+        if let Some(previous) = globals.iter().find(|symbol| symbol.name == ident.name) {
+            self.duplicates.push(DuplicateDefinition {
+                name: ident.name.clone(),
+                span: ident.span,
+                previous: previous.span,
+            });
+        }
+
+        self.declare(ident, kind);
+    }
+
+    fn declare(&mut self, ident: &Ident, kind: SymbolKind) {
+        self.table.scopes[self.current.0].symbols.push(Symbol {
+            name: ident.name.clone(),
+            kind,
+            span: ident.span,
+        });
+    }
+
+    /// Runs `f` in a new scope nested in the current one
+    fn in_scope(&mut self, f: impl FnOnce(&mut Self)) {
+        let parent = self.current;
+        self.current = ScopeId(self.table.scopes.len());
+        self.table.scopes.push(Scope {
+            parent: Some(parent),
+            symbols: Vec::new(),
+        });
+
+        f(self);
+        self.current = parent;
+    }
+}
+
+impl<'ast> Visitor<'ast> for Builder<'ast> {
+    fn visit_item(&mut self, item: &'ast Item) {
+        match &item.kind {
+            ItemKind::Fn(decl) => self.in_scope(|builder| {
+                for (param, _) in &decl.params {
+                    builder.declare(param, SymbolKind::Param);
+                }
+                builder.visit_block(&decl.body);
+            }),
+            _ => walk_item(self, item),
+        }
+    }
+
+    fn visit_block(&mut self, block: &'ast Block) {
+        self.in_scope(|builder| walk_block(builder, block));
+    }
+
+    fn visit_stmt(&mut self, stmt: &'ast Stmt) {
+        match stmt {
+            // The initializer is visited first since the variable is not in scope within it
+            Stmt::Var(decl) => {
+                walk_stmt(self, stmt);
+                self.declare(&decl.name, SymbolKind::Var);
+            }
+            Stmt::For(stmt) => {
+                self.visit_expr(&stmt.iterable);
+                self.in_scope(|builder| {
+                    builder.declare(&stmt.binding, SymbolKind::LoopVar);
+                    builder.visit_block(&stmt.body);
+                });
+            }
+            _ => walk_stmt(self, stmt),
+        }
+    }
+
+    fn visit_expr(&mut self, expr: &'ast Expr) {
+        if let Expr::Identifier(ident) = expr {
+            if self.table.lookup(self.current, &ident.name).is_none() {
+                self.undefined.push(ident);
+            }
+        }
+        walk_expr(self, expr);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::parser::test_program;
+
+    use super::*;
+
+    fn span_of(source: &str, needle: &str) -> Span {
+        let start = source.find(needle).expect("needle should be in the source");
+        Span::new(start, start + needle.len())
+    }
+
+    #[test]
+    fn collects_top_level_symbols() {
+        let source = "fn main() {} struct Point { x: int } enum Color { Red } var limit = 3;";
+        let (table, duplicates) = test_program(source).symbol_table();
+
+        assert!(duplicates.is_empty());
+        let globals: Vec<(&str, SymbolKind)> = table
+            .scope(SymbolTable::GLOBAL)
+            .symbols
+            .iter()
+            .map(|symbol| (symbol.name.as_str(), symbol.kind))
+            .collect();
+        assert_eq!(
+            globals,
+            [
+                ("main", SymbolKind::Fn),
+                ("Point", SymbolKind::Struct),
+                ("Color", SymbolKind::Enum),
+                ("limit", SymbolKind::Global),
+            ]
+        );
+        assert_eq!(
+            table.lookup(SymbolTable::GLOBAL, "Point").map(|s| s.span),
+            Some(span_of(source, "Point"))
+        );
+    }
+
+    #[test]
+    fn reports_duplicate_top_level_definitions() {
+        let source = "fn f() {}\nstruct f { }";
+        let (table, duplicates) = test_program(source).symbol_table();
+
+        assert_eq!(
+            duplicates,
+            [DuplicateDefinition {
+                name: "f".to_owned(),
+                span: Span::new(17, 18),
+                previous: Span::new(3, 4),
+            }]
+        );
+        assert_eq!(duplicates[0].to_string(), "'f' is defined more than once.");
+        assert_eq!(table.scope(SymbolTable::GLOBAL).symbols.len(), 2);
+    }
+
+    #[test]
+    fn locals_are_scoped() {
+        let source =
+            "fn f(a: int) { var b = a; for i in 0..b { var c = i; } } fn g() { var a = 1; }";
+        let (table, _) = test_program(source).symbol_table();
+
+        let scope_with = |name: &str| {
+            table
+                .scopes()
+                .find(|(_, scope)| scope.symbols.iter().any(|s| s.name == name))
+                .map(|(id, _)| id)
+                .expect("name should be declared")
+        };
+
+        let loop_body = scope_with("c");
+        assert_eq!(
+            table.lookup(loop_body, "i").map(|s| s.kind),
+            Some(SymbolKind::LoopVar)
+        );
+        assert_eq!(
+            table.lookup(loop_body, "b").map(|s| (s.kind, s.span)),
+            Some((SymbolKind::Var, span_of(source, "b")))
+        );
+        assert_eq!(
+            table.lookup(loop_body, "a").map(|s| s.kind),
+            Some(SymbolKind::Param)
+        );
+        assert_eq!(
+            table.lookup(loop_body, "g").map(|s| s.kind),
+            Some(SymbolKind::Fn)
+        );
+        assert_eq!(table.lookup(SymbolTable::GLOBAL, "b"), None);
+        assert_eq!(table.lookup(scope_with("b"), "c"), None);
+    }
+}
@@ -0,0 +1,255 @@
+use crate::lexer::{Token, TokenType};
+
+use super::{
+    error::{ParserError, Result},
+    unexpected_token,
+};
+
+/// An owned sequence of tokens along with a cursor into it, e.g. the arguments of an attribute
+/// or the tokens being parsed.
+///
+/// An `Eof` token is treated as the end of the stream, so it is never peeked at or consumed.
+#[derive(Debug, PartialEq, Clone, Default)]
+pub struct TokenStream {
+    tokens: Vec<Token>,
+    /// Index of the next token
+    position: usize,
+    /// The furthest `position` reached, which [TokenStream::reset] does not move back
+    furthest: usize,
+}
+
+/// A position in a [TokenStream] to [reset](TokenStream::reset) back to
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub struct Mark(usize);
+
+impl TokenStream {
+    pub fn new(tokens: Vec<Token>) -> Self {
+        Self {
+            tokens,
+            position: 0,
+            furthest: 0,
+        }
+    }
+
+    pub fn tokens(&self) -> &[Token] {
+        &self.tokens
+    }
+
+    pub fn len(&self) -> usize {
+        self.tokens.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.tokens.is_empty()
+    }
+
+    /// The next token, without consuming it
+    pub fn peek(&self) -> Option<&Token> {
+        self.peek_nth(0)
+    }
+
+    /// The token `n` tokens after the next one, without consuming anything
+    pub fn peek_nth(&self, n: usize) -> Option<&Token> {
+        self.tokens
+            .get(self.position + n)
+            .filter(|token| token.ttype != TokenType::Eof)
+    }
+
+    pub fn advance(&mut self) -> Option<&Token> {
+        let token = self
+            .tokens
+            .get(self.position)
+            .filter(|token| token.ttype != TokenType::Eof)?;
+        self.position += 1;
+        self.furthest = self.furthest.max(self.position);
+
+        Some(token)
+    }
+
+    /// Whether the next token is of the given type
+    pub fn check(&self, ttype: &TokenType) -> bool {
+        self.peek().is_some_and(|token| &token.ttype == ttype)
+    }
+
+    /// Consumes the next token if it is of the given type, otherwise reports what was
+    /// `expected` instead
+    pub fn consume(&mut self, ttype: &TokenType, expected: &str) -> Result<&Token> {
+        match self.peek() {
+            Some(token) if &token.ttype == ttype => {
+                Ok(self.advance().expect("next token should exist"))
+            }
+            Some(token) => Err(self.unexpected(token, expected)),
+            None => Err(self.eof_error(expected)),
+        }
+    }
+
+    /// An error for finding `token` when something else was `expected`. After backtracking
+    /// with [TokenStream::reset] it is positioned at the furthest token reached instead, since
+    /// that is where the input stopped making sense.
+    pub fn unexpected(&self, token: &Token, expected: &str) -> ParserError {
+        if self.position >= self.furthest {
+            return unexpected_token(token, expected);
+        }
+
+        match self
+            .tokens
+            .get(self.furthest)
+            .filter(|token| token.ttype != TokenType::Eof)
+        {
+            Some(furthest) => unexpected_token(furthest, expected),
+            None => self.eof_error(expected),
+        }
+    }
+
+    /// The current position, which can be returned to with [TokenStream::reset]
+    pub fn mark(&self) -> Mark {
+        Mark(self.position)
+    }
+
+    /// Moves back, or forward, to a position taken with [TokenStream::mark]
+    pub fn reset(&mut self, mark: Mark) {
+        self.position = mark.0;
+    }
+
+    /// An error for running out of tokens while expecting something, positioned at the last
+    /// token
+    pub fn eof_error(&self, expected: &str) -> ParserError {
+        let (line, col) = self
+            .tokens
+            .last()
+            .map(|token| (token.line, token.col))
+            .unwrap_or((1, 0));
+
+        ParserError::UnexpectedEof {
+            line,
+            col,
+            expected: expected.to_owned(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::parser::test_tokens;
+
+    use super::*;
+
+    fn stream(source: &str) -> TokenStream {
+        let tokens = test_tokens(source);
+
+        TokenStream::new(tokens)
+    }
+
+    fn ttype(token: Option<&Token>) -> Option<&TokenType> {
+        token.map(|token| &token.ttype)
+    }
+
+    #[test]
+    fn peeking_does_not_consume() {
+        let mut tokens = stream("a + b");
+
+        assert_eq!(
+            ttype(tokens.peek()),
+            Some(&TokenType::Identifier("a".to_owned()))
+        );
+        assert_eq!(ttype(tokens.peek_nth(1)), Some(&TokenType::Add));
+        assert_eq!(ttype(tokens.peek_nth(3)), None);
+        assert!(tokens.check(&TokenType::Identifier("a".to_owned())));
+
+        tokens.advance();
+        assert_eq!(ttype(tokens.peek()), Some(&TokenType::Add));
+    }
+
+    #[test]
+    fn consumes_an_expected_token() {
+        let mut tokens = stream("( )");
+
+        let token = tokens
+            .consume(&TokenType::LeftParen, "'('")
+            .expect("'(' should be consumed");
+        assert_eq!((token.line, token.col), (1, 1));
+        assert!(tokens.check(&TokenType::RightParen));
+    }
+
+    #[test]
+    fn consuming_a_different_token_is_an_error() {
+        let mut tokens = stream("x");
+
+        assert_eq!(
+            tokens.consume(&TokenType::LeftParen, "'('"),
+            Err(ParserError::UnexpectedToken {
+                line: 1,
+                col: 1,
+                expected: "'('".to_owned(),
+                got: TokenType::Identifier("x".to_owned()),
+            })
+        );
+        // Nothing is consumed on error
+        assert!(tokens.check(&TokenType::Identifier("x".to_owned())));
+
+        tokens.advance();
+        assert!(matches!(
+            tokens.consume(&TokenType::LeftParen, "'('"),
+            Err(ParserError::UnexpectedEof { .. })
+        ));
+    }
+
+    #[test]
+    fn reset_backtracks_to_a_mark() {
+        let mut tokens = stream("a b c");
+
+        tokens.advance();
+        let mark = tokens.mark();
+        tokens.advance();
+        tokens.advance();
+        assert_eq!(tokens.peek(), None);
+
+        tokens.reset(mark);
+        assert_eq!(
+            ttype(tokens.advance()),
+            Some(&TokenType::Identifier("b".to_owned()))
+        );
+    }
+
+    #[test]
+    fn errors_after_reset_point_at_the_furthest_token_reached() {
+        let mut tokens = stream("a b c d");
+
+        let mark = tokens.mark();
+        tokens.advance();
+        tokens.advance();
+        tokens.reset(mark);
+        assert_eq!(
+            tokens.consume(&TokenType::Comma, "','"),
+            Err(ParserError::UnexpectedToken {
+                line: 1,
+                col: 5,
+                expected: "','".to_owned(),
+                got: TokenType::Identifier("c".to_owned()),
+            })
+        );
+
+        // Once the furthest token is reached again errors are positioned as usual
+        tokens.advance();
+        tokens.advance();
+        tokens.advance();
+        assert_eq!(
+            tokens.consume(&TokenType::Comma, "','"),
+            Err(ParserError::UnexpectedToken {
+                line: 1,
+                col: 7,
+                expected: "','".to_owned(),
+                got: TokenType::Identifier("d".to_owned()),
+            })
+        );
+
+        // Having reached the end, errors after backtracking are at the end too
+        tokens.advance();
+        tokens.reset(mark);
+        tokens.advance();
+        assert!(matches!(
+            tokens.consume(&TokenType::Comma, "','"),
+            Err(ParserError::UnexpectedEof { .. })
+        ));
+    }
+}
@@ -0,0 +1,222 @@
+//! Type checking, which so far only covers variables annotated with a primitive type and
+//! initialized with a literal, e.g. `var x: bool = 5;`.
+
+use crate::{lexer::PrimitiveType, span::Span};
+
+use super::{
+    ast::{Expr, Item, ItemKind, Literal, Program, Stmt, Type, UnaryOp, VarDecl},
+    visit::{walk_item, walk_program, walk_stmt, Visitor},
+};
+
+#[derive(Debug, PartialEq, Clone)]
+pub enum TypeError {
+    /// A literal of a different kind than the annotated type, positioned at the variable's name
+    Mismatch {
+        expected: PrimitiveType,
+        /// The kind of literal found, e.g. `integer`
+        found: &'static str,
+        span: Span,
+    },
+    /// An integer literal that does not fit in the annotated integer type, positioned at the
+    /// variable's name
+    OutOfRange { ty: PrimitiveType, span: Span },
+}
+
+impl std::fmt::Display for TypeError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Mismatch {
+                expected, found, ..
+            } => write!(
+                f,
+                "Mismatched types, expected '{expected}', found {found} literal."
+            ),
+            Self::OutOfRange { ty, .. } => {
+                write!(f, "Integer literal is out of range for '{ty}'.")
+            }
+        }
+    }
+}
+
+impl std::error::Error for TypeError {}
+
+impl Program {
+    /// Checks that every variable annotated with a primitive type and initialized with a literal
+    /// is given a literal of that type
+    pub fn type_check(&self) -> Vec<TypeError> {
+        let mut checker = Checker::default();
+        walk_program(&mut checker, self);
+
+        checker.errors
+    }
+}
+
+#[derive(Default)]
+struct Checker {
+    errors: Vec<TypeError>,
+}
+
+impl Checker {
+    fn check_var(&mut self, decl: &VarDecl) {
+        let (Some(Type::Primitive(expected)), Some(init)) = (&decl.ty, &decl.init) else {
+            return;
+        };
+        let span = decl.name.span;
+
+        // A negative number is parsed as the negation of a literal
+        let (literal, negative) = match init {
+            Expr::Literal(literal) => (literal, false),
+            Expr::Unary {
+                op: UnaryOp::Negate,
+                operand,
+            } => match operand.as_ref() {
+                Expr::Literal(literal @ (Literal::Integer(_) | Literal::Decimal(_))) => {
+                    (literal, true)
+                }
+                _ => return,
+            },
+            _ => return,
+        };
+
+        let found = match literal {
+            Literal::Integer(n) if is_integer(expected) => {
+                if !fits(expected, *n, negative) {
+                    self.errors.push(TypeError::OutOfRange {
+                        ty: expected.clone(),
+                        span,
+                    });
+                }
+                return;
+            }
+            Literal::Integer(_) => "integer",
+            Literal::Decimal(_) if *expected == PrimitiveType::Float => return,
+            Literal::Decimal(_) => "decimal",
+            Literal::Bool(_) if *expected == PrimitiveType::Bool => return,
+            Literal::Bool(_) => "boolean",
+            Literal::Character(_) if *expected == PrimitiveType::Char => return,
+            Literal::Character(_) => "character",
+            Literal::String(_) => "string",
+            // There are no nullable types yet, so what `null` may be assigned to is left open
+            Literal::Null => return,
+        };
+
+        self.errors.push(TypeError::Mismatch {
+            expected: expected.clone(),
+            found,
+            span,
+        });
+    }
+}
+
+fn is_integer(ty: &PrimitiveType) -> bool {
+    matches!(
+        ty,
+        PrimitiveType::Int | PrimitiveType::UInt | PrimitiveType::Isize | PrimitiveType::Usize
+    )
+}
+
+/// Whether the integer of magnitude `n`, negated if `negative`, fits in the integer type `ty`
+fn fits(ty: &PrimitiveType, n: u64, negative: bool) -> bool {
+    let n = u128::from(n);
+    let (min, max) = match ty {
+        PrimitiveType::Int => (i64::MIN.unsigned_abs().into(), i64::MAX as u128),
+        PrimitiveType::Isize => (isize::MIN.unsigned_abs() as u128, isize::MAX as u128),
+        PrimitiveType::UInt => (0, u64::MAX.into()),
+        PrimitiveType::Usize => (0, usize::MAX as u128),
+        _ => return false,
+    };
+
+    if negative {
+        n <= min
+    } else {
+        n <= max
+    }
+}
+
+impl<'ast> Visitor<'ast> for Checker {
+    fn visit_item(&mut self, item: &'ast Item) {
+        if let ItemKind::Global(decl) = &item.kind {
+            self.check_var(decl);
+        }
+        walk_item(self, item);
+    }
+
+    fn visit_stmt(&mut self, stmt: &'ast Stmt) {
+        if let Stmt::Var(decl) = stmt {
+            self.check_var(decl);
+        }
+        walk_stmt(self, stmt);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::parser::test_program;
+
+    use super::*;
+
+    fn type_errors(source: &str) -> Vec<TypeError> {
+        let program = test_program(source);
+
+        program.type_check()
+    }
+
+    #[test]
+    fn matching_literals_are_accepted() {
+        let source = "var a: int = 5; var b: float = 1.5; var c: bool = true; var d: char = 'd';
+            fn f() { var e: uint = 18446744073709551615; var g: int = -9223372036854775808; }
+            var h: Point = 5; var i: int = a; var j: int = null;";
+
+        assert_eq!(type_errors(source), []);
+    }
+
+    #[test]
+    fn mismatched_literals_are_reported() {
+        let errors =
+            type_errors("var x: bool = 5;\nfn f() { var y: int = \"s\"; var z: float = 1; }");
+
+        assert_eq!(
+            errors,
+            [
+                TypeError::Mismatch {
+                    expected: PrimitiveType::Bool,
+                    found: "integer",
+                    span: Span::new(4, 5),
+                },
+                TypeError::Mismatch {
+                    expected: PrimitiveType::Int,
+                    found: "string",
+                    span: Span::new(30, 31),
+                },
+                TypeError::Mismatch {
+                    expected: PrimitiveType::Float,
+                    found: "integer",
+                    span: Span::new(48, 49),
+                },
+            ]
+        );
+        assert_eq!(
+            errors[0].to_string(),
+            "Mismatched types, expected 'bool', found integer literal."
+        );
+    }
+
+    #[test]
+    fn integers_out_of_range_are_reported() {
+        let errors = type_errors("var a: uint = -1; var b: int = 9223372036854775808;");
+
+        assert_eq!(
+            errors,
+            [
+                TypeError::OutOfRange {
+                    ty: PrimitiveType::UInt,
+                    span: Span::new(4, 5),
+                },
+                TypeError::OutOfRange {
+                    ty: PrimitiveType::Int,
+                    span: Span::new(22, 23),
+                },
+            ]
+        );
+    }
+}
@@ -0,0 +1,180 @@
+//! Read-only traversal of the AST.
+//!
+//! A [Visitor] overrides the `visit_*` methods for the nodes it is interested in and calls the
+//! matching `walk_*` function from them to keep recursing into the children. The default methods
+//! only recurse, so a visitor that overrides nothing visits every node.
+
+use super::ast::{Block, Else, Expr, Item, ItemKind, Pattern, Program, Stmt};
+
+pub trait Visitor<'ast> {
+    fn visit_item(&mut self, item: &'ast Item) {
+        walk_item(self, item);
+    }
+
+    fn visit_block(&mut self, block: &'ast Block) {
+        walk_block(self, block);
+    }
+
+    fn visit_stmt(&mut self, stmt: &'ast Stmt) {
+        walk_stmt(self, stmt);
+    }
+
+    fn visit_expr(&mut self, expr: &'ast Expr) {
+        walk_expr(self, expr);
+    }
+
+    fn visit_pattern(&mut self, _pattern: &'ast Pattern) {}
+}
+
+pub fn walk_program<'ast, V: Visitor<'ast> + ?Sized>(visitor: &mut V, program: &'ast Program) {
+    for item in &program.items {
+        visitor.visit_item(item);
+    }
+}
+
+pub fn walk_item<'ast, V: Visitor<'ast> + ?Sized>(visitor: &mut V, item: &'ast Item) {
+    match &item.kind {
+        ItemKind::Fn(decl) => visitor.visit_block(&decl.body),
+        ItemKind::Global(decl) => {
+            if let Some(init) = &decl.init {
+                visitor.visit_expr(init);
+            }
+        }
+        ItemKind::Struct(_) | ItemKind::Enum(_) => {}
+    }
+}
+
+pub fn walk_block<'ast, V: Visitor<'ast> + ?Sized>(visitor: &mut V, block: &'ast Block) {
+    for stmt in &block.stmts {
+        visitor.visit_stmt(stmt);
+    }
+}
+
+pub fn walk_stmt<'ast, V: Visitor<'ast> + ?Sized>(visitor: &mut V, stmt: &'ast Stmt) {
+    match stmt {
+        Stmt::Var(decl) => {
+            if let Some(init) = &decl.init {
+                visitor.visit_expr(init);
+            }
+        }
+        Stmt::If(stmt) => {
+            let mut stmt = stmt;
+            loop {
+                visitor.visit_expr(&stmt.cond);
+                visitor.visit_block(&stmt.then_block);
+
+                match &stmt.else_block {
+                    Some(Else::If(next)) => stmt = next,
+                    Some(Else::Block(block)) => break visitor.visit_block(block),
+                    None => break,
+                }
+            }
+        }
+        Stmt::While(stmt) => {
+            visitor.visit_expr(&stmt.cond);
+            visitor.visit_block(&stmt.body);
+        }
+        Stmt::Loop(stmt) => visitor.visit_block(&stmt.body),
+        Stmt::For(stmt) => {
+            visitor.visit_expr(&stmt.iterable);
+            visitor.visit_block(&stmt.body);
+        }
+        Stmt::Break(_) | Stmt::Continue(_) | Stmt::Return(None) => {}
+        Stmt::Return(Some(expr)) | Stmt::Expr(expr) => visitor.visit_expr(expr),
+        Stmt::Block(block) => visitor.visit_block(block),
+    }
+}
+
+pub fn walk_expr<'ast, V: Visitor<'ast> + ?Sized>(visitor: &mut V, expr: &'ast Expr) {
+    match expr {
+        Expr::Literal(_) | Expr::Identifier(_) => {}
+        Expr::Array(elements) => {
+            for element in elements {
+                visitor.visit_expr(element);
+            }
+        }
+        Expr::Unary { operand, .. } | Expr::Field { base: operand, .. } => {
+            visitor.visit_expr(operand)
+        }
+        Expr::Binary { left, right, .. } => {
+            visitor.visit_expr(left);
+            visitor.visit_expr(right);
+        }
+        Expr::Call { callee, args } => {
+            visitor.visit_expr(callee);
+            for arg in args {
+                visitor.visit_expr(arg);
+            }
+        }
+        Expr::Index { target, index } => {
+            visitor.visit_expr(target);
+            visitor.visit_expr(index);
+        }
+        Expr::Range { start, end, .. } => {
+            visitor.visit_expr(start);
+            visitor.visit_expr(end);
+        }
+        Expr::StructLit { fields, .. } => {
+            for (_, value) in fields {
+                visitor.visit_expr(value);
+            }
+        }
+        Expr::Assign(assign) => {
+            visitor.visit_expr(&assign.target);
+            visitor.visit_expr(&assign.value);
+        }
+        Expr::Block(block) => visitor.visit_block(block),
+        Expr::Match { scrutinee, arms } => {
+            visitor.visit_expr(scrutinee);
+            for (pattern, arm) in arms {
+                visitor.visit_pattern(pattern);
+                visitor.visit_expr(arm);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::parser::{ast::Literal, test_program};
+
+    use super::*;
+
+    #[derive(Default)]
+    struct IntegerCounter {
+        count: usize,
+    }
+
+    impl Visitor<'_> for IntegerCounter {
+        fn visit_expr(&mut self, expr: &Expr) {
+            if let Expr::Literal(Literal::Integer(_)) = expr {
+                self.count += 1;
+            }
+            walk_expr(self, expr);
+        }
+
+        fn visit_pattern(&mut self, pattern: &Pattern) {
+            if let Pattern::Literal(Literal::Integer(_)) = pattern {
+                self.count += 1;
+            }
+        }
+    }
+
+    #[test]
+    fn counts_integer_literals() {
+        let program = test_program(
+            "fn f(n: int) -> int {
+                var a = [1, 2.5, 3];
+                if (n > 4) { return 5; } else if (n < 6) { a[7] = -8; } else { g(9); }
+                for i in 10..n { while (true) { loop { break; } } }
+                return match n { 11 => 12, _ => { 13; } };
+            }
+            struct S { x: int }",
+        );
+
+        let mut counter = IntegerCounter::default();
+        walk_program(&mut counter, &program);
+
+        assert_eq!(counter.count, 12);
+    }
+}
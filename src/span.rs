@@ -0,0 +1,69 @@
+/// A range of byte offsets into the source, `end` being exclusive
+#[derive(Debug, PartialEq, Eq, Clone, Copy, Hash, Default)]
+pub struct Span {
+    pub start: usize,
+    pub end: usize,
+}
+
+impl Span {
+    pub fn new(start: usize, end: usize) -> Self {
+        Self { start, end }
+    }
+
+    /// The smallest span covering both spans along with anything between them, e.g. the span of
+    /// a binary expression from the spans of its operands
+    pub fn merge(self, other: Span) -> Span {
+        Span::new(self.start.min(other.start), self.end.max(other.end))
+    }
+
+    /// The number of bytes the span covers
+    pub fn len(&self) -> usize {
+        self.end - self.start
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.start == self.end
+    }
+
+    /// Whether the byte at `offset` is within the span
+    pub fn contains(&self, offset: usize) -> bool {
+        (self.start..self.end).contains(&offset)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn merge_covers_the_gap_between_spans() {
+        let left = Span::new(2, 5);
+        let right = Span::new(9, 12);
+
+        assert_eq!(left.merge(right), Span::new(2, 12));
+        assert_eq!(right.merge(left), Span::new(2, 12));
+    }
+
+    #[test]
+    fn merge_adjacent_and_overlapping() {
+        assert_eq!(Span::new(0, 3).merge(Span::new(3, 6)), Span::new(0, 6));
+        assert_eq!(Span::new(0, 4).merge(Span::new(2, 6)), Span::new(0, 6));
+        assert_eq!(Span::new(0, 6).merge(Span::new(2, 4)), Span::new(0, 6));
+    }
+
+    #[test]
+    fn len_and_contains() {
+        let span = Span::new(4, 7);
+
+        assert_eq!(span.len(), 3);
+        assert!(!span.is_empty());
+        assert!(!span.contains(3));
+        assert!(span.contains(4));
+        assert!(span.contains(6));
+        assert!(!span.contains(7));
+
+        let empty = Span::new(4, 4);
+        assert!(empty.is_empty());
+        assert!(!empty.contains(4));
+    }
+}
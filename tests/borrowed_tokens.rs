@@ -0,0 +1,66 @@
+use std::{
+    alloc::{GlobalAlloc, Layout, System},
+    borrow::Cow,
+    sync::atomic::{AtomicUsize, Ordering},
+};
+
+use sklang::lexer::{Lexer, TokenType};
+
+/// Counts every allocation made by the test binary
+struct CountingAllocator;
+
+static ALLOCATIONS: AtomicUsize = AtomicUsize::new(0);
+
+unsafe impl GlobalAlloc for CountingAllocator {
+    unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+        ALLOCATIONS.fetch_add(1, Ordering::Relaxed);
+        System.alloc(layout)
+    }
+
+    unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
+        System.dealloc(ptr, layout)
+    }
+}
+
+#[global_allocator]
+static GLOBAL: CountingAllocator = CountingAllocator;
+
+fn allocations_during<T>(f: impl FnOnce() -> T) -> (T, usize) {
+    let before = ALLOCATIONS.load(Ordering::Relaxed);
+    let result = f();
+    let after = ALLOCATIONS.load(Ordering::Relaxed);
+
+    (result, after - before)
+}
+
+// Kept as a single test so no other test allocates concurrently while counting
+#[test]
+fn borrowed_tokens_do_not_allocate_per_identifier() {
+    const IDENTIFIERS: usize = 10_000;
+
+    let source: String = (0..IDENTIFIERS).map(|i| format!("ident_{i} ")).collect();
+
+    let mut lexer = Lexer::new(&source);
+    let (tokens, borrowed_allocations) = allocations_during(|| lexer.tokenize_borrowed());
+
+    assert_eq!(tokens.len(), IDENTIFIERS);
+    for (i, token) in tokens.iter().enumerate() {
+        let token = token.as_ref().expect("identifier should lex");
+        match &token.ttype {
+            TokenType::Identifier(Cow::Borrowed(identifier)) => {
+                assert_eq!(*identifier, format!("ident_{i}"))
+            }
+            ttype => panic!("expected a borrowed identifier, got {ttype:?}"),
+        }
+    }
+
+    // Only the growth of the returned `Vec` allocates, which is logarithmic in the token count
+    assert!(
+        borrowed_allocations < 32,
+        "tokenize_borrowed made {borrowed_allocations} allocations"
+    );
+
+    let mut lexer = Lexer::new(&source);
+    let (_, owned_allocations) = allocations_during(|| lexer.tokenize());
+    assert!(owned_allocations >= IDENTIFIERS);
+}
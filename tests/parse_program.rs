@@ -0,0 +1,25 @@
+use sklang::{
+    lexer::{Lexer, Token},
+    parser::{ast::ItemKind, parse_program},
+};
+
+#[test]
+fn parses_the_sample_program() {
+    let source = "fn math() -> int { var a: int = 3.14159; print(a <= 1); if (a & 1 > 0 && a != 2 || false) { return a; } else { return 1117; } }";
+    // Integration tests only see the public API, so the crate's `cfg(test)` helpers are out of
+    // reach here
+    let tokens: Vec<Token> = Lexer::new(source)
+        .tokenize()
+        .into_iter()
+        .collect::<Result<_, _>>()
+        .expect("sample should lex");
+
+    let program = parse_program(tokens).expect("sample should parse");
+
+    assert_eq!(program.items.len(), 1);
+    let ItemKind::Fn(decl) = &program.items[0].kind else {
+        panic!("expected a function, got {:?}", program.items[0].kind);
+    };
+    assert_eq!(decl.name.name, "math");
+    assert_eq!(decl.body.stmts.len(), 3);
+}
@@ -0,0 +1,81 @@
+use std::{
+    io::Write,
+    process::{Command, Output, Stdio},
+};
+
+/// Runs the `sklang` binary with `source` on stdin
+fn run(source: &str) -> Output {
+    let mut child = Command::new(env!("CARGO_BIN_EXE_sklang"))
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()
+        .expect("binary should start");
+
+    child
+        .stdin
+        .take()
+        .expect("stdin should be piped")
+        .write_all(source.as_bytes())
+        .expect("source should be written");
+
+    child.wait_with_output().expect("binary should finish")
+}
+
+#[test]
+fn valid_program_is_printed() {
+    let output = run("fn main() { print(1 + 2); }");
+
+    assert!(output.status.success());
+    assert!(output.stderr.is_empty());
+    assert_eq!(
+        String::from_utf8(output.stdout).unwrap(),
+        "fn main() {\n    print(1 + 2);\n}\n"
+    );
+}
+
+#[test]
+fn lexer_errors_are_reported_with_their_line() {
+    let output = run("fn main() {\n    var s = \"never closed;\n}");
+
+    assert!(!output.status.success());
+    assert!(output.stdout.is_empty());
+    assert_eq!(
+        String::from_utf8(output.stderr).unwrap(),
+        "error: [line 2: 13] Unterminated string literal\n  |\n2 |     var s = \"never closed;\n  |             ^\n"
+    );
+}
+
+#[test]
+fn every_parser_error_is_reported() {
+    let output = run("fn f() { var = 1; }\nfn g() { return 1 }\n");
+
+    assert!(!output.status.success());
+    let stderr = String::from_utf8(output.stderr).unwrap();
+    let errors: Vec<&str> = stderr
+        .lines()
+        .filter(|line| line.starts_with("error: "))
+        .collect();
+    assert_eq!(
+        errors,
+        [
+            "error: [line 1: 14] Unexpected token '=', expected a variable name.",
+            "error: [line 2: 19] Unexpected token '}', expected ';'.",
+        ]
+    );
+}
+
+#[test]
+fn source_is_read_from_a_file_argument() {
+    let path = std::env::temp_dir().join(format!("sklang-pipeline-{}.sk", std::process::id()));
+    std::fs::write(&path, "var x = 1;").unwrap();
+
+    let output = Command::new(env!("CARGO_BIN_EXE_sklang"))
+        .arg(&path)
+        .output()
+        .expect("binary should run");
+    std::fs::remove_file(&path).unwrap();
+
+    assert!(output.status.success());
+    assert_eq!(String::from_utf8(output.stdout).unwrap(), "var x = 1;\n");
+}